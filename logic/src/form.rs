@@ -1,8 +1,11 @@
 use std::fmt::{self, Write, Display, Formatter};
 
 use consts::*;
+use parse::{self, ParseError};
+use tok::{Token, Tokens};
 pub use self::Formula::*;
 
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Formula {
 	Var(char),
 	Not(Box<Formula>),
@@ -10,17 +13,204 @@ pub enum Formula {
 	Or(Box<Formula>, Box<Formula>),
 	Implies(Box<Formula>, Box<Formula>),
 	Iff(Box<Formula>, Box<Formula>),
+	/// Falsum, the formula that is always false.
+	Bottom,
 }
 impl Formula {
-	//pub fn new(s: &str) -> Box<Formula> {
-	//	
-	//}
+	/// Parses a formula from a string, accepting any of the notations in `consts`.
+	pub fn new(s: &str) -> Result<Formula, ParseError> {
+		Formula::parse(&Tokens::from_str(s))
+	}
+	
+	/// Parses a formula from a slice of simplified tokens.
+	pub fn parse(toks: &[Token]) -> Result<Formula, ParseError> {
+		parse::parse(toks)
+	}
+	
+	/// Evaluates the formula, looking up the truth value of each variable with `v`.
+	pub fn eval<F: Fn(char) -> bool>(&self, v: &F) -> bool {
+		match self {
+			&Var(c)                => v(c),
+			&Not(ref p)            => !p.eval(v),
+			&And(ref p, ref q)     => p.eval(v) && q.eval(v),
+			&Or(ref p, ref q)      => p.eval(v) || q.eval(v),
+			&Implies(ref p, ref q) => !p.eval(v) || q.eval(v),
+			&Iff(ref p, ref q)     => p.eval(v) == q.eval(v),
+			&Bottom                => false,
+		}
+	}
+	
+	/// Adds the variables of the formula to `vars`, keeping it sorted and free of duplicates.
+	pub fn collect_vars(&self, vars: &mut Vec<char>) {
+		match self {
+			&Var(c) => if let Err(i) = vars.binary_search(&c) {
+				vars.insert(i, c);
+			},
+			&Not(ref p) => p.collect_vars(vars),
+			&And(ref p, ref q) | &Or(ref p, ref q) | &Implies(ref p, ref q) | &Iff(ref p, ref q) => {
+				p.collect_vars(vars);
+				q.collect_vars(vars);
+			},
+			&Bottom => {},
+		}
+	}
+	
+	/// Returns the sorted list of variables that occur in the formula.
+	pub fn vars(&self) -> Vec<char> {
+		let mut vars = Vec::new();
+		self.collect_vars(&mut vars);
+		vars
+	}
+	
+	/// Returns the formula and each of its subformulas, outermost first, from left to right.
+	pub fn subformulas(&self) -> Vec<&Formula> {
+		let mut subs = vec![self];
+		match self {
+			&Not(ref p) => subs.extend(p.subformulas()),
+			&And(ref p, ref q) | &Or(ref p, ref q) | &Implies(ref p, ref q) | &Iff(ref p, ref q) => {
+				subs.extend(p.subformulas());
+				subs.extend(q.subformulas());
+			},
+			&Var(_) | &Bottom => {},
+		}
+		subs
+	}
+	
+	/// Matches `self` as a pattern against `inst`, treating the variables of `self` as standing for any formula.
+	/// `subst` holds the substitution built up so far, and is extended with any new variables. Returns true
+	/// if `inst` is a substitution instance of `self` consistent with `subst`.
+	pub fn matches(&self, inst: &Formula, subst: &mut Vec<(char, Formula)>) -> bool {
+		match (self, inst) {
+			(&Var(c), inst) => {
+				if let Some(&(_, ref f)) = subst.iter().find(|&&(v, _)| v == c) {
+					return f == inst;
+				}
+				subst.push((c, inst.clone()));
+				true
+			},
+			(&Not(ref p), &Not(ref ip)) => p.matches(ip, subst),
+			(&And(ref p, ref q), &And(ref ip, ref iq)) | (&Or(ref p, ref q), &Or(ref ip, ref iq))
+			| (&Implies(ref p, ref q), &Implies(ref ip, ref iq)) | (&Iff(ref p, ref q), &Iff(ref ip, ref iq)) =>
+				p.matches(ip, subst) && q.matches(iq, subst),
+			(&Bottom, &Bottom) => true,
+			_ => false,
+		}
+	}
+	
+	/// Returns true if `other` is `self` with its variables renamed, each to a different variable, so that
+	/// `P→Q` renames to `R→P` but not to `R→R`. `renaming` holds the pairs of variables matched so far, and is
+	/// extended with any new ones.
+	pub fn renames(&self, other: &Formula, renaming: &mut Vec<(char, char)>) -> bool {
+		match (self, other) {
+			(&Var(a), &Var(b)) => match renaming.iter().find(|&&(x, y)| x == a || y == b) {
+				Some(&(x, y)) => x == a && y == b,
+				None => {
+					renaming.push((a, b));
+					true
+				},
+			},
+			(&Not(ref p), &Not(ref op)) => p.renames(op, renaming),
+			(&And(ref p, ref q), &And(ref op, ref oq)) | (&Or(ref p, ref q), &Or(ref op, ref oq))
+			| (&Implies(ref p, ref q), &Implies(ref op, ref oq)) | (&Iff(ref p, ref q), &Iff(ref op, ref oq)) =>
+				p.renames(op, renaming) && q.renames(oq, renaming),
+			(&Bottom, &Bottom) => true,
+			_ => false,
+		}
+	}
+	
+	/// Replaces every variable in `subst` with the formula it maps to.
+	pub fn substitute(&self, subst: &[(char, Formula)]) -> Formula {
+		let sub = |p: &Formula| Box::new(p.substitute(subst));
+		match self {
+			&Var(c) => match subst.iter().find(|&&(v, _)| v == c) {
+				Some(&(_, ref f)) => f.clone(),
+				None => Var(c),
+			},
+			&Not(ref p)            => Not(sub(p)),
+			&And(ref p, ref q)     => And(sub(p), sub(q)),
+			&Or(ref p, ref q)      => Or(sub(p), sub(q)),
+			&Implies(ref p, ref q) => Implies(sub(p), sub(q)),
+			&Iff(ref p, ref q)     => Iff(sub(p), sub(q)),
+			&Bottom                => Bottom,
+		}
+	}
+	
+	/// Evaluates the formula in every row of its truth table, in the order given by `valuations`.
+	pub fn truth_table(&self) -> Vec<(Vec<(char, bool)>, bool)> {
+		valuations(&self.vars()).into_iter().map(|row| {
+			let value = self.eval(&|c: char| row.iter().find(|&&(v, _)| v == c).map(|&(_, b)| b).unwrap_or(false));
+			(row, value)
+		}).collect()
+	}
+	
+	/// Searches the truth table for a valuation that makes the formula true.
+	pub fn satisfy(&self) -> Option<Vec<(char, bool)>> {
+		self.truth_table().into_iter().find(|&(_, value)| value).map(|(row, _)| row)
+	}
+	
+	/// Converts the formula into negation normal form: an equivalent formula made of `∧` and `∨`, with `¬` only
+	/// in front of variables and `⊥`.
+	pub fn nnf(&self) -> Formula {
+		let b = |p: Formula| Box::new(p);
+		let not = |p: &Formula| Not(Box::new(p.clone()));
+		match self {
+			&Var(_) | &Bottom => self.clone(),
+			&And(ref p, ref q) => And(b(p.nnf()), b(q.nnf())),
+			&Or(ref p, ref q) => Or(b(p.nnf()), b(q.nnf())),
+			&Implies(ref p, ref q) => Or(b(not(p).nnf()), b(q.nnf())),
+			&Iff(ref p, ref q) => And(b(Or(b(not(p)), q.clone()).nnf()), b(Or(b(not(q)), p.clone()).nnf())),
+			&Not(ref p) => match **p {
+				Var(_) | Bottom => self.clone(),
+				Not(ref p) => p.nnf(),
+				And(ref p, ref q) => Or(b(not(p).nnf()), b(not(q).nnf())),
+				Or(ref p, ref q) => And(b(not(p).nnf()), b(not(q).nnf())),
+				Implies(ref p, ref q) => And(b(p.nnf()), b(not(q).nnf())),
+				Iff(ref p, ref q) => Or(b(And(p.clone(), b(not(q))).nnf()), b(And(b(not(p)), q.clone()).nnf())),
+			},
+		}
+	}
+	
+	/// Converts the formula into conjunctive normal form: an equivalent conjunction of disjunctions of variables,
+	/// `⊥` and their negations. The result can be exponentially longer than the formula.
+	pub fn cnf(&self) -> Formula {
+		fn cnf(f: Formula) -> Formula {
+			match f {
+				And(p, q) => And(Box::new(cnf(*p)), Box::new(cnf(*q))),
+				Or(p, q) => distribute(cnf(*p), cnf(*q)),
+				f => f,
+			}
+		}
+		/// The disjunction of two formulas in CNF, in CNF.
+		fn distribute(p: Formula, q: Formula) -> Formula {
+			match (p, q) {
+				(And(a, b), q) => And(Box::new(distribute(*a, q.clone())), Box::new(distribute(*b, q))),
+				(p, And(a, b)) => And(Box::new(distribute(p.clone(), *a)), Box::new(distribute(p, *b))),
+				(p, q) => Or(Box::new(p), Box::new(q)),
+			}
+		}
+		cnf(self.nnf())
+	}
+	
+	/// Converts the formula into simplified tokens, as they would be typed into the editor.
+	pub fn to_tokens(&self) -> Tokens {
+		Tokens::from_str(&format!("{:#}", self))
+	}
 }
+
+/// Every valuation of `vars`, in the order of the rows of a truth table: all true first, all false last.
+pub fn valuations(vars: &[char]) -> Vec<Vec<(char, bool)>> {
+	(0..1u64 << vars.len()).map(|n| {
+		vars.iter().enumerate().map(|(i, &c)| (c, n & (1 << (vars.len() - 1 - i)) == 0)).collect()
+	}).collect()
+}
+
 impl Display for Formula {
 	fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
 		fn brackets(p: &Formula, f: &mut Formatter) -> Result<(), fmt::Error> {
 			if let &Var(_) = p {
 				try!(p.fmt(f));
+			} else if let &Bottom = p {
+				try!(p.fmt(f));
 			} else if let &Not(box Var(_)) = p {
 				try!(p.fmt(f));
 			} else {
@@ -31,17 +221,22 @@ impl Display for Formula {
 			Ok(())
 		}
 		
-		let (not, and, or, implies, iff) = if !f.alternate() {
-			(STR_NOT, STR_AND, STR_OR, STR_IF, STR_IFF)
+		let (not, and, or, implies, iff, bot) = if !f.alternate() {
+			(STR_NOT, STR_AND, STR_OR, STR_IF, STR_IFF, STR_BOT)
 		} else {
-			(STR_PRETTY_NOT, STR_PRETTY_AND, STR_PRETTY_OR, STR_PRETTY_IF, STR_PRETTY_IFF)
+			(STR_PRETTY_NOT, STR_PRETTY_AND, STR_PRETTY_OR, STR_PRETTY_IF, STR_PRETTY_IFF, STR_PRETTY_BOT)
 		};
 		
 		match self {
 			&Var(ref c) => try!(f.write_char(*c)),
 			&Not(ref p) => {
 				try!(f.write_str(not));
-				try!(brackets(&p, f));
+				if let &Not(_) = &**p {
+					// Stacked negations don't need brackets, i.e. `¬¬P`
+					try!(p.fmt(f));
+				} else {
+					try!(brackets(&p, f));
+				}
 			},
 			&And(ref p, ref q) => {
 				try!(brackets(&p, f));
@@ -62,7 +257,8 @@ impl Display for Formula {
 				try!(brackets(&p, f));
 				try!(f.write_str(iff));
 				try!(brackets(&q, f));
-			}
+			},
+			&Bottom => try!(f.write_str(bot)),
 		}
 		
 		Ok(())
@@ -92,4 +288,56 @@ mod tests {
 		test(&*implies(not(and(var('P'), var('Q'))), or(not(var('P')), not(var('Q')))), "(!(P^Q))->(!Pv!Q)", "(¬(P∧Q))→(¬P∨¬Q)");
 		test(&*iff(or(var('P'), var('Q')), or(var('Q'), var('P'))), "(PvQ)<->(QvP)", "(P∨Q)⇔(Q∨P)");
 	}
+	
+	#[test]
+	fn test_formula_parse() {
+		fn test(s: &str, expected: &str) {
+			let f = Formula::new(s).unwrap();
+			let f_sp = format!("{:#}", f);
+			println!("[test_formula_parse] {} -> {} == {} ? ... {}", s, f_sp, expected, yn(f_sp == expected));
+			assert_eq!(f_sp, expected);
+			assert_eq!(Formula::new(&f_sp).unwrap(), f);
+		}
+		
+		test("P", "P");
+		test("~~P", "¬¬P");
+		test("P & Q v R", "(P∧Q)∨R");
+		test("P -> Q -> R", "P→(Q→R)");
+		test("!(P^Q) <-> ~Pv~Q", "(¬(P∧Q))↔(¬P∨¬Q)");
+		test("(P -> _|_) -> ¬P", "(P→⊥)→¬P");
+		assert!(Formula::new("").is_err());
+		assert!(Formula::new("P &").is_err());
+		assert!(Formula::new("(P").is_err());
+		assert!(Formula::new("P Q").is_err());
+	}
+	
+	#[test]
+	fn test_formula_matches() {
+		let pat = Formula::new("(A -> B) & ~B").unwrap();
+		let inst = Formula::new("((P v Q) -> R) & ~R").unwrap();
+		let mut subst = Vec::new();
+		assert!(pat.matches(&inst, &mut subst));
+		assert_eq!(pat.substitute(&subst), inst);
+		assert!(!pat.matches(&Formula::new("(P -> Q) & ~R").unwrap(), &mut Vec::new()));
+		assert!(!pat.matches(&Formula::new("(P -> Q) v ~Q").unwrap(), &mut Vec::new()));
+	}
+	
+	#[test]
+	fn test_formula_normal_forms() {
+		fn test(s: &str, expected: &str) {
+			let f = Formula::new(s).unwrap();
+			let cnf = f.cnf();
+			println!("[test_formula_normal_forms] {} -> {:#}", s, cnf);
+			assert_eq!(format!("{:#}", cnf), expected);
+			assert_eq!(f.truth_table(), cnf.truth_table());
+		}
+		
+		test("P -> Q", "¬P∨Q");
+		test("~(P & Q)", "¬P∨¬Q");
+		test("P v (Q & R)", "(P∨Q)∧(P∨R)");
+		test("P <-> Q", "(¬P∨Q)∧(¬Q∨P)");
+		test("~(P -> (Q v ~R))", "P∧(¬Q∧R)");
+		assert_eq!(Formula::new("P & ~P").unwrap().satisfy(), None);
+		assert_eq!(Formula::new("P & ~Q").unwrap().satisfy(), Some(vec![('P', true), ('Q', false)]));
+	}
 }
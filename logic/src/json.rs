@@ -0,0 +1,623 @@
+//! A small JSON reader and writer, and the JSON schema used to exchange formulas, sequents and proofs with
+//! other programs.
+//!
+//! Values are wrapped in a versioned document by `to_document`, e.g. `{"version": 1, "formula": ...}`, so that
+//! the schema can change without breaking older readers silently. Formulas are written as their syntax tree:
+//!
+//! ```text
+//! {"op": "var", "name": "P"}
+//! {"op": "bottom"}
+//! {"op": "not", "arg": F}
+//! {"op": "and" | "or" | "implies" | "iff", "left": F, "right": F}
+//! ```
+//!
+//! Token strings, which may not be valid formulas, are arrays of strings of characters and operator tokens,
+//! which are written as `{"op": "not" | "and" | "or" | "implies" | "iff" | "bottom"}`, e.g.
+//! `["P", {"op": "implies"}, "(Q"]`.
+//! Sequents are `{"premises": [F, ...], "conclusion": F}`.
+use std::fmt::{self, Write, Display, Formatter};
+
+use form::Formula;
+use seq::Sequent;
+use tok::{Token, Tokens};
+
+/// The version of the schema written by `to_document`. Documents with a later version are rejected.
+pub const VERSION: u64 = 1;
+
+/// A JSON value. Objects keep their keys in order, so that output is stable.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+	Null,
+	Bool(bool),
+	Number(f64),
+	String(String),
+	Array(Vec<Json>),
+	Object(Vec<(String, Json)>),
+}
+
+/// An error produced when reading JSON.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum JsonError {
+	/// The text isn't valid JSON. Contains the byte offset of the problem.
+	Syntax(usize),
+	/// An object is missing a field. Contains the field name.
+	Missing(&'static str),
+	/// A value is of the wrong type or has an unknown value. Contains a description of what was expected.
+	Expected(&'static str),
+	/// The document is from a later version of the schema. Contains the version.
+	Version(u64),
+}
+impl Display for JsonError {
+	fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+		match self {
+			&JsonError::Syntax(pos)    => write!(f, "invalid JSON at byte {}", pos),
+			&JsonError::Missing(field) => write!(f, "missing field `{}`", field),
+			&JsonError::Expected(what) => write!(f, "expected {}", what),
+			&JsonError::Version(v)     => write!(f, "unsupported version {}, expected at most {}", v, VERSION),
+		}
+	}
+}
+
+/// A type that can be written as JSON.
+pub trait ToJson {
+	fn to_json(&self) -> Json;
+}
+
+/// A type that can be read from JSON.
+pub trait FromJson: Sized {
+	fn from_json(j: &Json) -> Result<Self, JsonError>;
+}
+
+impl Json {
+	/// Parses a JSON value. Whitespace around it is allowed.
+	pub fn parse(s: &str) -> Result<Json, JsonError> {
+		let mut r = Reader {
+			s: s,
+			pos: 0,
+		};
+		let j = try!(r.value());
+		r.skip_space();
+		if r.pos != s.len() {
+			return Err(JsonError::Syntax(r.pos));
+		}
+		Ok(j)
+	}
+	
+	/// Looks up `key` in an object. Returns `None` if `self` isn't an object or has no such key.
+	pub fn get(&self, key: &str) -> Option<&Json> {
+		match self {
+			&Json::Object(ref fields) => fields.iter().find(|&&(ref k, _)| k == key).map(|&(_, ref v)| v),
+			_ => None,
+		}
+	}
+	
+	/// Looks up `key` in an object, failing if it isn't there.
+	pub fn field(&self, key: &'static str) -> Result<&Json, JsonError> {
+		self.get(key).ok_or(JsonError::Missing(key))
+	}
+	
+	pub fn as_str(&self) -> Result<&str, JsonError> {
+		match self {
+			&Json::String(ref s) => Ok(s),
+			_ => Err(JsonError::Expected("a string")),
+		}
+	}
+	
+	pub fn as_bool(&self) -> Result<bool, JsonError> {
+		match self {
+			&Json::Bool(b) => Ok(b),
+			_ => Err(JsonError::Expected("a boolean")),
+		}
+	}
+	
+	/// Gets a non-negative whole number.
+	pub fn as_u64(&self) -> Result<u64, JsonError> {
+		match self {
+			&Json::Number(n) if n >= 0.0 && n.fract() == 0.0 && n < 9007199254740992.0 => Ok(n as u64),
+			_ => Err(JsonError::Expected("a non-negative integer")),
+		}
+	}
+	
+	pub fn as_array(&self) -> Result<&[Json], JsonError> {
+		match self {
+			&Json::Array(ref v) => Ok(v),
+			_ => Err(JsonError::Expected("an array")),
+		}
+	}
+	
+	/// Reads an array of values.
+	pub fn to_vec<T: FromJson>(&self) -> Result<Vec<T>, JsonError> {
+		let mut v = Vec::new();
+		for j in try!(self.as_array()) {
+			v.push(try!(T::from_json(j)));
+		}
+		Ok(v)
+	}
+}
+
+/// Writes `s` as a JSON string.
+fn write_str(f: &mut Formatter, s: &str) -> Result<(), fmt::Error> {
+	try!(f.write_char('"'));
+	for c in s.chars() {
+		match c {
+			'"'  => try!(f.write_str("\\\"")),
+			'\\' => try!(f.write_str("\\\\")),
+			'\n' => try!(f.write_str("\\n")),
+			'\t' => try!(f.write_str("\\t")),
+			'\r' => try!(f.write_str("\\r")),
+			c if (c as u32) < 0x20 => try!(write!(f, "\\u{:04x}", c as u32)),
+			c => try!(f.write_char(c)),
+		}
+	}
+	f.write_char('"')
+}
+
+impl Json {
+	fn fmt_indent(&self, f: &mut Formatter, indent: usize) -> Result<(), fmt::Error> {
+		// Only arrays and objects of arrays and objects are spread over several lines when pretty printing
+		let pretty = f.alternate() && match self {
+			&Json::Array(ref v) => v.iter().any(|j| j.is_container()),
+			&Json::Object(ref v) => v.iter().any(|&(_, ref j)| j.is_container()),
+			_ => false,
+		};
+		let newline = |f: &mut Formatter, indent: usize| -> Result<(), fmt::Error> {
+			if pretty {
+				try!(f.write_char('\n'));
+				for _ in 0..indent {
+					try!(f.write_str("  "));
+				}
+			}
+			Ok(())
+		};
+		let sep = if f.alternate() { ", " } else { "," };
+		match self {
+			&Json::Null => f.write_str("null"),
+			&Json::Bool(b) => write!(f, "{}", b),
+			// JSON has no NaN or infinity, so they are written as null like JavaScript does
+			&Json::Number(n) => if !n.is_finite() {
+				f.write_str("null")
+			} else if n.fract() == 0.0 && n.abs() < 1e15 {
+				write!(f, "{}", n as i64)
+			} else {
+				write!(f, "{}", n)
+			},
+			&Json::String(ref s) => write_str(f, s),
+			&Json::Array(ref v) => {
+				try!(f.write_char('['));
+				for (i, j) in v.iter().enumerate() {
+					if i != 0 {
+						try!(f.write_str(if pretty { "," } else { sep }));
+					}
+					try!(newline(f, indent + 1));
+					try!(j.fmt_indent(f, indent + 1));
+				}
+				if !v.is_empty() {
+					try!(newline(f, indent));
+				}
+				f.write_char(']')
+			},
+			&Json::Object(ref v) => {
+				try!(f.write_char('{'));
+				for (i, &(ref k, ref j)) in v.iter().enumerate() {
+					if i != 0 {
+						try!(f.write_str(if pretty { "," } else { sep }));
+					}
+					try!(newline(f, indent + 1));
+					try!(write_str(f, k));
+					try!(f.write_str(if f.alternate() { ": " } else { ":" }));
+					try!(j.fmt_indent(f, indent + 1));
+				}
+				if !v.is_empty() {
+					try!(newline(f, indent));
+				}
+				f.write_char('}')
+			},
+		}
+	}
+	
+	fn is_container(&self) -> bool {
+		match self {
+			&Json::Array(_) | &Json::Object(_) => true,
+			_ => false,
+		}
+	}
+}
+/// Writes compact JSON, or indented JSON with `{:#}`.
+impl Display for Json {
+	fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+		self.fmt_indent(f, 0)
+	}
+}
+
+/// Recursive descent JSON parser over a string.
+struct Reader<'a> {
+	s: &'a str,
+	pos: usize,
+}
+impl<'a> Reader<'a> {
+	fn err<T>(&self) -> Result<T, JsonError> {
+		Err(JsonError::Syntax(self.pos))
+	}
+	
+	fn peek(&self) -> Option<char> {
+		self.s[self.pos..].chars().next()
+	}
+	
+	fn skip_space(&mut self) {
+		while let Some(c) = self.peek() {
+			if c != ' ' && c != '\t' && c != '\n' && c != '\r' {
+				break;
+			}
+			self.pos += 1;
+		}
+	}
+	
+	/// Skips whitespace and then `c`, failing if it isn't there.
+	fn expect(&mut self, c: char) -> Result<(), JsonError> {
+		self.skip_space();
+		if self.peek() != Some(c) {
+			return self.err();
+		}
+		self.pos += 1;
+		Ok(())
+	}
+	
+	/// Skips `word` if it comes next.
+	fn eat(&mut self, word: &str) -> bool {
+		if self.s[self.pos..].starts_with(word) {
+			self.pos += word.len();
+			true
+		} else {
+			false
+		}
+	}
+	
+	fn value(&mut self) -> Result<Json, JsonError> {
+		self.skip_space();
+		match self.peek() {
+			Some('{') => self.object(),
+			Some('[') => self.array(),
+			Some('"') => self.string().map(Json::String),
+			Some(c) if c == '-' || c.is_digit(10) => self.number(),
+			_ if self.eat("null")  => Ok(Json::Null),
+			_ if self.eat("true")  => Ok(Json::Bool(true)),
+			_ if self.eat("false") => Ok(Json::Bool(false)),
+			_ => self.err(),
+		}
+	}
+	
+	fn object(&mut self) -> Result<Json, JsonError> {
+		try!(self.expect('{'));
+		let mut fields = Vec::new();
+		self.skip_space();
+		if self.eat("}") {
+			return Ok(Json::Object(fields));
+		}
+		loop {
+			self.skip_space();
+			let key = try!(self.string());
+			try!(self.expect(':'));
+			let value = try!(self.value());
+			fields.push((key, value));
+			self.skip_space();
+			if self.eat("}") {
+				return Ok(Json::Object(fields));
+			}
+			try!(self.expect(','));
+		}
+	}
+	
+	fn array(&mut self) -> Result<Json, JsonError> {
+		try!(self.expect('['));
+		let mut v = Vec::new();
+		self.skip_space();
+		if self.eat("]") {
+			return Ok(Json::Array(v));
+		}
+		loop {
+			v.push(try!(self.value()));
+			self.skip_space();
+			if self.eat("]") {
+				return Ok(Json::Array(v));
+			}
+			try!(self.expect(','));
+		}
+	}
+	
+	/// Reads the four hex digits of a `\u` escape.
+	fn hex4(&mut self) -> Result<u32, JsonError> {
+		let end = self.pos + 4;
+		// `from_str_radix` would also take a sign
+		if end > self.s.len() || !self.s.as_bytes()[self.pos..end].iter().all(|&b| (b as char).is_digit(16)) {
+			return self.err();
+		}
+		match u32::from_str_radix(&self.s[self.pos..end], 16).ok() {
+			Some(n) => {
+				self.pos += 4;
+				Ok(n)
+			},
+			None => self.err(),
+		}
+	}
+	
+	fn string(&mut self) -> Result<String, JsonError> {
+		if !self.eat("\"") {
+			return self.err();
+		}
+		let mut s = String::new();
+		loop {
+			let c = match self.peek() {
+				Some(c) => c,
+				None => return self.err(),
+			};
+			self.pos += c.len_utf8();
+			match c {
+				'"' => return Ok(s),
+				'\\' => {
+					let e = match self.peek() {
+						Some(e) => e,
+						None => return self.err(),
+					};
+					self.pos += 1;
+					s.push(match e {
+						'"' => '"',
+						'\\' => '\\',
+						'/' => '/',
+						'b' => '\u{8}',
+						'f' => '\u{c}',
+						'n' => '\n',
+						'r' => '\r',
+						't' => '\t',
+						'u' => {
+							let mut n = try!(self.hex4());
+							// Characters outside the BMP are written as a surrogate pair
+							if n >= 0xD800 && n < 0xDC00 && self.eat("\\u") {
+								let lo = try!(self.hex4());
+								if lo < 0xDC00 || lo >= 0xE000 {
+									return self.err();
+								}
+								n = 0x10000 + ((n - 0xD800) << 10) + (lo - 0xDC00);
+							}
+							match ::std::char::from_u32(n) {
+								Some(c) => c,
+								None => return self.err(),
+							}
+						},
+						_ => return self.err(),
+					});
+				},
+				c if (c as u32) < 0x20 => return self.err(),
+				c => s.push(c),
+			}
+		}
+	}
+	
+	/// Skips over digits, returning how many there were.
+	fn digits(&mut self) -> usize {
+		let start = self.pos;
+		while self.peek().map_or(false, |c| c.is_digit(10)) {
+			self.pos += 1;
+		}
+		self.pos - start
+	}
+	
+	/// Reads a number of the form `-?(0|[1-9][0-9]*)(\.[0-9]+)?([eE][+-]?[0-9]+)?`.
+	fn number(&mut self) -> Result<Json, JsonError> {
+		let start = self.pos;
+		self.eat("-");
+		let int = self.pos;
+		let n = self.digits();
+		if n == 0 || (n > 1 && self.s[int..].starts_with('0')) {
+			return Err(JsonError::Syntax(start));
+		}
+		if self.eat(".") && self.digits() == 0 {
+			return self.err();
+		}
+		if self.eat("e") || self.eat("E") {
+			if !self.eat("+") {
+				self.eat("-");
+			}
+			if self.digits() == 0 {
+				return self.err();
+			}
+		}
+		match self.s[start..self.pos].parse() {
+			Ok(n) => Ok(Json::Number(n)),
+			Err(_) => Err(JsonError::Syntax(start)),
+		}
+	}
+}
+
+/// Wraps `value` in a versioned document, as `{"version": VERSION, kind: value}`.
+pub fn to_document<T: ToJson>(kind: &str, value: &T) -> Json {
+	Json::Object(vec![
+		("version".to_string(), Json::Number(VERSION as f64)),
+		(kind.to_string(), value.to_json()),
+	])
+}
+
+/// Reads the value of type `kind` from a versioned document made by `to_document`.
+pub fn from_document<T: FromJson>(kind: &'static str, doc: &Json) -> Result<T, JsonError> {
+	let version = try!(try!(doc.field("version")).as_u64());
+	if version > VERSION {
+		return Err(JsonError::Version(version));
+	}
+	T::from_json(try!(doc.field(kind)))
+}
+
+impl ToJson for Formula {
+	fn to_json(&self) -> Json {
+		fn obj(op: &str, mut fields: Vec<(&str, Json)>) -> Json {
+			fields.insert(0, ("op", Json::String(op.to_string())));
+			Json::Object(fields.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+		}
+		let bin = |op: &str, p: &Formula, q: &Formula| obj(op, vec![("left", p.to_json()), ("right", q.to_json())]);
+		match self {
+			&Formula::Var(c)                => obj("var", vec![("name", Json::String(c.to_string()))]),
+			&Formula::Bottom                => obj("bottom", vec![]),
+			&Formula::Not(ref p)            => obj("not", vec![("arg", p.to_json())]),
+			&Formula::And(ref p, ref q)     => bin("and", p, q),
+			&Formula::Or(ref p, ref q)      => bin("or", p, q),
+			&Formula::Implies(ref p, ref q) => bin("implies", p, q),
+			&Formula::Iff(ref p, ref q)     => bin("iff", p, q),
+		}
+	}
+}
+impl FromJson for Formula {
+	fn from_json(j: &Json) -> Result<Formula, JsonError> {
+		let sub = |key: &'static str| -> Result<Box<Formula>, JsonError> {
+			Ok(Box::new(try!(Formula::from_json(try!(j.field(key))))))
+		};
+		Ok(match try!(try!(j.field("op")).as_str()) {
+			"var" => {
+				let name = try!(try!(j.field("name")).as_str());
+				let mut chars = name.chars();
+				match (chars.next(), chars.next()) {
+					(Some(c), None) if c.is_alphabetic() => Formula::Var(c),
+					_ => return Err(JsonError::Expected("a single letter variable name")),
+				}
+			},
+			"bottom"  => Formula::Bottom,
+			"not"     => Formula::Not(try!(sub("arg"))),
+			"and"     => Formula::And(try!(sub("left")), try!(sub("right"))),
+			"or"      => Formula::Or(try!(sub("left")), try!(sub("right"))),
+			"implies" => Formula::Implies(try!(sub("left")), try!(sub("right"))),
+			"iff"     => Formula::Iff(try!(sub("left")), try!(sub("right"))),
+			_ => return Err(JsonError::Expected("an operator: var, bottom, not, and, or, implies or iff")),
+		})
+	}
+}
+
+impl ToJson for Token {
+	fn to_json(&self) -> Json {
+		let op = match self {
+			&Token::Char(c) => return Json::String(c.to_string()),
+			&Token::Not     => "not",
+			&Token::And     => "and",
+			&Token::Or      => "or",
+			&Token::Implies => "implies",
+			&Token::Iff     => "iff",
+			&Token::Bottom  => "bottom",
+		};
+		Json::Object(vec![("op".to_string(), Json::String(op.to_string()))])
+	}
+}
+impl FromJson for Token {
+	fn from_json(j: &Json) -> Result<Token, JsonError> {
+		if let &Json::String(ref s) = j {
+			let mut chars = s.chars();
+			return match (chars.next(), chars.next()) {
+				(Some(c), None) => Ok(Token::Char(c)),
+				_ => Err(JsonError::Expected("a single character")),
+			};
+		}
+		Ok(match try!(try!(j.field("op")).as_str()) {
+			"not"     => Token::Not,
+			"and"     => Token::And,
+			"or"      => Token::Or,
+			"implies" => Token::Implies,
+			"iff"     => Token::Iff,
+			"bottom"  => Token::Bottom,
+			_ => return Err(JsonError::Expected("an operator: not, and, or, implies, iff or bottom")),
+		})
+	}
+}
+
+/// Runs of characters are written as one string.
+impl ToJson for Tokens {
+	fn to_json(&self) -> Json {
+		let mut v = Vec::new();
+		for t in self.iter() {
+			match (t, v.last_mut()) {
+				(&Token::Char(c), Some(&mut Json::String(ref mut s))) => {
+					s.push(c);
+					continue;
+				},
+				_ => {},
+			}
+			v.push(t.to_json());
+		}
+		Json::Array(v)
+	}
+}
+impl FromJson for Tokens {
+	fn from_json(j: &Json) -> Result<Tokens, JsonError> {
+		let mut toks = Vec::new();
+		for t in try!(j.as_array()) {
+			match t {
+				&Json::String(ref s) => toks.extend(s.chars().map(Token::Char)),
+				t => toks.push(try!(Token::from_json(t))),
+			}
+		}
+		Ok(Tokens::from_vec(toks))
+	}
+}
+
+impl ToJson for Sequent {
+	fn to_json(&self) -> Json {
+		Json::Object(vec![
+			("premises".to_string(), Json::Array(self.premises.iter().map(|p| p.to_json()).collect())),
+			("conclusion".to_string(), self.conclusion.to_json()),
+		])
+	}
+}
+impl FromJson for Sequent {
+	fn from_json(j: &Json) -> Result<Sequent, JsonError> {
+		Ok(Sequent::new(try!(try!(j.field("premises")).to_vec()), try!(Formula::from_json(try!(j.field("conclusion"))))))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn test_json_round_trip() {
+		let seq = Sequent::parse("P->(Q<->~R), _|_ |- (PvQ)&R").unwrap();
+		let doc = to_document("sequent", &seq);
+		let s = format!("{}", doc);
+		println!("[test_json_round_trip] {}\n{:#}", s, doc);
+		assert_eq!(Json::parse(&s).unwrap(), doc);
+		assert_eq!(Json::parse(&format!("{:#}", doc)).unwrap(), doc);
+		assert_eq!(from_document::<Sequent>("sequent", &doc).unwrap(), seq);
+		
+		let f = Formula::new("~P").unwrap();
+		assert_eq!(format!("{}", f.to_json()), r#"{"op":"not","arg":{"op":"var","name":"P"}}"#);
+		let mut toks = Tokens::from_str("P -> ¬(Q");
+		toks.simplify();
+		assert_eq!(format!("{}", toks.to_json()), r#"["P ",{"op":"implies"}," ",{"op":"not"},"(Q"]"#);
+		assert_eq!(Tokens::from_json(&toks.to_json()).unwrap(), toks);
+		
+		let j = Json::parse(" {\"a\" : [1, -2.5e1, true, null, \"\\\"\\u00e9\\ud83d\\ude00\\n\"], \"b\": {}} ").unwrap();
+		assert_eq!(j.get("a").unwrap().as_array().unwrap()[1], Json::Number(-25.0));
+		assert_eq!(j.get("a").unwrap().as_array().unwrap()[4].as_str().unwrap(), "\"é😀\n");
+		assert_eq!(Json::parse(&format!("{}", j)).unwrap(), j);
+	}
+	
+	#[test]
+	fn test_json_errors() {
+		assert_eq!(Json::parse("[1, 2"), Err(JsonError::Syntax(5)));
+		assert_eq!(Json::parse("{\"a\" 1}"), Err(JsonError::Syntax(5)));
+		assert_eq!(Json::parse("[1] x"), Err(JsonError::Syntax(4)));
+		// A high surrogate must be followed by a low one
+		assert_eq!(Json::parse(r#""\ud83d\u0041""#), Err(JsonError::Syntax(13)));
+		assert_eq!(Json::parse(r#""\ud83d\ud83d""#), Err(JsonError::Syntax(13)));
+		assert_eq!(Json::parse(r#""\ude00""#), Err(JsonError::Syntax(7)));
+		assert_eq!(Json::parse(r#""\u+041""#), Err(JsonError::Syntax(3)));
+		assert_eq!(Json::parse(r#""\u004""#), Err(JsonError::Syntax(3)));
+		for s in &["1.", ".5", "-", "01", "1e", "1e+", "+1", "1.e5", "--1"] {
+			assert!(Json::parse(s).is_err(), "{} was accepted", s);
+		}
+		for &(s, n) in &[("0", 0.0), ("-0.5", -0.5), ("10E-1", 1.0), ("2e+2", 200.0)] {
+			assert_eq!(Json::parse(s), Ok(Json::Number(n)));
+		}
+		let j = Json::Array(vec![Json::Number(::std::f64::NAN), Json::Number(::std::f64::INFINITY)]);
+		assert_eq!(format!("{}", j), "[null,null]");
+		let doc = Json::parse(r#"{"version": 2, "formula": {"op": "bottom"}}"#).unwrap();
+		assert_eq!(from_document::<Formula>("formula", &doc), Err(JsonError::Version(2)));
+		let doc = Json::parse(r#"{"version": 1, "formula": {"op": "and", "left": {"op": "bottom"}}}"#).unwrap();
+		assert_eq!(from_document::<Formula>("formula", &doc), Err(JsonError::Missing("right")));
+		let doc = Json::parse(r#"{"version": 1, "formula": {"op": "var", "name": "PQ"}}"#).unwrap();
+		assert!(from_document::<Formula>("formula", &doc).is_err());
+	}
+}
@@ -3,6 +3,11 @@ pub mod consts;
 pub mod prelude;
 pub mod tok;
 pub mod form;
+pub mod parse;
+pub mod seq;
+pub mod json;
 
 pub use form::Formula;
+pub use parse::ParseError;
+pub use seq::Sequent;
 pub use tok::{Token, Tokens};
@@ -0,0 +1,157 @@
+use std::fmt::{self, Display, Formatter};
+
+use tok::Token;
+use form::Formula;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ParseErrorKind {
+	/// There were no tokens to parse.
+	Empty,
+	/// A token was found where it wasn't expected.
+	Unexpected(Token),
+	/// The tokens ended before the formula was complete.
+	UnexpectedEnd,
+	/// A `(` was never closed.
+	UnclosedParen,
+}
+
+/// An error produced when parsing a formula. `pos` is the index of the offending token.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+	pub pos: usize,
+	pub kind: ParseErrorKind,
+}
+impl ParseError {
+	fn new(pos: usize, kind: ParseErrorKind) -> ParseError {
+		ParseError {
+			pos: pos,
+			kind: kind,
+		}
+	}
+}
+impl Display for ParseError {
+	fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+		match self.kind {
+			ParseErrorKind::Empty         => write!(f, "empty formula"),
+			ParseErrorKind::Unexpected(t) => write!(f, "unexpected `{:#}` at position {}", t, self.pos + 1),
+			ParseErrorKind::UnexpectedEnd => write!(f, "formula ends unexpectedly"),
+			ParseErrorKind::UnclosedParen => write!(f, "unclosed `(` at position {}", self.pos + 1),
+		}
+	}
+}
+
+/// Returns true if `t` should be skipped by the parser.
+fn is_space(t: Token) -> bool {
+	match t {
+		Token::Char(c) => c.is_whitespace() || c == '\u{2009}' || c == '\u{200A}',
+		_ => false,
+	}
+}
+
+/// Recursive descent parser over a slice of tokens.
+///
+/// Precedence, from tightest to loosest, is `¬`, `∧`, `∨`, `→`, `↔`. `→` and `↔` associate to the right,
+/// `∧` and `∨` to the left.
+struct Parser<'a> {
+	toks: &'a [Token],
+	pos: usize,
+}
+impl<'a> Parser<'a> {
+	fn peek(&mut self) -> Option<Token> {
+		while self.pos < self.toks.len() && is_space(self.toks[self.pos]) {
+			self.pos += 1;
+		}
+		self.toks.get(self.pos).map(|t| *t)
+	}
+	
+	fn iff(&mut self) -> Result<Formula, ParseError> {
+		let p = try!(self.implies());
+		if self.peek() == Some(Token::Iff) {
+			self.pos += 1;
+			let q = try!(self.iff());
+			Ok(Formula::Iff(Box::new(p), Box::new(q)))
+		} else {
+			Ok(p)
+		}
+	}
+	
+	fn implies(&mut self) -> Result<Formula, ParseError> {
+		let p = try!(self.or());
+		if self.peek() == Some(Token::Implies) {
+			self.pos += 1;
+			let q = try!(self.implies());
+			Ok(Formula::Implies(Box::new(p), Box::new(q)))
+		} else {
+			Ok(p)
+		}
+	}
+	
+	fn or(&mut self) -> Result<Formula, ParseError> {
+		let mut p = try!(self.and());
+		while self.peek() == Some(Token::Or) {
+			self.pos += 1;
+			let q = try!(self.and());
+			p = Formula::Or(Box::new(p), Box::new(q));
+		}
+		Ok(p)
+	}
+	
+	fn and(&mut self) -> Result<Formula, ParseError> {
+		let mut p = try!(self.unary());
+		while self.peek() == Some(Token::And) {
+			self.pos += 1;
+			let q = try!(self.unary());
+			p = Formula::And(Box::new(p), Box::new(q));
+		}
+		Ok(p)
+	}
+	
+	fn unary(&mut self) -> Result<Formula, ParseError> {
+		match self.peek() {
+			Some(Token::Not) => {
+				self.pos += 1;
+				let p = try!(self.unary());
+				Ok(Formula::Not(Box::new(p)))
+			},
+			Some(Token::Bottom) => {
+				self.pos += 1;
+				Ok(Formula::Bottom)
+			},
+			Some(Token::Char('(')) => {
+				let open = self.pos;
+				self.pos += 1;
+				let p = try!(self.iff());
+				match self.peek() {
+					Some(Token::Char(')')) => {
+						self.pos += 1;
+						Ok(p)
+					},
+					Some(t) => Err(ParseError::new(self.pos, ParseErrorKind::Unexpected(t))),
+					None => Err(ParseError::new(open, ParseErrorKind::UnclosedParen)),
+				}
+			},
+			Some(Token::Char(c)) if c.is_alphabetic() => {
+				self.pos += 1;
+				Ok(Formula::Var(c))
+			},
+			Some(t) => Err(ParseError::new(self.pos, ParseErrorKind::Unexpected(t))),
+			None    => Err(ParseError::new(self.pos, ParseErrorKind::UnexpectedEnd)),
+		}
+	}
+}
+
+/// Parses a formula from a slice of tokens. The tokens should already be simplified.
+pub fn parse(toks: &[Token]) -> Result<Formula, ParseError> {
+	let mut p = Parser {
+		toks: toks,
+		pos: 0,
+	};
+	if p.peek().is_none() {
+		return Err(ParseError::new(0, ParseErrorKind::Empty));
+	}
+	let f = try!(p.iff());
+	match p.peek() {
+		Some(t) => Err(ParseError::new(p.pos, ParseErrorKind::Unexpected(t))),
+		None    => Ok(f),
+	}
+}
@@ -16,3 +16,6 @@ pub fn implies(p: Box<::Formula>, q: Box<::Formula>) -> Box<::Formula> {
 pub fn iff(p: Box<::Formula>, q: Box<::Formula>) -> Box<::Formula> {
 	box ::Formula::Iff(p, q)
 }
+pub fn bottom() -> Box<::Formula> {
+	box ::Formula::Bottom
+}
@@ -0,0 +1,96 @@
+use std::fmt::{self, Display, Formatter};
+
+use consts::*;
+use form::Formula;
+use parse::ParseError;
+
+/// A sequent `Γ ⊢ φ`: a list of premises and the conclusion that should follow from them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sequent {
+	pub premises: Vec<Formula>,
+	pub conclusion: Formula,
+}
+impl Sequent {
+	pub fn new(premises: Vec<Formula>, conclusion: Formula) -> Sequent {
+		Sequent {
+			premises: premises,
+			conclusion: conclusion,
+		}
+	}
+	
+	/// Parses a sequent such as `P, P->Q |- Q` or `⊢ P∨¬P`.
+	///
+	/// If the parse fails, returns the index of the formula that failed (premises first, then the conclusion)
+	/// along with the error.
+	pub fn parse(s: &str) -> Result<Sequent, (usize, ParseError)> {
+		let (prems, concl) = match s.find(STR_PRETTY_TURNSTILE) {
+			Some(i) => (&s[..i], &s[i + STR_PRETTY_TURNSTILE.len()..]),
+			None => match s.find(STR_TURNSTILE) {
+				Some(i) => (&s[..i], &s[i + STR_TURNSTILE.len()..]),
+				None    => ("", s),
+			},
+		};
+		let mut premises = Vec::new();
+		if !prems.trim().is_empty() {
+			for (i, p) in prems.split(',').enumerate() {
+				premises.push(try!(Formula::new(p).map_err(|e| (i, e))));
+			}
+		}
+		let n = premises.len();
+		let conclusion = try!(Formula::new(concl).map_err(|e| (n, e)));
+		Ok(Sequent::new(premises, conclusion))
+	}
+	
+	/// All the variables that occur in the sequent, sorted.
+	pub fn vars(&self) -> Vec<char> {
+		let mut vars = Vec::new();
+		for p in self.premises.iter() {
+			p.collect_vars(&mut vars);
+		}
+		self.conclusion.collect_vars(&mut vars);
+		vars
+	}
+	
+	/// Searches the truth table for a valuation that makes every premise true and the conclusion false.
+	pub fn counterexample(&self) -> Option<Vec<(char, bool)>> {
+		let vars = self.vars();
+		for n in 0..(1u64 << vars.len()) {
+			let v = |c: char| {
+				let i = vars.iter().position(|&v| v == c).unwrap();
+				n & (1 << (vars.len() - 1 - i)) != 0
+			};
+			if self.premises.iter().all(|p| p.eval(&v)) && !self.conclusion.eval(&v) {
+				return Some(vars.iter().map(|&c| (c, v(c))).collect());
+			}
+		}
+		None
+	}
+	
+	/// True if the conclusion is true under every valuation that makes all the premises true.
+	pub fn is_valid(&self) -> bool {
+		self.counterexample().is_none()
+	}
+}
+impl Display for Sequent {
+	fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+		let alt = f.alternate();
+		for (i, p) in self.premises.iter().enumerate() {
+			if i != 0 {
+				try!(write!(f, ", "));
+			}
+			if alt {
+				try!(write!(f, "{:#}", p));
+			} else {
+				try!(write!(f, "{}", p));
+			}
+		}
+		if !self.premises.is_empty() {
+			try!(write!(f, " "));
+		}
+		if alt {
+			write!(f, "{} {:#}", STR_PRETTY_TURNSTILE, self.conclusion)
+		} else {
+			write!(f, "{} {}", STR_TURNSTILE, self.conclusion)
+		}
+	}
+}
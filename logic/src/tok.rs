@@ -1,7 +1,7 @@
 use std::fmt::{self, Write, Display, Formatter};
 use std::ops::{Deref, DerefMut};
 
-#[derive(Copy, Clone, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Token {
 	Char(char),
 	Not,
@@ -9,6 +9,7 @@ pub enum Token {
 	Or,
 	Implies,
 	Iff,
+	Bottom,
 }
 impl Token {
 	fn append_to_gui_string(&self, space: bool, s: &mut String) {
@@ -19,6 +20,7 @@ impl Token {
 			&Token::Or          => s.push('v'),
 			&Token::Implies     => s.push('→'),
 			&Token::Iff         => s.push('↔'),
+			&Token::Bottom      => s.push('⊥'),
 		}
 		if space {
 			s.push('\u{2009}'); // U+2009 THIN SPACE
@@ -32,10 +34,10 @@ impl Display for Token {
 		use Token::*;
 		use consts::*;
 		
-		let (not, and, or, implies, iff) = if !f.alternate() {
-			(STR_NOT, STR_AND, STR_OR, STR_IF, STR_IFF)
+		let (not, and, or, implies, iff, bot) = if !f.alternate() {
+			(STR_NOT, STR_AND, STR_OR, STR_IF, STR_IFF, STR_BOT)
 		} else {
-			(STR_PRETTY_NOT, STR_PRETTY_AND, STR_PRETTY_OR, STR_PRETTY_IF, STR_PRETTY_IFF)
+			(STR_PRETTY_NOT, STR_PRETTY_AND, STR_PRETTY_OR, STR_PRETTY_IF, STR_PRETTY_IFF, STR_PRETTY_BOT)
 		};
 		
 		match self {
@@ -45,11 +47,12 @@ impl Display for Token {
 			&Or          => f.write_str(or),
 			&Implies     => f.write_str(implies),
 			&Iff         => f.write_str(iff),
+			&Bottom      => f.write_str(bot),
 		}
 	}
 }
 
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Tokens {
 	inner: Vec<Token>
 }
@@ -107,6 +110,12 @@ impl Tokens {
 			} else if s.starts_with(STR_PRETTY_IFF) {
 				s = &s[STR_PRETTY_IFF.len()..];
 				res.push(Iff);
+			} else if s.starts_with(STR_BOT) {
+				s = &s[STR_BOT.len()..];
+				res.push(Bottom);
+			} else if s.starts_with(STR_PRETTY_BOT) {
+				s = &s[STR_PRETTY_BOT.len()..];
+				res.push(Bottom);
 			} else {
 				let c = s.chars().next().unwrap();
 				s = &s[c.len_utf8()..];
@@ -168,6 +177,12 @@ impl Tokens {
 				} else if ts.starts_with(TOK_STR_PRETTY_IFF) {
 					ts = &ts[TOK_STR_PRETTY_IFF.len()..];
 					res.push(Iff);
+				} else if ts.starts_with(TOK_STR_BOT) {
+					ts = &ts[TOK_STR_BOT.len()..];
+					res.push(Bottom);
+				} else if ts.starts_with(TOK_STR_PRETTY_BOT) {
+					ts = &ts[TOK_STR_PRETTY_BOT.len()..];
+					res.push(Bottom);
 				} else {
 					res.push(ts[0]);
 					ts = &ts[1..];
@@ -0,0 +1,486 @@
+use std::fmt::{self, Display, Formatter};
+
+use logic::{Formula, ParseError, Sequent};
+use logic::form::{Not, And, Or, Implies, Iff, Bottom};
+
+use edit::{Col, Lines};
+use just::{Justification, JustError};
+use library::Library;
+use rule::Rule;
+use system::{ProofSystem, Lemmon};
+
+/// Something wrong with a line of a proof.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CheckError {
+	/// The formula of the line couldn't be parsed.
+	Formula(ParseError),
+	/// The method column couldn't be read as a justification.
+	Method(JustError),
+	/// A cited line doesn't exist, or isn't before the line citing it.
+	BadRef(usize),
+	/// A cited line has a formula that couldn't be parsed.
+	CitedInvalid(usize),
+	/// The rule was cited with the wrong number of lines.
+	Arity(Rule, usize),
+	/// A line that the rule discharges isn't an assumption.
+	NotAssumption(usize),
+	/// The rule doesn't give the formula from the cited lines.
+	Mismatch(Rule),
+	/// The library has no sequent with this name.
+	UnknownSequent(String),
+	/// The dependencies aren't the ones the rule gives. Contains the correct dependencies.
+	Deps(Vec<usize>),
+	/// The rule isn't part of the proof system being checked.
+	NotAllowed(Rule),
+	/// The rule cites single lines where it needs subproofs, or the other way around.
+	Shape(Rule),
+	/// A cited line is inside a subproof that has already closed.
+	OutOfScope(usize),
+	/// A cited range isn't a whole subproof that can be cited here.
+	NotSubproof(usize, usize),
+	/// The line is indented without opening a subproof, or a premise is inside a subproof.
+	BadDepth,
+}
+impl Display for CheckError {
+	fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+		match self {
+			&CheckError::Formula(ref e)      => write!(f, "malformed formula: {}", e),
+			&CheckError::Method(ref e)       => e.fmt(f),
+			&CheckError::BadRef(no)          => write!(f, "line {} can't be cited here", no + 1),
+			&CheckError::CitedInvalid(no)    => write!(f, "cited line {} is malformed", no + 1),
+			&CheckError::Arity(rule, n)      => write!(f, "{:#} needs {} cited line{}", rule, n, if n == 1 { "" } else { "s" }),
+			&CheckError::NotAssumption(no)   => write!(f, "line {} is not an assumption", no + 1),
+			&CheckError::Mismatch(rule)      => write!(f, "{:#} doesn't give this formula from the cited lines", rule),
+			&CheckError::UnknownSequent(ref s) => write!(f, "no sequent called `{}` in the library", s),
+			&CheckError::Deps(ref deps) => {
+				try!(write!(f, "dependencies should be {{"));
+				for (i, d) in deps.iter().enumerate() {
+					try!(write!(f, "{}{}", if i == 0 { "" } else { ", " }, d + 1));
+				}
+				write!(f, "}}")
+			},
+			&CheckError::NotAllowed(rule)    => write!(f, "{:#} can't be used in this proof system", rule),
+			&CheckError::Shape(rule)         => write!(f, "{:#} cites the wrong mix of lines and subproofs", rule),
+			&CheckError::OutOfScope(no)      => write!(f, "line {} is in a closed subproof", no + 1),
+			&CheckError::NotSubproof(a, b)   => write!(f, "lines {}-{} aren't a subproof that can be cited here", a + 1, b + 1),
+			&CheckError::BadDepth            => write!(f, "line is indented wrongly"),
+		}
+	}
+}
+
+/// A problem found on line `no` of a proof.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+	pub no: usize,
+	pub err: CheckError,
+}
+impl Display for Diagnostic {
+	fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+		write!(f, "line {}: {}", self.no + 1, self.err)
+	}
+}
+impl Diagnostic {
+	/// The column of the line that the problem is in, and the range of its tokens to mark: the token a formula
+	/// couldn't be parsed at, or else the whole column. The range ends one past the last token if the formula
+	/// ends too soon.
+	pub fn span(&self, lines: &Lines) -> (Col, usize, usize) {
+		let l = &lines[self.no];
+		match self.err {
+			CheckError::Formula(e) => (Col::Step, e.pos, e.pos + 1),
+			CheckError::BadDepth   => (Col::Step, 0, l.step.len()),
+			CheckError::Deps(_)    => (Col::Deps, 0, l.deps_tokens().len()),
+			_ => (Col::Method, 0, l.method.len()),
+		}
+	}
+}
+
+/// A line of the proof, as far as it could be parsed.
+pub struct Parsed {
+	pub form: Result<Formula, ParseError>,
+	pub just: Result<Justification, JustError>,
+}
+
+/// True if `f` is a contradiction that `¬I` and `RAA` can discharge from: `⊥`, or `ψ∧¬ψ` as in Lemmon.
+fn is_contradiction(f: &Formula) -> bool {
+	match f {
+		&Bottom => true,
+		&And(ref p, ref q) => match **q {
+			Not(ref q) => p == q,
+			_ => false,
+		},
+		_ => false,
+	}
+}
+
+/// Checks that the rule of line `no` gives its formula from the lines it cites. Only the formulas are looked
+/// at, so this is shared by every proof system; which rules are allowed and which lines can be cited is up to
+/// the caller.
+pub fn check_line(parsed: &[Parsed], lib: &Library, no: usize) -> Result<(), CheckError> {
+	let f = match parsed[no].form {
+		Ok(ref f) => f,
+		Err(e) => return Err(CheckError::Formula(e)),
+	};
+	let (rule, ref cites, name) = match parsed[no].just {
+		Ok(ref j) => (j.rule, j.lines(), &j.name),
+		Err(ref e) => return Err(CheckError::Method(e.clone())),
+	};
+	
+	let mut fs = Vec::with_capacity(cites.len());
+	for &c in cites.iter() {
+		if c >= no {
+			return Err(CheckError::BadRef(c));
+		}
+		match parsed[c].form {
+			Ok(ref f) => fs.push(f),
+			Err(_) => return Err(CheckError::CitedInvalid(c)),
+		}
+	}
+	let arity = |n: usize| if cites.len() == n { Ok(()) } else { Err(CheckError::Arity(rule, n)) };
+	let assumption = |i: usize| match parsed[cites[i]].just {
+		Ok(ref j) if j.rule.is_assumption() => Ok(()),
+		_ => Err(CheckError::NotAssumption(cites[i])),
+	};
+	let mismatch = Err(CheckError::Mismatch(rule));
+	
+	match rule {
+		Rule::Premise | Rule::Assumption => {
+			arity(0)
+		},
+		Rule::AndI => {
+			try!(arity(2));
+			match f {
+				&And(ref p, ref q) if (**p == *fs[0] && **q == *fs[1]) || (**p == *fs[1] && **q == *fs[0]) =>
+					Ok(()),
+				_ => mismatch,
+			}
+		},
+		Rule::AndE => {
+			try!(arity(1));
+			match fs[0] {
+				&And(ref p, ref q) if **p == *f || **q == *f => Ok(()),
+				_ => mismatch,
+			}
+		},
+		Rule::OrI => {
+			try!(arity(1));
+			match f {
+				&Or(ref p, ref q) if **p == *fs[0] || **q == *fs[0] => Ok(()),
+				_ => mismatch,
+			}
+		},
+		Rule::OrE => {
+			// l: φ∨ψ, m: φ assumed, n: χ, o: ψ assumed, p: χ
+			try!(arity(5));
+			try!(assumption(1));
+			try!(assumption(3));
+			match fs[0] {
+				&Or(ref p, ref q) if ((**p == *fs[1] && **q == *fs[3]) || (**p == *fs[3] && **q == *fs[1]))
+						&& *fs[2] == *f && *fs[4] == *f =>
+					Ok(()),
+				_ => mismatch,
+			}
+		},
+		Rule::ImpliesI => {
+			try!(arity(2));
+			try!(assumption(0));
+			match f {
+				&Implies(ref p, ref q) if **p == *fs[0] && **q == *fs[1] => Ok(()),
+				_ => mismatch,
+			}
+		},
+		Rule::ImpliesE => {
+			try!(arity(2));
+			let ok = |i: usize, j: usize| match fs[i] {
+				&Implies(ref p, ref q) => **p == *fs[j] && **q == *f,
+				_ => false,
+			};
+			if ok(0, 1) || ok(1, 0) { Ok(()) } else { mismatch }
+		},
+		Rule::Mtt => {
+			try!(arity(2));
+			let ok = |i: usize, j: usize| match (fs[i], fs[j], f) {
+				(&Implies(ref p, ref q), &Not(ref nq), &Not(ref np)) => p == np && q == nq,
+				_ => false,
+			};
+			if ok(0, 1) || ok(1, 0) { Ok(()) } else { mismatch }
+		},
+		Rule::NotI => {
+			try!(arity(2));
+			try!(assumption(0));
+			match f {
+				&Not(ref p) if **p == *fs[0] && is_contradiction(fs[1]) => Ok(()),
+				_ => mismatch,
+			}
+		},
+		Rule::NotE => {
+			try!(arity(2));
+			let ok = |i: usize, j: usize| match fs[j] {
+				&Not(ref p) => **p == *fs[i],
+				_ => false,
+			};
+			if *f == Bottom && (ok(0, 1) || ok(1, 0)) { Ok(()) } else { mismatch }
+		},
+		Rule::Raa => {
+			// Either φ is assumed and ¬φ concluded, or ¬φ is assumed and φ concluded
+			try!(arity(2));
+			try!(assumption(0));
+			let ok = match (f, fs[0]) {
+				(&Not(ref p), a) if **p == *a => true,
+				(f, &Not(ref a)) if **a == *f => true,
+				_ => false,
+			};
+			if ok && is_contradiction(fs[1]) { Ok(()) } else { mismatch }
+		},
+		Rule::Dn => {
+			try!(arity(1));
+			let ok = |p: &Formula, q: &Formula| match p {
+				&Not(ref p) => match **p {
+					Not(ref p) => **p == *q,
+					_ => false,
+				},
+				_ => false,
+			};
+			if ok(fs[0], f) || ok(f, fs[0]) { Ok(()) } else { mismatch }
+		},
+		Rule::IffI if cites.len() == 4 => {
+			// Fitch: i: φ assumed, j: ψ, k: ψ assumed, l: φ
+			try!(assumption(0));
+			try!(assumption(2));
+			match f {
+				&Iff(ref p, ref q) if **p == *fs[0] && **q == *fs[1] && **q == *fs[2] && **p == *fs[3] => Ok(()),
+				_ => mismatch,
+			}
+		},
+		Rule::IffI => {
+			try!(arity(2));
+			let ok = |i: usize, j: usize| match (f, fs[i], fs[j]) {
+				(&Iff(ref p, ref q), &Implies(ref a, ref b), &Implies(ref c, ref d)) =>
+					p == a && q == b && q == c && p == d,
+				_ => false,
+			};
+			if ok(0, 1) || ok(1, 0) { Ok(()) } else { mismatch }
+		},
+		Rule::IffE => {
+			// Either φ↔ψ gives φ→ψ and ψ→φ, or φ↔ψ and φ give ψ
+			if cites.len() == 1 {
+				match (fs[0], f) {
+					(&Iff(ref p, ref q), &Implies(ref a, ref b)) if (p == a && q == b) || (p == b && q == a) =>
+						Ok(()),
+					_ => mismatch,
+				}
+			} else {
+				try!(arity(2));
+				let ok = |i: usize, j: usize| match fs[i] {
+					&Iff(ref p, ref q) => (**p == *fs[j] && **q == *f) || (**q == *fs[j] && **p == *f),
+					_ => false,
+				};
+				if ok(0, 1) || ok(1, 0) { Ok(()) } else { mismatch }
+			}
+		},
+		Rule::Si | Rule::Ti => {
+			let name = name.as_ref().unwrap();
+			if !lib.contains(name) {
+				return Err(CheckError::UnknownSequent(name.clone()));
+			}
+			if rule == Rule::Ti {
+				try!(arity(0));
+			}
+			if lib.instance(name, &fs, f) { Ok(()) } else { mismatch }
+		},
+		Rule::Reit => {
+			try!(arity(1));
+			if *fs[0] == *f { Ok(()) } else { mismatch }
+		},
+		Rule::X => {
+			try!(arity(1));
+			if *fs[0] == Bottom { Ok(()) } else { mismatch }
+		},
+		Rule::Ip => {
+			try!(arity(2));
+			try!(assumption(0));
+			match fs[0] {
+				&Not(ref p) if **p == *f && *fs[1] == Bottom => Ok(()),
+				_ => mismatch,
+			}
+		},
+		Rule::Taut => {
+			let seq = Sequent::new(fs.iter().map(|&f| f.clone()).collect(), f.clone());
+			if seq.is_valid() { Ok(()) } else { mismatch }
+		},
+		Rule::Derived => {
+			if lib.rule_instance(name.as_ref().unwrap(), &fs, f) { Ok(()) } else { mismatch }
+		},
+	}
+}
+
+/// Checks every line of a Lemmon-style proof against the rules, and that each line has exactly the
+/// dependencies its rule gives it. The dependencies of cited lines are taken as they are written, so a
+/// wrong dependency is only reported on the line it is wrong on. Returns every problem found, in line order.
+///
+/// `SI` and `TI` lines are checked against the standard library.
+pub fn check(lines: &Lines) -> Vec<Diagnostic> {
+	check_with(lines, &Library::standard())
+}
+
+/// Checks a proof like `check`, with `SI` and `TI` lines citing sequents in `lib`.
+pub fn check_with(lines: &Lines, lib: &Library) -> Vec<Diagnostic> {
+	check_deps(&Lemmon, lines, lib)
+}
+
+/// True if `rule` can be used in a proof in `sys`: it is one of the system's rules and `lib` doesn't forbid
+/// it, or it is one of the derived rules in `lib`.
+pub fn is_allowed<S: ProofSystem + ?Sized>(sys: &S, lib: &Library, rule: Rule) -> bool {
+	rule == Rule::Derived || (sys.rules().contains(&rule) && !lib.forbids(rule))
+}
+
+/// Checks a proof in a system where each line lists its dependencies: every rule must be allowed in `sys`, must
+/// give the line's formula from the lines it cites, and must give the line the dependencies it has.
+pub fn check_deps<S: ProofSystem + ?Sized>(sys: &S, lines: &Lines, lib: &Library) -> Vec<Diagnostic> {
+	let parsed: Vec<Parsed> = lines.iter().map(|l| Parsed {
+		form: Formula::parse(&l.step),
+		just: l.justification_with(lib),
+	}).collect();
+	
+	let mut diags = Vec::new();
+	for no in 0..lines.len() {
+		let res = match parsed[no].just {
+			Ok(ref j) if !is_allowed(sys, lib, j.rule) => Err(CheckError::NotAllowed(j.rule)),
+			_ => check_line(&parsed, lib, no),
+		};
+		let err = match (res, &parsed[no].just) {
+			(Ok(()), &Ok(ref j)) => {
+				let mut found = lines[no].deps.clone();
+				found.sort();
+				found.dedup();
+				match sys.deps(j, no, lines) {
+					Some(deps) => if deps != found { Some(CheckError::Deps(deps)) } else { None },
+					None => None,
+				}
+			},
+			(Ok(()), _) => None,
+			(Err(e), _) => Some(e),
+		};
+		if let Some(err) = err {
+			diags.push(Diagnostic {
+				no: no,
+				err: err,
+			});
+		}
+	}
+	diags
+}
+
+#[cfg(test)]
+mod tests {
+	use logic::Sequent;
+	
+	use edit::{line, Col, Lines};
+	use library::LibError;
+	use prove::prove;
+	use super::*;
+	
+	#[test]
+	fn test_check_found_proofs() {
+		for s in ["P, P->Q |- Q", "|- Pv~P", "P->Q, ~Q |- ~P", "~(P^Q) |- ~Pv~Q", "P<->Q |- Q<->P",
+				"P^(QvR) |- (P^Q)v(P^R)", "P, ~P |- Q", "|- ((P->Q)->P)->P"].iter() {
+			let lines = prove(&Sequent::parse(s).unwrap()).unwrap();
+			println!("[test_check_found_proofs] {}\n{:#}", s, lines);
+			assert_eq!(check(&lines), vec![]);
+		}
+	}
+	
+	#[test]
+	fn test_check_errors() {
+		let lines = Lines::from_vec(vec![
+			line(0, "P->Q", "A", vec![0]),
+			line(1, "P", "A", vec![1]),
+			line(2, "Q", "MPP 1, 2", vec![0]),
+			line(3, "P", "->E 1, 2", vec![0, 1]),
+			line(4, "P->", "A", vec![4]),
+			line(5, "Q", "Foo 1", vec![0]),
+			line(6, "P^Q", "&I 2, 8", vec![1]),
+			line(7, "P->P", "->I 3, 2", vec![]),
+		]);
+		let diags = check(&lines);
+		let spans: Vec<(Col, usize, usize)> = diags.iter().map(|d| d.span(&lines)).collect();
+		let errs: Vec<CheckError> = diags.into_iter().map(|d| d.err).collect();
+		println!("[test_check_errors] {:?}\n{:?}", errs, spans);
+		assert_eq!(errs[0], CheckError::Deps(vec![0, 1]));
+		assert_eq!(errs[1], CheckError::Mismatch(Rule::ImpliesE));
+		match errs[2] { CheckError::Formula(_) => {}, ref e => panic!("{:?}", e) }
+		assert_eq!(errs[3], CheckError::Method(JustError::UnknownRule("Foo".to_string())));
+		assert_eq!(errs[4], CheckError::BadRef(7));
+		assert_eq!(errs[5], CheckError::NotAssumption(2));
+		assert_eq!(errs.len(), 6);
+		// A formula that ends too soon is marked just past its end, and other problems mark their whole column
+		assert_eq!(spans[0], (Col::Deps, 0, 1));
+		assert_eq!(spans[1], (Col::Method, 0, 7));
+		assert_eq!(spans[2], (Col::Step, 2, 3));
+	}
+	
+	#[test]
+	fn test_check_sequent_introduction() {
+		let lines = Lines::from_vec(vec![
+			line(0, "~(R^(S->T))", "A", vec![0]),
+			line(1, "~Rv~(S->T)", "SI (DM) 1", vec![0]),
+			line(2, "R", "A", vec![2]),
+			line(3, "~(S->T)", "SI (DS) 2, 3", vec![0, 2]),
+			line(4, "Qv~Q", "TI (LEM)", vec![]),
+			line(5, "~R^~(S->T)", "SI (DM) 1", vec![0]),
+			line(6, "Q", "SI (Foo) 5", vec![4]),
+		]);
+		let errs = check(&lines);
+		println!("[test_check_sequent_introduction] {:?}", errs);
+		assert_eq!(errs, vec![
+			Diagnostic { no: 3, err: CheckError::Mismatch(Rule::Si) },
+			Diagnostic { no: 5, err: CheckError::Mismatch(Rule::Si) },
+			Diagnostic { no: 6, err: CheckError::UnknownSequent("Foo".to_string()) },
+		]);
+	}
+	
+	#[test]
+	fn test_check_derived_rules() {
+		let mut lib = Library::new();
+		lib.load_rules("# Assignment 3\nrule DS: φ∨ψ, ¬φ ⊢ ψ\nforbid MTT, DN\n").unwrap();
+		assert_eq!(lib.load_rules("rule Bad: φ∨ψ |- φ").unwrap_err().0, 0);
+		assert_eq!(lib.load_rules("rule MTT: φ |- φ"), Err((0, LibError::BadName("MTT".to_string()))));
+		assert_eq!(lib.load_rules("\nforbid Foo"), Err((1, LibError::UnknownRule("Foo".to_string()))));
+		assert!(!lib.has_rule("Bad"));
+		
+		let lines = Lines::from_vec(vec![
+			line(0, "(P^Q)vR", "Premise", vec![0]),
+			line(1, "~(P^Q)", "Premise", vec![1]),
+			line(2, "R", "DS 1, 2", vec![0, 1]),
+			line(3, "P^Q", "DS 2, 1", vec![0, 1]),
+			line(4, "~~R", "DN 3", vec![0, 1]),
+		]);
+		let errs = check_with(&lines, &lib);
+		println!("[test_check_derived_rules] {:?}", errs);
+		assert_eq!(errs, vec![
+			Diagnostic { no: 3, err: CheckError::Mismatch(Rule::Derived) },
+			Diagnostic { no: 4, err: CheckError::NotAllowed(Rule::Dn) },
+		]);
+		assert_eq!(format!("{:#}", lines[2].justification_with(&lib).unwrap()), "DS 1, 2");
+		assert_eq!(check(&lines)[0].err, CheckError::Method(JustError::UnknownRule("DS".to_string())));
+	}
+	
+	#[test]
+	fn test_update_deps() {
+		let mut lines = Lines::from_vec(vec![
+			line(0, "PvQ", "A", vec![]),
+			line(1, "P", "A", vec![]),
+			line(2, "QvP", "vI 2", vec![]),
+			line(3, "Q", "A", vec![]),
+			line(4, "QvP", "vI 4", vec![]),
+			line(5, "QvP", "vE 1, 2-3, 4-5", vec![]),
+			line(6, "(PvQ)->(QvP)", "->I 1, 6", vec![]),
+		]);
+		lines[4].manual_deps = true;
+		lines[4].deps = vec![0, 3];
+		assert_eq!(lines.update_deps(), vec![4]);
+		let deps: Vec<Vec<usize>> = lines.iter().map(|l| l.deps.clone()).collect();
+		assert_eq!(deps, vec![vec![0], vec![1], vec![1], vec![3], vec![0, 3], vec![0], vec![]]);
+		let errs = check(&lines);
+		assert_eq!(errs, vec![Diagnostic { no: 4, err: CheckError::Deps(vec![3]) }]);
+	}
+}
@@ -0,0 +1,341 @@
+//! The command line interface, for checking proofs and formulas in scripts and on machines without a display.
+//!
+//! ```text
+//! plc check FILE...     checks the proofs in `.plc` files, or `.json` files written by `json::write_proof`
+//! plc taut FORMULA...   says whether each formula is a tautology, a contradiction or contingent
+//! plc table FORMULA     prints the truth table of a formula
+//! plc grade [--json] SPEC DIR
+//!                       grades the proofs each student in `DIR` handed in for the assignment `SPEC`, described in
+//!                       `grade`, printing a CSV or JSON report
+//! plc repl              starts an interactive prompt for formulas and sequents, described in `repl`
+//! plc tui [FILE]        edits a proof in the terminal, described in `tui`
+//! ```
+//!
+//! The exit code is `SUCCESS` if every proof is correct or every formula a tautology, `FAILURE` if not, and
+//! `ERROR` if a file or formula can't be read; a proof handed in for grading that can't be read is graded as
+//! unreadable instead. As in the editor, the `PLC_RULES` environment variable names a file of derived rules and
+//! sequents to check proofs with. It isn't used for grading, as the assignment gives the rules.
+use std::env;
+use std::fmt::{self, Display, Formatter};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use logic::{Formula, Sequent};
+
+use edit::Lines;
+use file::ProofFile;
+use grade::{self, Assignment};
+use json;
+use library::Library;
+#[cfg(feature = "repl")]
+use repl;
+use rule::Rule;
+use system::{self, ProofSystem, Structure, Lemmon};
+#[cfg(feature = "tui")]
+use tui;
+
+pub const USAGE: &'static str = "usage: plc [FILE]
+       plc check FILE...
+       plc taut FORMULA...
+       plc table FORMULA
+       plc grade [--json] SPEC DIR
+       plc repl
+       plc tui [FILE]";
+
+/// Everything checked was correct.
+pub const SUCCESS: i32 = 0;
+/// A proof has mistakes in it, or a formula isn't a tautology.
+pub const FAILURE: i32 = 1;
+/// The arguments, or a file or formula given, couldn't be read.
+pub const ERROR: i32 = 2;
+
+/// Runs the command in `args`, the command line arguments after the program name. Returns the exit code, or
+/// `None` if `args` isn't a command, so the editor should be opened.
+pub fn run(args: &[String]) -> Option<i32> {
+	let (cmd, args) = match args.split_first() {
+		Some((cmd, args)) => (cmd, args),
+		None => return None,
+	};
+	Some(match &cmd[..] {
+		"check" => check(args),
+		"taut"  => taut(args),
+		"table" => table(args),
+		"grade" => grade(args),
+		#[cfg(feature = "repl")]
+		"repl"  => repl::run(),
+		#[cfg(feature = "tui")]
+		"tui"   => tui::run(args),
+		"help" | "-h" | "--help" => {
+			println!("{}", USAGE);
+			SUCCESS
+		},
+		_ => return None,
+	})
+}
+
+/// Checks each proof file, printing the mistakes in each.
+fn check(paths: &[String]) -> i32 {
+	if paths.is_empty() {
+		eprintln!("{}", USAGE);
+		return ERROR;
+	}
+	let mut lib = Library::standard();
+	if let Err(e) = env_rules(&mut lib) {
+		eprintln!("{}", e);
+		return ERROR;
+	}
+	let mut code = SUCCESS;
+	for path in paths.iter() {
+		match check_file(path, &lib) {
+			Ok(problems) => if problems.is_empty() {
+				println!("{}: ok", path);
+			} else {
+				for p in problems.iter() {
+					println!("{}: {}", path, p);
+				}
+				code = code.max(FAILURE);
+			},
+			Err(e) => {
+				eprintln!("{}: {}", path, e);
+				code = ERROR;
+			},
+		}
+	}
+	code
+}
+
+/// The proof system named by the `PLC_SYSTEM` environment variable, or Lemmon's if it isn't set.
+pub fn env_system() -> Result<Box<ProofSystem>, String> {
+	match env::var("PLC_SYSTEM") {
+		Ok(name) => system::from_name(&name).ok_or(format!("unknown proof system `{}`", name)),
+		Err(_) => Ok(Box::new(Lemmon)),
+	}
+}
+
+/// Loads the rule file named by the `PLC_RULES` environment variable into `lib`, if it is set. On error, returns
+/// the problem with the file and the line it is on; the rules before it have been loaded.
+pub fn env_rules(lib: &mut Library) -> Result<(), String> {
+	let path = match env::var("PLC_RULES") {
+		Ok(path) => path,
+		Err(_) => return Ok(()),
+	};
+	read(&path).and_then(|text| lib.load_rules(&text).map_err(|(no, e)| format!("{}: {}", no + 1, e)))
+		.map_err(|e| format!("{}: {}", path, e))
+}
+
+/// Reads the proof in the file at `path`: a `.json` file written by `json::write_proof`, or a `.plc` file.
+pub fn read_proof<P: AsRef<Path>>(path: P) -> Result<ProofFile, String> {
+	let text = try!(read(&path));
+	if path.as_ref().extension().map_or(false, |ext| ext == "json") {
+		json::read_proof(&text).map_err(|e| e.to_string())
+	} else {
+		ProofFile::parse(&text).map_err(|e| e.to_string())
+	}
+}
+
+/// Checks the proof in the file at `path`, returning the mistakes found. Returns `Err` if the file can't be read.
+fn check_file(path: &str, lib: &Library) -> Result<Vec<String>, String> {
+	let file = try!(read_proof(path));
+	let sys: Box<ProofSystem> = match file.header.system {
+		Some(ref name) => try!(system::from_name(name).ok_or(format!("unknown proof system `{}`", name))),
+		None => Box::new(Lemmon),
+	};
+	let mut problems: Vec<String> = sys.check(&file.lines, lib).iter().map(|d| d.to_string()).collect();
+	if let Some(ref seq) = file.header.sequent {
+		problems.extend(check_sequent(&*sys, &file.lines, seq));
+	}
+	Ok(problems)
+}
+
+/// Checks that `lines` is a proof of `seq`: that it ends with the conclusion, from no premises but those of the
+/// sequent, and with every assumption discharged. The lines themselves are checked by `ProofSystem::check`.
+fn check_sequent(sys: &ProofSystem, lines: &Lines, seq: &Sequent) -> Vec<String> {
+	let mut problems = Vec::new();
+	let last = match lines.last() {
+		Some(l) => l,
+		None => return vec!["the proof is empty".to_string()],
+	};
+	if Formula::parse(&last.step).ok().as_ref() != Some(&seq.conclusion) {
+		problems.push(format!("the proof doesn't end with the conclusion {:#}", seq.conclusion));
+	}
+	let is_premise = |no: usize| lines.get(no).and_then(|l| l.justification().ok()).map(|j| j.rule == Rule::Premise)
+		.unwrap_or(false);
+	for l in lines.iter().filter(|l| is_premise(l.no)) {
+		match Formula::parse(&l.step) {
+			Ok(ref f) if seq.premises.contains(f) => {},
+			_ => problems.push(format!("line {}: {:#} isn't a premise of the sequent", l.no + 1, l.step)),
+		}
+	}
+	let open = match sys.structure() {
+		Structure::Deps => last.deps.iter().any(|&d| !is_premise(d)),
+		Structure::Subproofs => last.depth != 0,
+	};
+	if open {
+		problems.push("the conclusion rests on an assumption that hasn't been discharged".to_string());
+	}
+	problems
+}
+
+/// Classifies each formula, printing whether it is a tautology, a contradiction or contingent.
+fn taut(args: &[String]) -> i32 {
+	if args.is_empty() {
+		eprintln!("{}", USAGE);
+		return ERROR;
+	}
+	let mut code = SUCCESS;
+	for s in args.iter() {
+		let f = match parse(s) {
+			Ok(f) => f,
+			Err(e) => {
+				eprintln!("{}", e);
+				code = ERROR;
+				continue;
+			},
+		};
+		let kind = classify(&f);
+		println!("{:#}: {}", f, kind);
+		if kind != Classification::Tautology && code == SUCCESS {
+			code = FAILURE;
+		}
+	}
+	code
+}
+
+/// Prints the truth table of a formula, with a column for each variable.
+fn table(args: &[String]) -> i32 {
+	if args.len() != 1 {
+		eprintln!("{}", USAGE);
+		return ERROR;
+	}
+	let f = match parse(&args[0]) {
+		Ok(f) => f,
+		Err(e) => {
+			eprintln!("{}", e);
+			return ERROR;
+		},
+	};
+	print!("{}", truth_table(&f, true));
+	if classify(&f) == Classification::Tautology { SUCCESS } else { FAILURE }
+}
+
+/// Grades a class's proofs for an assignment, printing a report as CSV, or as JSON with `--json`.
+fn grade(args: &[String]) -> i32 {
+	let as_json = args.iter().any(|a| a == "--json");
+	let args: Vec<&String> = args.iter().filter(|a| *a != "--json").collect();
+	if args.len() != 2 {
+		eprintln!("{}", USAGE);
+		return ERROR;
+	}
+	let assignment = match read(args[0]).and_then(|text| Assignment::parse(&text).map_err(|(no, e)| format!("{}: {}", no + 1, e))) {
+		Ok(a) => a,
+		Err(e) => {
+			eprintln!("{}: {}", args[0], e);
+			return ERROR;
+		},
+	};
+	let report = match grade::grade_dir(&assignment, Path::new(args[1])) {
+		Ok(report) => report,
+		Err(e) => {
+			eprintln!("{}: {}", args[1], e);
+			return ERROR;
+		},
+	};
+	if as_json {
+		println!("{}", report.write_json());
+	} else {
+		print!("{}", report.to_csv());
+	}
+	if report.all_proved() { SUCCESS } else { FAILURE }
+}
+
+/// What a formula is by its truth table.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Classification {
+	/// True on every row.
+	Tautology,
+	/// False on every row.
+	Contradiction,
+	/// True on some rows and false on others.
+	Contingent,
+}
+impl Display for Classification {
+	fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+		f.write_str(match *self {
+			Classification::Tautology     => "tautology",
+			Classification::Contradiction => "contradiction",
+			Classification::Contingent    => "contingent",
+		})
+	}
+}
+
+/// Whether `f` is a tautology, a contradiction or contingent.
+pub fn classify(f: &Formula) -> Classification {
+	let table = f.truth_table();
+	if table.iter().all(|&(_, value)| value) {
+		Classification::Tautology
+	} else if table.iter().all(|&(_, value)| !value) {
+		Classification::Contradiction
+	} else {
+		Classification::Contingent
+	}
+}
+
+/// Writes out the truth table of `f`, with a column for each variable, using `T` and `F`. The formula in the
+/// heading is written in unicode if `unicode` is set, and in ascii otherwise.
+pub fn truth_table(f: &Formula, unicode: bool) -> String {
+	let head: Vec<String> = f.vars().iter().map(|c| c.to_string()).collect();
+	let mut s = if unicode {
+		format!("{} | {:#}\n", head.join(" "), f)
+	} else {
+		format!("{} | {}\n", head.join(" "), f)
+	};
+	for (row, value) in f.truth_table() {
+		let cells: Vec<&str> = row.iter().map(|&(_, b)| if b { "T" } else { "F" }).collect();
+		s.push_str(&format!("{} | {}\n", cells.join(" "), if value { "T" } else { "F" }));
+	}
+	s
+}
+
+fn parse(s: &str) -> Result<Formula, String> {
+	Formula::new(s).map_err(|e| format!("`{}`: {}", s, e))
+}
+
+fn read<P: AsRef<Path>>(path: P) -> Result<String, String> {
+	let mut text = String::new();
+	try!(File::open(path).and_then(|mut f| f.read_to_string(&mut text)).map_err(|e| e.to_string()));
+	Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+	use logic::{Formula, Sequent};
+	
+	use file::ProofFile;
+	use system::{Fitch, Lemmon};
+	use super::*;
+	
+	#[test]
+	fn test_cli() {
+		let f = |s: &str| Formula::new(s).unwrap();
+		assert_eq!(classify(&f("Pv~P")), Classification::Tautology);
+		assert_eq!(classify(&f("P&~P")), Classification::Contradiction);
+		assert_eq!(classify(&f("P->(Q&R)")), Classification::Contingent);
+		assert_eq!(Classification::Contingent.to_string(), "contingent");
+		assert_eq!(truth_table(&f("P->Q"), false), "P Q | P->Q\nT T | T\nT F | F\nF T | T\nF F | T\n");
+		assert_eq!(taut(&["Pv~P".to_string(), "P->Q".to_string()]), FAILURE);
+		assert_eq!(taut(&["P->(Q->P)".to_string()]), SUCCESS);
+		assert_eq!(taut(&["P->".to_string()]), ERROR);
+		assert_eq!(run(&["proof.plc".to_string()]), None);
+		
+		let seq = Sequent::parse("P->Q, P |- Q").unwrap();
+		let file = ProofFile::parse("{1} 1. P->Q ; Premise\n{2} 2. P ; Premise\n{1, 2} 3. Q ; ->E 2, 1\n").unwrap();
+		assert!(check_sequent(&Lemmon, &file.lines, &seq).is_empty());
+		let file = ProofFile::parse("{1} 1. P->Q ; Premise\n{2} 2. P ; A\n{1, 2} 3. Q ; ->E 2, 1\n").unwrap();
+		let problems = check_sequent(&Lemmon, &file.lines, &seq);
+		println!("[test_cli] {:?}", problems);
+		assert_eq!(problems, vec!["the conclusion rests on an assumption that hasn't been discharged"]);
+		let file = ProofFile::parse("{} 1. P->Q ; Premise\n{} 2. | R ; A\n{} 3. | R ; R 2\n").unwrap();
+		assert_eq!(check_sequent(&Fitch, &file.lines, &seq).len(), 2);
+	}
+}
@@ -1,12 +1,36 @@
+use std::borrow::Cow;
+use std::cmp;
 use std::fmt::{self, Write, Display, Formatter};
 use std::mem;
 use std::ops::{Deref, DerefMut};
 
-use gtk::signal::Inhibit;
-use gdk::EventKey;
+use logic::{Formula, Token, Tokens, Sequent};
 
-use logic::{Token, Tokens};
-use logic::consts::ALLOWED_CHARS;
+use check::Diagnostic;
+use file::{self, Header, ProofFile, FileError, FileErrorKind};
+use input::{Change, EditorCommand, KeyInput, Motion};
+use just::{Justification, JustError};
+use library::Library;
+use prove::{self, ProveError};
+use rule::Rule;
+use system::{ProofSystem, Structure, Lemmon};
+
+/// Why the text typed in the dependency column isn't a list of dependencies.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DepsError {
+	/// Something that isn't a line number was found.
+	BadNumber(String),
+	/// A line number that isn't this line or an earlier one. Line numbers start at 0.
+	NotEarlier(usize),
+}
+impl Display for DepsError {
+	fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+		match self {
+			&DepsError::BadNumber(ref s) => write!(f, "expected a line number, found `{}`", s),
+			&DepsError::NotEarlier(no)   => write!(f, "line {} doesn't come before this line", no + 1),
+		}
+	}
+}
 
 #[derive(Clone)]
 pub struct Line {
@@ -14,10 +38,16 @@ pub struct Line {
 	pub no: usize,
 	/// A step of the proof. e.g. (P\^Q)->P. This is a vector of tokens that can be invalid.
 	pub step: Tokens,
-	/// A token string representing the method of the proof.
+	/// A token string representing the method of the proof. See `Line::justification` for the parsed form.
 	pub method: Tokens,
 	/// Line numbers that this depends on. Line numbers start at 0.
 	pub deps: Vec<usize>,
+	/// True if `deps` was entered by hand, so `Lines::update_deps` shouldn't overwrite it.
+	pub manual_deps: bool,
+	/// The text typed in the dependency column, while it is being edited. `None` if the column shows `deps`.
+	pub deps_text: Option<Tokens>,
+	/// How many subproofs the line is inside, in systems where assumptions open subproofs. See `fitch`.
+	pub depth: usize,
 }
 impl Line {
 	/// Constructs an empty line
@@ -27,6 +57,9 @@ impl Line {
 			step: Tokens::new(),
 			method: Tokens::new(),
 			deps: Vec::new(),
+			manual_deps: false,
+			deps_text: None,
+			depth: 0,
 		}
 	}
 	/// Constructs a line with the specified tokens in the `step` field.
@@ -36,6 +69,9 @@ impl Line {
 			step: step,
 			method: Tokens::new(),
 			deps: Vec::new(),
+			manual_deps: false,
+			deps_text: None,
+			depth: 0,
 		}
 	}
 	pub fn full(no: usize, step: Tokens, method: Tokens, deps: Vec<usize>) -> Line {
@@ -44,6 +80,9 @@ impl Line {
 			step: step,
 			method: method,
 			deps: deps,
+			manual_deps: false,
+			deps_text: None,
+			depth: 0,
 		}
 	}
 	
@@ -68,12 +107,16 @@ impl Line {
 			step_str.push('|');
 		}
 		
-		let mut dep_str = String::with_capacity(self.deps.len() * 2);
-		for (i, dep) in self.deps.iter().enumerate() {
-			try!(write!(dep_str, "{}", dep + 1));
-			if i < self.deps.len() - 1 {
-				dep_str.push_str(", ")
+		let deps = self.deps_tokens();
+		let mut dep_str = String::with_capacity(deps.len() + 1);
+		for (i, t) in deps.iter().enumerate() {
+			if c.no == self.no && c.col == Col::Deps && c.i == i {
+				dep_str.push('|');
 			}
+			try!(write!(dep_str, "{}", t));
+		}
+		if c.no == self.no && c.col == Col::Deps && c.i == deps.len() {
+			dep_str.push('|');
 		}
 		
 		let mut method_str = String::with_capacity(self.method.len() + 8);
@@ -106,6 +149,130 @@ impl Line {
 		f.pad(&format!("{: >3}. {: <20} {: <15} {{{}}}", self.no + 1, step_str, method_str, dep_str))
 	}
 	
+	/// Parses `self.method` into the rule used and the lines it cites.
+	pub fn justification(&self) -> Result<Justification, JustError> {
+		Justification::parse(&self.method)
+	}
+	/// Parses `self.method` like `justification`, also recognising the derived rules in `lib`.
+	pub fn justification_with(&self, lib: &Library) -> Result<Justification, JustError> {
+		Justification::parse_with(&self.method, lib)
+	}
+	/// Replaces `self.method` with the text form of `j`.
+	pub fn set_justification(&mut self, j: &Justification) {
+		self.method = j.to_tokens();
+	}
+	
+	/// Rewrites the line numbers cited in the method column, and the dependencies, using `map` to find the new
+	/// number of each line. References to lines that `map` gives no number for are replaced with `?` in the
+	/// method column, so that they show up as mistakes, and dropped from the dependencies. The sequent name in
+	/// brackets after `SI` and `TI` is left alone. Returns true if any reference was broken.
+	pub fn renumber<F: Fn(usize) -> Option<usize>>(&mut self, map: &F) -> bool {
+		self.deps = self.deps.iter().filter_map(|&d| map(d)).collect();
+		// Text being typed in the dependency column is replaced by the renumbered dependencies
+		self.deps_text = None;
+		if let Ok(j) = self.justification() {
+			if j.lines().into_iter().all(|c| map(c).is_some()) {
+				let renumbered = j.map_lines(|c| map(c).unwrap());
+				if renumbered != j {
+					self.set_justification(&renumbered);
+				}
+				return false;
+			}
+		}
+		
+		// The method can't be read, or cites a line that is gone, so the numbers in it are rewritten one by one
+		let mut broken = false;
+		let mut method = Vec::with_capacity(self.method.len());
+		let mut brackets = 0;
+		let mut i = 0;
+		while i < self.method.len() {
+			let start = i;
+			// `None` if the number is too big to be a line number
+			let mut n: Option<usize> = Some(0);
+			while brackets == 0 && i < self.method.len() {
+				match self.method[i] {
+					Token::Char(c) if c.is_digit(10) => {
+						let d = c.to_digit(10).unwrap() as usize;
+						n = n.and_then(|n| n.checked_mul(10)).and_then(|n| n.checked_add(d));
+					},
+					_ => break,
+				}
+				i += 1;
+			}
+			if i == start {
+				match self.method[i] {
+					Token::Char('(') => brackets += 1,
+					Token::Char(')') if brackets > 0 => brackets -= 1,
+					_ => {},
+				}
+				method.push(self.method[i]);
+				i += 1;
+				continue;
+			}
+			match n.and_then(|n| n.checked_sub(1)).map(|n| map(n)) {
+				Some(Some(new)) => method.extend((new + 1).to_string().chars().map(Token::Char)),
+				Some(None) => {
+					method.push(Token::Char('?'));
+					broken = true;
+				},
+				None => method.extend_from_slice(&self.method[start..i]),
+			}
+		}
+		self.method = Tokens::from_vec(method);
+		broken
+	}
+	
+	/// The tokens shown in the dependency column: the text being typed, or else `deps`, e.g. `1, 3`.
+	pub fn deps_tokens(&self) -> Tokens {
+		match self.deps_text {
+			Some(ref toks) => toks.clone(),
+			None => {
+				let s: Vec<String> = self.deps.iter().map(|d| (d + 1).to_string()).collect();
+				Tokens::from_str(&s.join(", "))
+			},
+		}
+	}
+	/// Parses the text of the dependency column: line numbers, starting at 1, separated by commas or spaces.
+	/// Each must be this line or an earlier one. The dependencies are returned sorted, starting at 0.
+	pub fn parse_deps(&self, toks: &[Token]) -> Result<Vec<usize>, DepsError> {
+		let s = format!("{}", Tokens::from_vec(toks.to_vec()));
+		let mut deps = Vec::new();
+		for part in s.split(|c: char| c == ',' || c.is_whitespace()).filter(|p| !p.is_empty()) {
+			match part.parse::<usize>() {
+				Ok(n) if n > self.no + 1 => return Err(DepsError::NotEarlier(n - 1)),
+				Ok(n) if n > 0 => deps.push(n - 1),
+				_ => return Err(DepsError::BadNumber(part.to_string())),
+			}
+		}
+		deps.sort();
+		deps.dedup();
+		Ok(deps)
+	}
+	/// Sets the text of the dependency column, as typed by hand. Valid dependencies are used in place of those
+	/// computed from the justification, and invalid ones leave `deps` as it was. Clearing the text makes the
+	/// dependencies automatic again.
+	pub fn set_deps_text(&mut self, toks: Tokens) {
+		if !toks.is_empty() {
+			if let Ok(deps) = self.parse_deps(&toks) {
+				self.deps = deps;
+			}
+		}
+		self.manual_deps = !toks.is_empty();
+		self.deps_text = Some(toks);
+	}
+	/// The problem with the text typed in the dependency column, if there is one.
+	pub fn deps_error(&self) -> Option<DepsError> {
+		match self.deps_text {
+			Some(ref toks) => self.parse_deps(toks).err(),
+			None => None,
+		}
+	}
+	
+	/// True if the method column has a reference broken by deleting the line it cited. See `renumber`.
+	pub fn has_broken_ref(&self) -> bool {
+		self.method.contains(&Token::Char('?'))
+	}
+	
 	/// True if `self.step` and `self.method` are empty
 	pub fn is_empty(&self) -> bool {
 		self.step.is_empty() && self.method.is_empty()
@@ -121,59 +288,322 @@ impl Display for Line {
 	fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
 		if !f.alternate() {
 			try!(write!(f, "{: >3}. {: <20} {: <15} {{", self.no + 1, self.step, self.method));
-			let len = self.deps.len();
-			for i in 0..len - 1 {
-				try!(write!(f, "{}, ", self.deps[i]));
-			}
-			try!(write!(f, "{}}}", self.deps[len - 1]));
 		} else {
 			try!(write!(f, "{: >3}. {: <#20} {: <#15} {{", self.no + 1, self.step, self.method));
-			let len = self.deps.len();
-			for i in 0..len - 1 {
-				try!(write!(f, "{}, ", self.deps[i]));
+		}
+		for (i, dep) in self.deps.iter().enumerate() {
+			if i != 0 {
+				try!(write!(f, ", "));
 			}
-			try!(write!(f, "{}}}", self.deps[len - 1]));
+			try!(write!(f, "{}", dep + 1));
 		}
+		try!(write!(f, "}}"));
 		Ok(())
 	}
 }
 
+/// A line for tests, with its step and method read from text.
+#[cfg(test)]
+pub fn line(no: usize, step: &str, method: &str, deps: Vec<usize>) -> Line {
+	Line::full(no, Tokens::from_str(step), Tokens::from_str(method), deps)
+}
+
 #[derive(Clone)]
 pub struct Lines {
-	inner: Vec<Line>
+	inner: Vec<Line>,
+	/// Comments between the lines, and the number of lines before each. See `ProofFile`. A comment stays before
+	/// the same line when lines are inserted, deleted or moved.
+	comments: Vec<(usize, String)>,
 }
 impl Lines {
 	/// Creates a lines object with one line.
 	pub fn new() -> Lines {
-		Lines {
-			inner: vec![Line::new(0)]
-		}
+		Lines::from_vec(vec![Line::new(0)])
 	}
 	/// Creates a lines object from a vector.
 	pub fn from_vec(v: Vec<Line>) -> Lines {
 		Lines {
-			inner: v
+			inner: v,
+			comments: Vec::new(),
 		}
 	}
+	/// Gets the comments between the lines, and the number of lines before each.
+	pub fn comments(&self) -> &[(usize, String)] {
+		&self.comments
+	}
+	/// Sets the comments between the lines. They must be in order.
+	pub fn set_comments(&mut self, comments: Vec<(usize, String)>) {
+		self.comments = comments;
+	}
 	
-	/// Inserts `nl` at `nl.no` in `lines`, and updates all line numbers in `lines`
+	/// Inserts `nl` at `nl.no` in `lines`, and updates all line numbers in `lines`, including the references to
+	/// them in other lines.
 	pub fn insert_line(&mut self, nl: Line) {
 		let no = nl.no;
-		for l in self.iter_mut() {
-			if l.no >= no {
-				l.no += 1;
-			}
-		}
+		self.renumber(|n| Some(if n >= no { n + 1 } else { n }));
 		self.insert(no, nl);
+		self.number_lines();
+	}
+	/// Inserts the lines of `block` at line `at`, and updates all line numbers in `lines`, including the
+	/// references to them in other lines. `block` can be numbered from any line, as when it is copied from
+	/// another proof: references within it are moved with it, and references to other lines are left alone.
+	pub fn insert_lines(&mut self, at: usize, mut block: Lines) {
+		let n = block.len();
+		let first = block.first().map(|l| l.no).unwrap_or(0);
+		self.renumber(|m| Some(if m >= at { m + n } else { m }));
+		block.renumber(|m| Some(if m >= first && m < first + n { m - first + at } else { m }));
+		for (k, l) in block.inner.into_iter().enumerate() {
+			self.inner.insert(at + k, l);
+		}
+		self.number_lines();
 	}
-	/// Deletes the line at `no` in `lines`, and updates all line numbers in `lines`
-	pub fn delete_line(&mut self, no: usize) {
+	/// Deletes the line at `no` in `lines`, and updates all line numbers in `lines`, including the references to
+	/// them in other lines. References to the deleted line are broken, and replaced with `?`. Returns the lines
+	/// with references that were broken.
+	pub fn delete_line(&mut self, no: usize) -> Vec<usize> {
 		self.inner.remove(no);
+		self.number_lines();
+		self.renumber(|n| if n == no {
+			None
+		} else if n > no {
+			Some(n - 1)
+		} else {
+			Some(n)
+		})
+	}
+	/// Moves the line at `from` so that it is at `to`, shifting the lines in between, and updates all line
+	/// numbers in `lines`, including the references to them in other lines.
+	pub fn move_line(&mut self, from: usize, to: usize) {
+		let l = self.inner.remove(from);
+		self.inner.insert(to, l);
+		self.number_lines();
+		self.renumber(|n| Some(if n == from {
+			to
+		} else if from < to && n > from && n <= to {
+			n - 1
+		} else if to < from && n >= to && n < from {
+			n + 1
+		} else {
+			n
+		}));
+	}
+	/// Renumbers the references in every line with `map`. See `Line::renumber`. Returns the lines with
+	/// references that were broken.
+	fn renumber<F: Fn(usize) -> Option<usize>>(&mut self, map: F) -> Vec<usize> {
+		let mut broken = Vec::new();
+		for l in self.inner.iter_mut() {
+			if l.renumber(&map) {
+				broken.push(l.no);
+			}
+		}
+		// A comment before a deleted line is kept before the line after it
+		for c in self.comments.iter_mut() {
+			c.0 = (c.0..c.0 + 2).filter_map(|n| map(n)).next().unwrap_or(c.0);
+		}
+		self.comments.sort_by_key(|c| c.0);
+		broken
+	}
+	/// Sets the number of each line to its position.
+	fn number_lines(&mut self) {
+		for (no, l) in self.inner.iter_mut().enumerate() {
+			l.no = no;
+		}
+	}
+	
+	/// Computes the dependencies of every line from its justification, in order, and fills them in.
+	/// Lines with `manual_deps` set are left alone. Lines without a valid justification are left alone.
+	/// Returns the numbers of the manual lines whose dependencies disagree with the computed ones.
+	pub fn update_deps(&mut self) -> Vec<usize> {
+		self.update_deps_with(&Lemmon, &Library::new())
+	}
+	/// Updates the dependencies like `update_deps`, using the dependency discipline of `sys` and the derived
+	/// rules in `lib`. Nothing is changed in systems that don't track dependencies.
+	pub fn update_deps_with<S: ProofSystem + ?Sized>(&mut self, sys: &S, lib: &Library) -> Vec<usize> {
+		let mut conflicts = Vec::new();
+		for no in 0..self.len() {
+			let deps = match self[no].justification_with(lib) {
+				Ok(j) => sys.deps(&j, no, self),
+				Err(_) => None,
+			};
+			if let Some(deps) = deps {
+				if !self[no].manual_deps {
+					self[no].deps = deps;
+				} else {
+					let mut found = self[no].deps.clone();
+					found.sort();
+					found.dedup();
+					if found != deps {
+						conflicts.push(no);
+					}
+				}
+			}
+		}
+		conflicts
+	}
+	
+	/// Tidies up the proof. Lines that the last line doesn't rest on, directly or through the lines it cites, are
+	/// removed, and so are lines that repeat an earlier line: the same formula with the same dependencies.
+	/// References to a repeated line are changed to the earlier line, and the remaining lines are renumbered.
+	/// Premises are kept even if they aren't used, so that the proof is still of the same sequent. Returns the
+	/// new number of each line, or of the line that replaces it, or `None` if it was removed.
+	pub fn clean(&mut self) -> Vec<Option<usize>> {
+		self.clean_with(&Lemmon, &Library::new())
+	}
+	/// Tidies up the proof like `clean`, recognising the derived rules in `lib`. Repeated lines are only merged
+	/// in systems that track dependencies, as otherwise a repeat may be in a different subproof. Nothing is
+	/// changed if a line that would be kept has a justification that can't be read, as the lines it cites aren't
+	/// known.
+	pub fn clean_with<S: ProofSystem + ?Sized>(&mut self, sys: &S, lib: &Library) -> Vec<Option<usize>> {
+		let len = self.len();
+		if len == 0 {
+			return Vec::new();
+		}
+		let justs: Vec<Option<Justification>> = self.iter().map(|l| l.justification_with(lib).ok()).collect();
+		let forms: Vec<Option<Formula>> = self.iter().map(|l| Formula::parse(&l.step).ok()).collect();
+		let deps: Vec<Vec<usize>> = self.iter().map(|l| {
+			let mut deps = l.deps.clone();
+			deps.sort();
+			deps.dedup();
+			deps
+		}).collect();
+		
+		// The earliest line that each line repeats, or the line itself. The last line is kept as the conclusion.
+		let mut same: Vec<usize> = (0..len).collect();
+		if sys.structure() == Structure::Deps {
+			for no in 0..len - 1 {
+				if forms[no].is_none() || justs[no].is_none() {
+					continue;
+				}
+				if let Some(e) = (0..no).find(|&e| same[e] == e && justs[e].is_some() && forms[e] == forms[no]
+						&& deps[e] == deps[no]) {
+					same[no] = e;
+				}
+			}
+		}
+		
+		// Keep the lines reachable from the conclusion and the premises
+		let mut keep = vec![false; len];
+		let mut stack = vec![len - 1];
+		for (no, j) in justs.iter().enumerate() {
+			if let &Some(ref j) = j {
+				if j.rule == Rule::Premise {
+					stack.push(no);
+				}
+			}
+		}
+		while let Some(no) = stack.pop() {
+			let no = same[no];
+			if keep[no] {
+				continue;
+			}
+			keep[no] = true;
+			match justs[no] {
+				Some(ref j) => stack.extend(j.lines().into_iter().filter(|&c| c < len)),
+				None => return (0..len).map(Some).collect(),
+			}
+		}
+		
+		let mut map = vec![None; len];
+		let mut next = 0;
+		for no in 0..len {
+			if keep[no] {
+				map[no] = Some(next);
+				next += 1;
+			}
+		}
+		for no in 0..len {
+			if map[no].is_none() {
+				map[no] = map[same[no]];
+			}
+		}
+		
+		for c in self.comments.iter_mut() {
+			c.0 = (c.0..len).find(|&no| keep[no]).map_or(next, |no| map[no].unwrap());
+		}
+		let old = mem::replace(&mut self.inner, Vec::new());
+		for (no, mut l) in old.into_iter().enumerate() {
+			if !keep[no] {
+				continue;
+			}
+			if let Some(ref j) = justs[no] {
+				let renumbered = j.map_lines(|c| map.get(c).and_then(|&m| m).unwrap_or(c));
+				if renumbered != *j {
+					l.set_justification(&renumbered);
+				}
+			}
+			l.deps = l.deps.iter().filter_map(|&d| map.get(d).and_then(|&m| m)).collect();
+			l.deps_text = None;
+			l.no = map[no].unwrap();
+			self.inner.push(l);
+		}
+		map
+	}
+	
+	/// Finds every occurrence of the tokens `find` in the step column, in order. Occurrences don't overlap. The
+	/// method column isn't searched, as letters would be found in the names of rules.
+	pub fn find_tokens(&self, find: &[Token]) -> Vec<Selection> {
+		let mut found = Vec::new();
+		if find.is_empty() {
+			return found;
+		}
+		for l in self.iter() {
+			let mut i = 0;
+			while i + find.len() <= l.step.len() {
+				if &l.step[i..i + find.len()] == find {
+					found.push(Selection::Tokens { no: l.no, col: Col::Step, start: i, end: i + find.len() });
+					i += find.len();
+				} else {
+					i += 1;
+				}
+			}
+		}
+		found
+	}
+	/// Finds every subformula of a step that is an instance of `pattern`, whose variables stand for any formula.
+	/// e.g. `¬¬P` finds every double negation. Returns the line of each, the subformula and the substitution
+	/// that makes it an instance of `pattern`.
+	pub fn find_formula(&self, pattern: &Formula) -> Vec<(usize, Formula, Vec<(char, Formula)>)> {
+		let mut found = Vec::new();
+		for l in self.iter() {
+			let f = match Formula::parse(&l.step) {
+				Ok(f) => f,
+				Err(_) => continue,
+			};
+			for sub in f.subformulas() {
+				let mut subst = Vec::new();
+				if pattern.matches(sub, &mut subst) {
+					found.push((l.no, sub.clone(), subst));
+				}
+			}
+		}
+		found
+	}
+	/// Replaces every occurrence of the tokens `find` in the step column with `with`. Returns how many were
+	/// replaced.
+	pub fn replace_tokens(&mut self, find: &[Token], with: &[Token]) -> usize {
+		let found = self.find_tokens(find);
+		// From the end, so that the positions of the rest are still right
+		for sel in found.iter().rev() {
+			if let &Selection::Tokens { no, start, end, .. } = sel {
+				let _ = self[no].step.splice(start..end, with.iter().cloned());
+			}
+		}
+		self.simplify();
+		found.len()
+	}
+	/// Replaces each variable in `subst` with the formula it maps to, at the same time, in every step. Brackets
+	/// are added where needed, so that e.g. replacing `P` with `R∧S` in `P→Q` gives `(R∧S)→Q`. A proof stays
+	/// valid, and becomes a proof of the substitution instance of its sequent. Returns the lines whose step isn't
+	/// a formula, which are left alone.
+	pub fn substitute(&mut self, subst: &[(char, Formula)]) -> Vec<usize> {
+		let mut skipped = Vec::new();
 		for l in self.iter_mut() {
-			if l.no >= no {
-				l.no -= 1;
+			match Formula::parse(&l.step) {
+				Ok(f) => l.step = f.substitute(subst).to_tokens(),
+				Err(_) => skipped.push(l.no),
 			}
 		}
+		skipped
 	}
 	
 	/// Simplifies all the lines that this structure holds.
@@ -185,10 +615,22 @@ impl Lines {
 	
 	/// Inserts token `tok` at `cursor` pos.
 	/// Returns Ok(n) with n being the number of tokens removed.
+	/// Only digits, commas and spaces can be typed in the dependency column.
 	pub fn insert_at(&mut self, cursor: &Cursor, tok: Token) -> Result<usize, ()> {
 		let toks = match cursor.col {
 			Col::Step   => &mut self[cursor.no].step,
 			Col::Method => &mut self[cursor.no].method,
+			Col::Deps   => {
+				match tok {
+					Token::Char(c) if c.is_digit(10) || c == ',' || c == ' ' => {},
+					_ => return Err(()),
+				}
+				let l = &mut self[cursor.no];
+				let mut toks = l.deps_tokens();
+				toks.insert(cursor.i, tok);
+				l.set_deps_text(toks);
+				return Ok(0);
+			},
 		};
 		toks.insert(cursor.i, tok);
 		let n = toks.simplify();
@@ -221,12 +663,23 @@ impl Display for Lines {
 	}
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Col {
 	Step,
 	Method,
+	/// The dependencies, which can be typed by hand. See `Line::set_deps_text`.
+	Deps,
+}
+/// The part of the proof between the cursor and where the selection was started.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Selection {
+	/// Tokens `start..end` of a column of line `no`, if the selection is within one column.
+	Tokens { no: usize, col: Col, start: usize, end: usize },
+	/// Lines `start..end`, if the selection spans more than one column.
+	Lines(usize, usize),
 }
-#[derive(Debug, PartialEq, Eq)]
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Cursor {
 	/// Line number
 	no: usize,
@@ -234,6 +687,11 @@ pub struct Cursor {
 	col: Col,
 	/// Index of token/string
 	i: usize,
+	/// The line, column and index the selection was started at, if there is one.
+	anchor: Option<(usize, Col, usize)>,
+	/// The index that `up` and `down` keep to on lines that are long enough, so that moving past a short line
+	/// doesn't move the cursor left. Set by the first move up or down, and cleared by any other move or edit.
+	goal: Option<usize>,
 }
 impl Cursor {
 	pub fn new() -> Cursor {
@@ -241,10 +699,123 @@ impl Cursor {
 			no: 0,
 			col: Col::Step,
 			i: 0,
+			anchor: None,
+			goal: None,
+		}
+	}
+	
+	/// The line, column and index in the column of the cursor.
+	pub fn position(&self) -> (usize, Col, usize) {
+		(self.no, self.col, self.i)
+	}
+	/// Starts a selection at the cursor, if there isn't one already. The selection is extended by moving the
+	/// cursor.
+	pub fn select(&mut self) {
+		if self.anchor.is_none() {
+			self.anchor = Some((self.no, self.col, self.i));
 		}
 	}
+	/// Selects every line.
+	pub fn select_all(&mut self, lines: &Lines) {
+		self.anchor = Some((0, Col::Step, 0));
+		self.no = lines.len() - 1;
+		self.col = Col::Deps;
+		self.end(lines);
+	}
+	/// Clears the selection.
+	pub fn deselect(&mut self) {
+		self.anchor = None;
+	}
+	/// Gets the selection, if there is one and it isn't empty.
+	pub fn selection(&self) -> Option<Selection> {
+		let (no, col, i) = match self.anchor {
+			Some(a) => a,
+			None => return None,
+		};
+		if no == self.no && col == self.col {
+			if i == self.i {
+				None
+			} else {
+				Some(Selection::Tokens { no: no, col: col, start: cmp::min(i, self.i), end: cmp::max(i, self.i) })
+			}
+		} else {
+			Some(Selection::Lines(cmp::min(no, self.no), cmp::max(no, self.no) + 1))
+		}
+	}
+	/// Deletes the selection, leaving the cursor where it started. References to deleted lines are broken, as in
+	/// `Lines::delete_line`. If every line is deleted, an empty line is left. Error if nothing is selected.
+	pub fn delete_selection(&mut self, lines: &mut Lines) -> Result<(), ()> {
+		let sel = try!(self.selection().ok_or(()));
+		self.anchor = None;
+		match sel {
+			Selection::Tokens { no, col, start, end } => {
+				match col {
+					Col::Step   => { let _ = lines[no].step.drain(start..end); },
+					Col::Method => { let _ = lines[no].method.drain(start..end); },
+					Col::Deps   => {
+						let mut deps = lines[no].deps_tokens();
+						let _ = deps.drain(start..end);
+						lines[no].set_deps_text(deps);
+					},
+				}
+				self.i = start;
+			},
+			Selection::Lines(start, end) => {
+				for no in (start..end).rev() {
+					lines.delete_line(no);
+				}
+				if lines.is_empty() {
+					lines.push(Line::new(0));
+				}
+				self.no = cmp::min(start, lines.len() - 1);
+				self.col = Col::Step;
+				self.i = 0;
+			},
+		}
+		Ok(())
+	}
+	/// The selection as text: the tokens in unicode, or the lines as the steps of a `.plc` file. See
+	/// `file::write_lines`.
+	pub fn selection_text(&self, lines: &Lines) -> Option<String> {
+		self.selection().map(|sel| match sel {
+			Selection::Tokens { no, col, start, end } => {
+				let c = Cursor { no: no, col: col, i: 0, anchor: None, goal: None };
+				format!("{:#}", Tokens::from_vec(c.field(lines)[start..end].to_vec()))
+			},
+			Selection::Lines(start, end) => file::write_lines(&lines[start..end]),
+		})
+	}
+	/// Pastes `text` at the cursor, replacing the selection. Text copied from whole lines is added as lines
+	/// after the cursor's line, or in place of it if it is empty, and the cursor is left at the end of them.
+	/// Other text is typed into the cursor's column, up to the end of its first line.
+	pub fn paste(&mut self, lines: &mut Lines, text: &str) -> Result<(), FileError> {
+		let _ = self.delete_selection(lines);
+		if text.trim_left().starts_with('{') {
+			let block = try!(file::parse_lines(text));
+			if block.is_empty() {
+				return Ok(());
+			}
+			let n = block.len();
+			let at = if lines[self.no].is_empty() {
+				lines.delete_line(self.no);
+				self.no
+			} else {
+				self.no + 1
+			};
+			lines.insert_lines(at, block);
+			self.no = at + n - 1;
+			self.col = Col::Step;
+			self.end(lines);
+		} else {
+			for t in Tokens::from_str(text.lines().next().unwrap_or("")).iter() {
+				let _ = self.insert(lines, *t);
+			}
+		}
+		Ok(())
+	}
 	/// Move the cursor to the right. Wrap at end of line. Error at end of text.
 	pub fn right(&mut self, lines: &Lines) -> Result<(), ()> {
+		self.goal = None;
 		let l = match lines.get(self.no) {
 			Some(l) => l,
 			None    => { *self = Cursor::new(); return Err(()); },
@@ -260,6 +831,13 @@ impl Cursor {
 			}
 			Col::Method => {
 				if self.i > l.method.len() {
+					self.col = Col::Deps;
+					self.i = 0;
+				}
+				Ok(())
+			}
+			Col::Deps => {
+				if self.i > l.deps_tokens().len() {
 					if self.no < lines.len() - 1 {
 						self.no += 1;
 						self.col = Col::Step;
@@ -277,6 +855,7 @@ impl Cursor {
 	}
 	/// Move the cursor to the left. Wrap at start of line. Error at start of text.
 	pub fn left(&mut self, lines: &Lines) -> Result<(), ()> {
+		self.goal = None;
 		let l = match lines.get(self.no) {
 			Some(l) => l,
 			None    => { *self = Cursor::new(); return Err(()); },
@@ -287,9 +866,9 @@ impl Cursor {
 				Col::Step => {
 					if self.no > 0 {
 						self.no -= 1;
-						self.col = Col::Method;
+						self.col = Col::Deps;
 						self.i = match lines.get(self.no) {
-							Some(l) => l.method.len(),
+							Some(l) => l.deps_tokens().len(),
 							None    => { *self = Cursor::new(); return Err(()); },
 						};
 					}
@@ -298,6 +877,10 @@ impl Cursor {
 					self.col = Col::Step;
 					self.i = l.step.len();
 				}
+				Col::Deps => {
+					self.col = Col::Method;
+					self.i = l.method.len();
+				}
 			}
 		} else {
 			self.i -= 1;
@@ -305,18 +888,152 @@ impl Cursor {
 		Ok(())
 	}
 	
+	/// Move the cursor up a line, staying in the same column, and as near the index it was at before the last
+	/// sideways move as the line allows. Error on the first line.
+	pub fn up(&mut self, lines: &Lines) -> Result<(), ()> {
+		if self.no == 0 {
+			return Err(());
+		}
+		let goal = self.goal.unwrap_or(self.i);
+		self.no -= 1;
+		self.i = cmp::min(goal, self.field(lines).len());
+		self.goal = Some(goal);
+		Ok(())
+	}
+	/// Move the cursor down a line, like `up`. Error on the last line.
+	pub fn down(&mut self, lines: &Lines) -> Result<(), ()> {
+		if self.no + 1 >= lines.len() {
+			return Err(());
+		}
+		let goal = self.goal.unwrap_or(self.i);
+		self.no += 1;
+		self.i = cmp::min(goal, self.field(lines).len());
+		self.goal = Some(goal);
+		Ok(())
+	}
+	/// Move the cursor to the start of its column.
+	pub fn home(&mut self) {
+		self.goal = None;
+		self.i = 0;
+	}
+	/// Move the cursor to the end of its column.
+	pub fn end(&mut self, lines: &Lines) {
+		self.goal = None;
+		self.i = self.field(lines).len();
+	}
+	/// Move the cursor to token `i` of column `col` of line `no`, or as near as the proof allows.
+	pub fn move_to(&mut self, lines: &Lines, no: usize, col: Col, i: usize) {
+		self.goal = None;
+		self.no = cmp::min(no, lines.len().saturating_sub(1));
+		self.col = col;
+		self.i = cmp::min(i, self.field(lines).len());
+	}
+	/// Move the cursor to the end of the next column, wrapping to the first column of the next line. Error in the
+	/// last column of the last line.
+	pub fn next_col(&mut self, lines: &Lines) -> Result<(), ()> {
+		match self.col {
+			Col::Step => self.col = Col::Method,
+			Col::Method => self.col = Col::Deps,
+			Col::Deps => {
+				if self.no + 1 >= lines.len() {
+					return Err(());
+				}
+				self.no += 1;
+				self.col = Col::Step;
+			},
+		}
+		self.end(lines);
+		Ok(())
+	}
+	/// Move the cursor to the end of the previous column, wrapping to the last column of the previous line. Error
+	/// in the first column of the first line.
+	pub fn prev_col(&mut self, lines: &Lines) -> Result<(), ()> {
+		match self.col {
+			Col::Deps => self.col = Col::Method,
+			Col::Method => self.col = Col::Step,
+			Col::Step => {
+				if self.no == 0 {
+					return Err(());
+				}
+				self.no -= 1;
+				self.col = Col::Deps;
+			},
+		}
+		self.end(lines);
+		Ok(())
+	}
+	/// Move the cursor right past the next word: a run of letters and digits, or any other single token. Spaces
+	/// before it are skipped. At the end of a column, moves like `right`.
+	pub fn word_right(&mut self, lines: &Lines) -> Result<(), ()> {
+		self.goal = None;
+		let toks = self.field(lines);
+		if self.i >= toks.len() {
+			return self.right(lines);
+		}
+		while self.i < toks.len() && is_space(toks[self.i]) {
+			self.i += 1;
+		}
+		if self.i < toks.len() {
+			if is_word(toks[self.i]) {
+				while self.i < toks.len() && is_word(toks[self.i]) {
+					self.i += 1;
+				}
+			} else {
+				self.i += 1;
+			}
+		}
+		Ok(())
+	}
+	/// Move the cursor left past the previous word, like `word_right`. At the start of a column, moves like `left`.
+	pub fn word_left(&mut self, lines: &Lines) -> Result<(), ()> {
+		self.goal = None;
+		let toks = self.field(lines);
+		if self.i == 0 {
+			return self.left(lines);
+		}
+		while self.i > 0 && is_space(toks[self.i - 1]) {
+			self.i -= 1;
+		}
+		if self.i > 0 {
+			if is_word(toks[self.i - 1]) {
+				while self.i > 0 && is_word(toks[self.i - 1]) {
+					self.i -= 1;
+				}
+			} else {
+				self.i -= 1;
+			}
+		}
+		Ok(())
+	}
+	
+	/// Clears the selection and moves the cursor back inside `lines`, after they have been changed around it.
+	fn clamp(&mut self, lines: &Lines) {
+		self.anchor = None;
+		self.goal = None;
+		self.no = cmp::min(self.no, lines.len().saturating_sub(1));
+		self.i = cmp::min(self.i, self.field(lines).len());
+	}
+	
+	/// The tokens of the column the cursor is in.
+	fn field<'a>(&self, lines: &'a Lines) -> Cow<'a, Tokens> {
+		match self.col {
+			Col::Step   => Cow::Borrowed(&lines[self.no].step),
+			Col::Method => Cow::Borrowed(&lines[self.no].method),
+			Col::Deps   => Cow::Owned(lines[self.no].deps_tokens()),
+		}
+	}
+	
 	/// Is end of tokens
-	/// Returns true if at the end of `Col::Step` or `Col::Method`
+	/// Returns true if at the end of the column the cursor is in
 	fn is_eot(&self, lines: &Lines) -> bool {
-		self.i == match self.col {
-			Col::Step   => lines[self.no].step.len(),
-			Col::Method => lines[self.no].method.len(),
-		}
+		self.i == self.field(lines).len()
 	}
 	
-	/// Delete the character in front of the cursor. If on an empty line && at the end, delete line. If no chars in front, returns `Err(())`.
+	/// Delete the character in front of the cursor, or the selection if there is one. If on an empty line && at the end, delete line. If no chars in front, returns `Err(())`.
 	pub fn delete(&mut self, lines: &mut Lines) -> Result<(), ()> {
-		if lines[self.no].is_empty() && self.col == Col::Method {
+		if self.selection().is_some() {
+			self.delete_selection(lines)
+		} else if lines[self.no].is_empty() && self.col == Col::Method {
 			if lines.len() == 1 {
 				return Err(());
 			}
@@ -334,13 +1051,22 @@ impl Cursor {
 			match self.col {
 				Col::Step   => { let _ = lines[self.no].step.remove(self.i); },
 				Col::Method => { let _ = lines[self.no].method.remove(self.i); },
+				Col::Deps   => {
+					let mut deps = lines[self.no].deps_tokens();
+					let _ = deps.remove(self.i);
+					lines[self.no].set_deps_text(deps);
+				},
 			}
 			Ok(())
 		}
 	}
-	/// Delete the character behind the character. If on an empty line && at the start, delete line. If no chars behind, returns `Err(())`
+	/// Delete the character behind the character, or the selection if there is one. If on an empty line && at the start, delete line. If no chars behind, returns `Err(())`
 	pub fn backspace(&mut self, lines: &mut Lines) -> Result<(), ()> {
-		if self.col == Col::Method && lines[self.no].method.is_empty() {
+		if self.selection().is_some() {
+			self.delete_selection(lines)
+		} else if self.col == Col::Method && lines[self.no].method.is_empty() {
+			self.left(lines)
+		} else if self.col == Col::Deps && self.i == 0 {
 			self.left(lines)
 		} else if lines[self.no].is_empty() && self.col == Col::Step {
 			if lines.len() == 1 {
@@ -360,19 +1086,20 @@ impl Cursor {
 			match self.col {
 				Col::Step   => { let _ = lines[self.no].step.remove(self.i - 1); },
 				Col::Method => { let _ = lines[self.no].method.remove(self.i - 1); },
+				Col::Deps   => {
+					let mut deps = lines[self.no].deps_tokens();
+					let _ = deps.remove(self.i - 1);
+					lines[self.no].set_deps_text(deps);
+				},
 			}
 			self.i -= 1;
 			Ok(())
 		}
 	}
-	/// Add a newline to `lines` if the cursor is at the end of the line.
+	/// Add an empty line below the cursor's line, wherever the cursor is in it, and move the cursor to it.
 	pub fn newline(&mut self, lines: &mut Lines) -> Result<(), ()> {
-		if !self.is_eot(lines) {
-			// Don't place a newline if the cursor isn't at the end of the line.
-			return Err(());
-		}
-		
 		lines.insert_line(Line::new(self.no + 1));
+		self.anchor = None;
 		
 		self.no += 1;
 		self.col = Col::Step;
@@ -380,8 +1107,20 @@ impl Cursor {
 		
 		Ok(())
 	}
-	/// Inserts a token at the cursor pos in `lines`. Simplifies the line affected.
+	/// Add an empty line above the cursor's line, and move the cursor to it.
+	pub fn newline_above(&mut self, lines: &mut Lines) -> Result<(), ()> {
+		lines.insert_line(Line::new(self.no));
+		self.anchor = None;
+		
+		self.col = Col::Step;
+		self.i = 0;
+		
+		Ok(())
+	}
+	/// Inserts a token at the cursor pos in `lines`, in place of the selection if there is one. Simplifies the
+	/// line affected.
 	pub fn insert(&mut self, lines: &mut Lines, c: Token) -> Result<(), ()> {
+		let _ = self.delete_selection(lines);
 		let ret = lines.insert_at(self, c);
 		if let Ok(n) = ret {
 			self.i += 1;
@@ -391,71 +1130,565 @@ impl Cursor {
 	}
 }
 
-pub struct Editor {
+/// The kinds of edit recorded in the undo history.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EditKind {
+	/// Typing a character. Consecutive characters typed on the same line are undone together.
+	Insert,
+	/// Deleting a character, or an empty line.
+	Delete,
+	/// Adding a line.
+	Newline,
+	/// Moving a line up or down.
+	Move,
+	/// Entering a line's dependencies by hand.
+	Deps,
+	/// Cutting or pasting.
+	Paste,
+	/// Replacing the whole proof, e.g. by loading a file.
+	Replace,
+}
+
+/// True if `t` is a space, which `Cursor::word_right` and `Cursor::word_left` skip.
+fn is_space(t: Token) -> bool {
+	match t {
+		Token::Char(c) => c.is_whitespace(),
+		_ => false,
+	}
+}
+
+/// True if `t` is part of a word, for `Cursor::word_right` and `Cursor::word_left`.
+fn is_word(t: Token) -> bool {
+	match t {
+		Token::Char(c) => c.is_alphanumeric(),
+		_ => false,
+	}
+}
+
+/// The most edits that can be undone.
+const HISTORY_LIMIT: usize = 500;
+
+/// The undo and redo stacks of an `Editor`. Each entry is the proof, cursor and file header as they were before
+/// an edit.
+struct History {
+	undo: Vec<(Lines, Cursor, Header)>,
+	redo: Vec<(Lines, Cursor, Header)>,
+	/// The kind of the last edit and the line it was on, so that typing can be grouped.
+	last: Option<(EditKind, usize)>,
+}
+impl History {
+	fn new() -> History {
+		History {
+			undo: Vec::new(),
+			redo: Vec::new(),
+			last: None,
+		}
+	}
+	
+	/// Records `before` as the state before an edit of `kind` on line `no`.
+	fn push(&mut self, kind: EditKind, no: usize, before: (Lines, Cursor, Header)) {
+		let group = kind == EditKind::Insert && self.last == Some((kind, no));
+		if !group {
+			self.undo.push(before);
+			if self.undo.len() > HISTORY_LIMIT {
+				self.undo.remove(0);
+			}
+		}
+		self.redo.clear();
+		self.last = Some((kind, no));
+	}
+}
+
+pub struct Editor<S: ProofSystem = Lemmon> {
+	/// The proof system the proof is written in.
+	system: S,
 	lines: Lines,
 	cursor: Cursor,
+	/// Lines whose hand-entered dependencies disagree with their justification.
+	dep_conflicts: Vec<usize>,
+	/// Lines whose dependency column has text that isn't a list of dependencies, and what is wrong with it.
+	dep_errors: Vec<(usize, DepsError)>,
+	/// Lines that cite a line that has been deleted.
+	broken_refs: Vec<usize>,
+	/// The sequents that `SI` and `TI` lines can cite.
+	library: Library,
+	/// The header of the file the proof was loaded from, which is written back when it is saved.
+	header: Header,
+	history: History,
+	/// Called with each change made by a command. See `on_change`.
+	listeners: Vec<Box<FnMut(&Change)>>,
 }
 
 impl Editor {
-	/// Constructs a new editor
+	/// Constructs a new editor for Lemmon-style proofs, with a sample proof.
 	pub fn new() -> Editor {
-		Editor {
-			lines: Lines::from_vec(vec![
-				Line::full(0, Tokens::from_str("P"),     Tokens::from_str("Premise"), vec![0]),
-				Line::full(1, Tokens::from_str("¬¬P"),   Tokens::from_str("¬I 1"), vec![0]),
-				Line::full(2, Tokens::from_str("P->Q"),  Tokens::from_str("Premise"), vec![2]),
-				Line::full(3, Tokens::from_str("Q"),     Tokens::from_str("->E 1, 3"), vec![0, 2]),
-				Line::full(4, Tokens::from_str("R<->S"), Tokens::from_str("Premise"), vec![4]),
-				Line::full(5, Tokens::from_str("PvT"),   Tokens::from_str("vI 1"), vec![0]),
-			]),
+		Editor::with_system(Lemmon)
+	}
+}
+impl<S: ProofSystem> Editor<S> {
+	/// Constructs a new editor for proofs in `system`, with the sample proof if it can be written in the system.
+	pub fn with_system(system: S) -> Editor<S> {
+		let sample = Lines::from_vec(vec![
+			Line::full(0, Tokens::from_str("P"),     Tokens::from_str("Premise"), vec![0]),
+			Line::full(1, Tokens::from_str("¬¬P"),   Tokens::from_str("DN 1"), vec![0]),
+			Line::full(2, Tokens::from_str("P->Q"),  Tokens::from_str("Premise"), vec![2]),
+			Line::full(3, Tokens::from_str("Q"),     Tokens::from_str("->E 1, 3"), vec![0, 2]),
+			Line::full(4, Tokens::from_str("R<->S"), Tokens::from_str("Premise"), vec![4]),
+			Line::full(5, Tokens::from_str("PvT"),   Tokens::from_str("vI 1"), vec![0]),
+		]);
+		let mut ed = Editor {
+			lines: system.from_lemmon(&sample).unwrap_or(Lines::new()),
+			system: system,
 			cursor: Cursor::new(),
-		}
+			dep_conflicts: Vec::new(),
+			dep_errors: Vec::new(),
+			broken_refs: Vec::new(),
+			library: Library::standard(),
+			header: Header::new(),
+			history: History::new(),
+			listeners: Vec::new(),
+		};
+		ed.update_deps();
+		ed
 	}
 	/// Gets a ref to `self.lines`.
 	pub fn lines(&self) -> &Lines {
 		&self.lines
 	}
+	/// Gets the proof system the proof is written in.
+	pub fn system(&self) -> &S {
+		&self.system
+	}
 	/// Gets a ref to `self.cursor`.
 	pub fn cursor(&self) -> &Cursor {
 		&self.cursor
 	}
-	/// Handles the input given to it, and whether to pass the input on or not.
-	pub fn handle_input(&mut self, e: &EventKey) -> Inhibit {
-		use gdk::enums::key;
-		use gdk::{keyval_to_unicode, keyval_name};
-		
-		let c = keyval_to_unicode(e.keyval).unwrap_or(' ');
-		let name = keyval_name(e.keyval).unwrap_or(" ".to_string());
-		println!("keypress: {0:#08x} : {1} : {2}", e.keyval, c, name);
-		
-		match unsafe { mem::transmute(e.keyval) } {
-			key::Right => {
-				let _ = self.cursor.right(&self.lines);
+	/// Gets the lines whose hand-entered dependencies disagree with what their justification gives.
+	pub fn dep_conflicts(&self) -> &[usize] {
+		&self.dep_conflicts
+	}
+	/// Gets the lines whose dependency column can't be read, and what is wrong with each.
+	pub fn dep_errors(&self) -> &[(usize, DepsError)] {
+		&self.dep_errors
+	}
+	/// Gets the lines that cite a line that has been deleted. The broken references are shown as `?`.
+	pub fn broken_refs(&self) -> &[usize] {
+		&self.broken_refs
+	}
+	/// Recomputes the dependencies of every line that wasn't entered by hand, and checks those that were.
+	pub fn update_deps(&mut self) {
+		self.dep_conflicts = self.lines.update_deps_with(&self.system, &self.library);
+		self.broken_refs = self.lines.iter().filter(|l| l.has_broken_ref()).map(|l| l.no).collect();
+		self.dep_errors = self.lines.iter().filter_map(|l| l.deps_error().map(|e| (l.no, e))).collect();
+		// A cleared dependency column shows the automatic dependencies once the cursor has left it
+		let cursor = &self.cursor;
+		for l in self.lines.iter_mut() {
+			if !l.manual_deps && (cursor.no != l.no || cursor.col != Col::Deps) {
+				l.deps_text = None;
+			}
+		}
+	}
+	/// Makes an edit with `f`, recording it in the undo history if it succeeds.
+	fn edit<F: FnOnce(&mut Cursor, &mut Lines) -> Result<(), ()>>(&mut self, kind: EditKind, f: F) -> Result<(), ()> {
+		let before = (self.lines.clone(), self.cursor.clone(), self.header.clone());
+		try!(f(&mut self.cursor, &mut self.lines));
+		self.cursor.goal = None;
+		self.history.push(kind, before.1.no, before);
+		Ok(())
+	}
+	/// Types `t` at the cursor.
+	pub fn insert(&mut self, t: Token) -> Result<(), ()> {
+		self.edit(EditKind::Insert, |c, lines| c.insert(lines, t))
+	}
+	/// Deletes the token in front of the cursor, or the line if it is empty. See `Cursor::delete`.
+	pub fn delete(&mut self) -> Result<(), ()> {
+		self.edit(EditKind::Delete, |c, lines| c.delete(lines))
+	}
+	/// Deletes the token behind the cursor, or the line if it is empty. See `Cursor::backspace`.
+	pub fn backspace(&mut self) -> Result<(), ()> {
+		self.edit(EditKind::Delete, |c, lines| c.backspace(lines))
+	}
+	/// Adds a line below the cursor's line. See `Cursor::newline`.
+	pub fn newline(&mut self) -> Result<(), ()> {
+		self.edit(EditKind::Newline, |c, lines| c.newline(lines))
+	}
+	/// Adds a line above the cursor's line. See `Cursor::newline_above`.
+	pub fn newline_above(&mut self) -> Result<(), ()> {
+		self.edit(EditKind::Newline, |c, lines| c.newline_above(lines))
+	}
+	/// Moves the line the cursor is on up one place, keeping the cursor on it. Returns `Err(())` on the first line.
+	pub fn move_line_up(&mut self) -> Result<(), ()> {
+		self.edit(EditKind::Move, |c, lines| {
+			if c.no == 0 {
+				return Err(());
+			}
+			lines.move_line(c.no, c.no - 1);
+			c.no -= 1;
+			Ok(())
+		})
+	}
+	/// Moves the line the cursor is on down one place, keeping the cursor on it. Returns `Err(())` on the last
+	/// line.
+	pub fn move_line_down(&mut self) -> Result<(), ()> {
+		self.edit(EditKind::Move, |c, lines| {
+			if c.no + 1 >= lines.len() {
+				return Err(());
+			}
+			lines.move_line(c.no, c.no + 1);
+			c.no += 1;
+			Ok(())
+		})
+	}
+	/// Sets the dependencies of line `no` by hand, so that they aren't recomputed from its justification.
+	pub fn set_deps(&mut self, no: usize, deps: Vec<usize>) -> Result<(), ()> {
+		let was_broken = self.broken_refs.clone();
+		try!(self.edit(EditKind::Deps, |_, lines| {
+			match lines.get_mut(no) {
+				Some(l) => {
+					l.deps = deps;
+					l.deps_text = None;
+					l.manual_deps = true;
+					Ok(())
+				},
+				None => Err(()),
+			}
+		}));
+		self.update_deps();
+		self.notify_edit(&was_broken);
+		Ok(())
+	}
+	/// Gets the selected part of the proof, if there is one.
+	pub fn selection(&self) -> Option<Selection> {
+		self.cursor.selection()
+	}
+	/// Selects every line.
+	pub fn select_all(&mut self) {
+		self.cursor.select_all(&self.lines);
+	}
+	/// Gets the selection as text for the clipboard. See `Cursor::selection_text`.
+	pub fn copy(&self) -> Option<String> {
+		self.cursor.selection_text(&self.lines)
+	}
+	/// Deletes the selection, returning it as text for the clipboard. See `EditorCommand::Cut`.
+	pub fn cut(&mut self) -> Option<String> {
+		match self.execute(EditorCommand::Cut) {
+			Some(Change::Cut { text, .. }) => Some(text),
+			_ => None,
+		}
+	}
+	/// Pastes text from the clipboard at the cursor. See `Cursor::paste`. Nothing is changed if copied lines
+	/// can't be read.
+	pub fn paste(&mut self, text: &str) -> Result<(), FileError> {
+		if text.trim_left().starts_with('{') {
+			try!(file::parse_lines(text));
+		}
+		self.execute(EditorCommand::Paste(text.to_string()));
+		Ok(())
+	}
+	/// Selects the next occurrence of the tokens `find` after the cursor, going back to the start of the proof
+	/// after the end. Returns `Err(())` if there isn't one. See `Lines::find_tokens`.
+	pub fn find_next(&mut self, find: &[Token]) -> Result<(), ()> {
+		let found = self.lines.find_tokens(find);
+		let here = (self.cursor.no, self.cursor.col, self.cursor.i);
+		let next = found.iter().find(|sel| match **sel {
+			Selection::Tokens { no, col, start, .. } => (no, col, start) >= here,
+			_ => false,
+		}).or(found.first());
+		if let Some(&Selection::Tokens { no, col, start, end }) = next {
+			self.cursor = Cursor { no: no, col: col, i: end, anchor: Some((no, col, start)), goal: None };
+			self.history.last = None;
+			Ok(())
+		} else {
+			Err(())
+		}
+	}
+	/// Replaces every occurrence of the tokens `find` with `with`. Returns how many were replaced.
+	pub fn replace_all(&mut self, find: &[Token], with: &[Token]) -> usize {
+		let was_broken = self.broken_refs.clone();
+		let mut n = 0;
+		let _ = self.edit(EditKind::Replace, |c, lines| {
+			n = lines.replace_tokens(find, with);
+			if n == 0 {
+				return Err(());
+			}
+			c.clamp(lines);
+			Ok(())
+		});
+		self.update_deps();
+		if n > 0 {
+			self.notify_edit(&was_broken);
+		}
+		n
+	}
+	/// Makes the proof into a proof of a substitution instance of its sequent, replacing each variable in
+	/// `subst` with the formula it maps to. The sequent in the header is changed to match, and is undone with the
+	/// proof. Returns the lines whose step isn't a formula, which are left alone. See `Lines::substitute`.
+	pub fn substitute(&mut self, subst: &[(char, Formula)]) -> Vec<usize> {
+		let was_broken = self.broken_refs.clone();
+		let mut skipped = Vec::new();
+		let _ = self.edit(EditKind::Replace, |c, lines| {
+			skipped = lines.substitute(subst);
+			c.clamp(lines);
+			Ok(())
+		});
+		if let Some(ref mut seq) = self.header.sequent {
+			*seq = Sequent::new(seq.premises.iter().map(|p| p.substitute(subst)).collect(),
+				seq.conclusion.substitute(subst));
+		}
+		self.update_deps();
+		self.notify_edit(&was_broken);
+		skipped
+	}
+	/// Undoes the last edit, putting the cursor back where it was. Returns `Err(())` if there is nothing to undo.
+	pub fn undo(&mut self) -> Result<(), ()> {
+		let (lines, cursor, header) = try!(self.history.undo.pop().ok_or(()));
+		self.history.redo.push((mem::replace(&mut self.lines, lines), mem::replace(&mut self.cursor, cursor),
+			mem::replace(&mut self.header, header)));
+		self.history.last = None;
+		self.update_deps();
+		Ok(())
+	}
+	/// Redoes the last edit undone. Returns `Err(())` if there is nothing to redo.
+	pub fn redo(&mut self) -> Result<(), ()> {
+		let (lines, cursor, header) = try!(self.history.redo.pop().ok_or(()));
+		self.history.undo.push((mem::replace(&mut self.lines, lines), mem::replace(&mut self.cursor, cursor),
+			mem::replace(&mut self.header, header)));
+		self.history.last = None;
+		self.update_deps();
+		Ok(())
+	}
+	/// Gets a ref to the library of sequents and derived rules.
+	pub fn library(&self) -> &Library {
+		&self.library
+	}
+	/// Gets a mutable ref to the library of sequents and derived rules, so that it can be extended.
+	pub fn library_mut(&mut self) -> &mut Library {
+		&mut self.library
+	}
+	/// Checks the proof being edited, returning the problems found on each line.
+	pub fn check(&self) -> Vec<Diagnostic> {
+		self.system.check(&self.lines, &self.library)
+	}
+	/// What is wrong with line `no`, if anything, as a message for the user: the first diagnostic for the line,
+	/// or else a problem with its dependencies or a citation of a deleted line.
+	pub fn problem(&self, no: usize) -> Option<String> {
+		self.problem_with(no, &self.check())
+	}
+	/// What is wrong with line `no`, as with `problem`, given the diagnostics from `check`, so that a frontend
+	/// that has already checked the proof doesn't check it again.
+	pub fn problem_with(&self, no: usize, diagnostics: &[Diagnostic]) -> Option<String> {
+		if let Some(d) = diagnostics.iter().find(|d| d.no == no) {
+			return Some(d.to_string());
+		}
+		if let Some(&(_, ref e)) = self.dep_errors.iter().find(|&&(n, _)| n == no) {
+			return Some(format!("line {}: {}", no + 1, e));
+		}
+		if self.broken_refs.contains(&no) {
+			return Some(format!("line {}: cites a deleted line", no + 1));
+		}
+		if self.dep_conflicts.contains(&no) {
+			return Some(format!("line {}: the dependencies typed aren't those the method gives", no + 1));
+		}
+		None
+	}
+	/// Replaces the proof being edited with an automatically found proof of `seq`, written in the editor's
+	/// proof system.
+	pub fn prove(&mut self, seq: &Sequent) -> Result<(), ProveError> {
+		let lines = try!(prove::prove(seq));
+		let lines = try!(self.system.from_lemmon(&lines).map_err(ProveError::Convert));
+		let _ = self.edit(EditKind::Replace, |c, l| {
+			*l = lines;
+			*c = Cursor::new();
+			Ok(())
+		});
+		self.update_deps();
+		self.notify_edit(&[]);
+		Ok(())
+	}
+	/// Gets the header of the file the proof was loaded from.
+	pub fn header(&self) -> &Header {
+		&self.header
+	}
+	/// Replaces the proof being edited with the one in the `.plc` file `text`. Dependencies that differ from
+	/// what the justification gives are kept as if they were entered by hand. Nothing is changed if the file
+	/// can't be read, or if it is for a different proof system. Undoing the load brings back the old header too.
+	pub fn load(&mut self, text: &str) -> Result<(), FileError> {
+		let file = try!(ProofFile::parse(text));
+		if let Some(ref name) = file.header.system {
+			if name.to_lowercase() != self.system.name().to_lowercase() {
+				return Err(FileError {
+					line: file.header_line("system").unwrap_or(0),
+					kind: FileErrorKind::System(name.clone()),
+				});
+			}
+		}
+		let mut lines = file.lines;
+		if lines.is_empty() {
+			lines = Lines::new();
+		}
+		lines.set_comments(file.comments);
+		for l in lines.iter_mut() {
+			l.manual_deps = true;
+		}
+		let manual = lines.update_deps_with(&self.system, &self.library);
+		for l in lines.iter_mut() {
+			l.manual_deps = manual.contains(&l.no);
+		}
+		let _ = self.edit(EditKind::Replace, |c, l| {
+			*l = lines;
+			*c = Cursor::new();
+			Ok(())
+		});
+		self.header = file.header;
+		self.update_deps();
+		// The lines of the old proof have nothing to do with the new one's
+		self.notify_edit(&[]);
+		Ok(())
+	}
+	/// Writes the proof being edited as a `.plc` file, with the header and comments it was loaded with.
+	pub fn save(&self) -> String {
+		let mut header = self.header.clone();
+		if header.system.is_none() && self.system.name() != Lemmon.name() {
+			header.system = Some(self.system.name().to_string());
+		}
+		format!("{}", ProofFile {
+			header: header,
+			lines: self.lines.clone(),
+			comments: self.lines.comments().to_vec(),
+			header_lines: Vec::new(),
+		})
+	}
+	/// Called before the cursor is moved by a command. The selection is extended if `select` is set, and cleared
+	/// otherwise. Typing after a move isn't undone with the typing before it.
+	fn start_move(&mut self, select: bool) {
+		if select {
+			self.cursor.select();
+		} else {
+			self.cursor.deselect();
+		}
+		self.history.last = None;
+	}
+	/// Adds a listener, which is called with each change after it is made: those made by commands, and edits
+	/// made by methods such as `load` and `replace_all`.
+	pub fn on_change<F: FnMut(&Change) + 'static>(&mut self, f: F) {
+		self.listeners.push(Box::new(f));
+	}
+	/// Runs a command, returning what it changed and telling the listeners. Returns `None` if the command
+	/// couldn't be carried out, e.g. moving left at the start of the proof, in which case nothing changed.
+	pub fn execute(&mut self, cmd: EditorCommand) -> Option<Change> {
+		let was_broken = self.broken_refs.clone();
+		let mut copied = None;
+		let res = match cmd {
+			EditorCommand::Undo => self.undo().map(|_| true),
+			EditorCommand::Redo => self.redo().map(|_| true),
+			EditorCommand::SelectAll => {
+				self.select_all();
+				self.history.last = None;
+				Ok(false)
 			},
-			key::Left => {
-				let _ = self.cursor.left(&self.lines);
+			EditorCommand::MoveLineUp => self.move_line_up().map(|_| true),
+			EditorCommand::MoveLineDown => self.move_line_down().map(|_| true),
+			EditorCommand::Move(motion, select) => {
+				self.start_move(select);
+				let lines = &self.lines;
+				match motion {
+					Motion::Left      => self.cursor.left(lines),
+					Motion::Right     => self.cursor.right(lines),
+					Motion::Up        => self.cursor.up(lines),
+					Motion::Down      => self.cursor.down(lines),
+					Motion::WordLeft  => self.cursor.word_left(lines),
+					Motion::WordRight => self.cursor.word_right(lines),
+					Motion::Home      => {
+						self.cursor.home();
+						Ok(())
+					},
+					Motion::End       => {
+						self.cursor.end(lines);
+						Ok(())
+					},
+				}.map(|_| false)
 			},
-			key::Delete => {
-				let _ = self.cursor.delete(&mut self.lines);
+			EditorCommand::MoveTo(no, col, i, select) => {
+				self.start_move(select);
+				self.cursor.move_to(&self.lines, no, col, i);
+				Ok(false)
 			},
-			key::Return => {
-				let _ = self.cursor.newline(&mut self.lines);
+			EditorCommand::NextCol => {
+				self.start_move(false);
+				self.cursor.next_col(&self.lines).map(|_| false)
 			},
-			key::BackSpace => {
-				let _ = self.cursor.backspace(&mut self.lines);
+			EditorCommand::PrevCol => {
+				self.start_move(false);
+				self.cursor.prev_col(&self.lines).map(|_| false)
 			},
-			_ => {
-				let _ = self.cursor.insert(&mut self.lines, match keyval_to_unicode(e.keyval) {
-					Some(c) if c.is_alphabetic() || ALLOWED_CHARS.contains(c) => Token::Char(c),
-					_ => { return Inhibit(false); },
-				});
-			}
+			EditorCommand::Delete => self.delete().map(|_| true),
+			EditorCommand::Backspace => self.backspace().map(|_| true),
+			EditorCommand::Newline => self.newline().map(|_| true),
+			EditorCommand::NewlineAbove => self.newline_above().map(|_| true),
+			EditorCommand::Insert(t) => self.insert(t).map(|_| true),
+			EditorCommand::Copy => {
+				copied = self.copy();
+				copied.as_ref().map(|_| false).ok_or(())
+			},
+			EditorCommand::Cut => {
+				copied = self.copy();
+				match copied {
+					Some(_) => self.edit(EditKind::Paste, |c, lines| c.delete_selection(lines)).map(|_| true),
+					None => Err(()),
+				}
+			},
+			EditorCommand::Paste(text) => {
+				self.edit(EditKind::Paste, |c, lines| c.paste(lines, &text).map_err(|_| ())).map(|_| true)
+			},
+		};
+		// Also run after moves, as the dependencies typed into a line are only tidied once the cursor leaves it.
+		self.update_deps();
+		let change = match (res, copied) {
+			(Ok(true), Some(text)) => Change::Cut {
+				text: text,
+				broken: self.broken_since(&was_broken),
+			},
+			(Ok(true), None) => Change::Edit {
+				broken: self.broken_since(&was_broken),
+			},
+			(Ok(false), Some(text)) => Change::Copy(text),
+			(Ok(false), None) => Change::Cursor,
+			(Err(()), _) => return None,
+		};
+		self.notify(&change);
+		Some(change)
+	}
+	/// The lines that cite a deleted line and aren't in `was_broken`.
+	fn broken_since(&self, was_broken: &[usize]) -> Vec<usize> {
+		self.broken_refs.iter().filter(|no| !was_broken.contains(no)).cloned().collect()
+	}
+	/// Calls the listeners with `change`.
+	fn notify(&mut self, change: &Change) {
+		for f in self.listeners.iter_mut() {
+			f(change);
+		}
+	}
+	/// Tells the listeners about an edit made by a method rather than a command, given the lines that cited a
+	/// deleted line before it.
+	fn notify_edit(&mut self, was_broken: &[usize]) {
+		let change = Change::Edit {
+			broken: self.broken_since(was_broken),
+		};
+		self.notify(&change);
+	}
+	/// Runs the command bound to a key, as given by `EditorCommand::from_key`. Returns false if no command is
+	/// bound to the key, so that the frontend can use it.
+	pub fn handle_key(&mut self, k: &KeyInput) -> bool {
+		match EditorCommand::from_key(k) {
+			Some(cmd) => {
+				self.execute(cmd);
+				true
+			},
+			None => false,
 		}
-		println!(" *** Editor *** - Cursor: {:?} \n{:#}", self.cursor, self);
-		Inhibit(true)
 	}
 }
-impl Display for Editor {
+impl<S: ProofSystem> Display for Editor<S> {
 	fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
 		for l in self.lines.iter() {
 			try!(l.fmt_cursor(f, &self.cursor));
@@ -464,3 +1697,338 @@ impl Display for Editor {
 		Ok(())
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use logic::Tokens;
+	
+	use check::check;
+	use super::*;
+	
+	#[test]
+	fn test_clean() {
+		let mut lines = Lines::from_vec(vec![
+			line(0, "P&Q", "Premise", vec![]),
+			line(1, "R", "Premise", vec![]),
+			line(2, "Q", "&E 1", vec![]),
+			line(3, "P", "&E 1", vec![]),
+			line(4, "QvS", "vI 3", vec![]),
+			line(5, "P", "&E 1", vec![]),
+			line(6, "Q&P", "&I 3, 6", vec![]),
+		]);
+		lines.update_deps();
+		lines.set_comments(vec![(4, "Removed".to_string()), (7, "End".to_string())]);
+		let map = lines.clean();
+		println!("[test_clean]\n{}", lines);
+		assert_eq!(map, vec![Some(0), Some(1), Some(2), Some(3), None, Some(3), Some(4)]);
+		assert_eq!(lines.iter().map(|l| format!("{:#}", l.method)).collect::<Vec<_>>(),
+			vec!["Premise", "Premise", "∧E 1", "∧E 1", "∧I 3, 4"]);
+		assert_eq!(lines.iter().map(|l| l.no).collect::<Vec<_>>(), vec![0, 1, 2, 3, 4]);
+		assert_eq!(lines[4].deps, vec![0]);
+		assert_eq!(lines.comments(), &[(4, "Removed".to_string()), (5, "End".to_string())]);
+		assert_eq!(check(&lines), vec![]);
+		
+		// The lines cited by a justification that can't be read aren't known, so nothing is removed
+		let mut lines = Lines::from_vec(vec![
+			line(0, "P", "Premise", vec![]),
+			line(1, "PvQ", "vI 1", vec![]),
+			line(2, "PvQ", "vI one", vec![]),
+		]);
+		let before = format!("{}", lines);
+		assert_eq!(lines.clean(), vec![Some(0), Some(1), Some(2)]);
+		assert_eq!(format!("{}", lines), before);
+	}
+	
+	#[test]
+	fn test_renumber() {
+		fn methods(lines: &Lines) -> Vec<String> {
+			lines.iter().map(|l| format!("{}", l.method)).collect()
+		}
+		fn comments(lines: &Lines) -> Vec<(usize, &str)> {
+			lines.comments().iter().map(|c| (c.0, &c.1[..])).collect()
+		}
+		let mut lines = Lines::from_vec(vec![
+			line(0, "P", "Premise", vec![0]),
+			line(1, "P->Q", "Premise", vec![1]),
+			line(2, "Q", "->E 2, 1", vec![0, 1]),
+			line(3, "R", "SI (S1) 3", vec![0, 1]),
+		]);
+		lines.set_comments(vec![(0, "a".to_string()), (2, "b".to_string()), (4, "c".to_string())]);
+		lines.insert_line(Line::new(1));
+		assert_eq!(methods(&lines), vec!["Premise", "", "Premise", "->E 3, 1", "SI (S1) 4"]);
+		assert_eq!(comments(&lines), vec![(0, "a"), (3, "b"), (5, "c")]);
+		assert_eq!(lines[3].deps, vec![0, 2]);
+		assert_eq!(lines.iter().map(|l| l.no).collect::<Vec<_>>(), vec![0, 1, 2, 3, 4]);
+		
+		lines.move_line(0, 2);
+		assert_eq!(methods(&lines), vec!["", "Premise", "Premise", "->E 2, 3", "SI (S1) 4"]);
+		assert_eq!(comments(&lines), vec![(2, "a"), (3, "b"), (5, "c")]);
+		
+		assert_eq!(lines.delete_line(0), vec![]);
+		assert_eq!(lines.delete_line(1), vec![1]);
+		println!("[test_renumber]\n{}", lines);
+		assert_eq!(methods(&lines), vec!["Premise", "->E 1, ?", "SI (S1) 2"]);
+		assert_eq!(lines[1].deps, vec![0]);
+		assert!(lines[1].has_broken_ref());
+		assert_eq!(comments(&lines), vec![(1, "a"), (1, "b"), (3, "c")]);
+		
+		// Numbers too big to be line numbers are left as they are
+		let mut lines = Lines::from_vec(vec![line(0, "P", "->E 99999999999999999999999, 1", vec![])]);
+		lines.insert_line(Line::new(0));
+		assert_eq!(methods(&lines), vec!["", "->E 99999999999999999999999, 2"]);
+	}
+	
+	#[test]
+	fn test_undo() {
+		fn text(ed: &Editor) -> Vec<String> {
+			ed.lines().iter().map(|l| format!("{} ; {}", l.step, l.method)).collect()
+		}
+		let mut ed = Editor::new();
+		ed.load("{1} 1. P ; Premise\n").unwrap();
+		let start = text(&ed);
+		for _ in 0..9 {
+			ed.cursor.right(&ed.lines).unwrap();
+		}
+		let end = ed.cursor().clone();
+		ed.newline().unwrap();
+		for c in "PvQ".chars() {
+			ed.insert(Token::Char(c)).unwrap();
+		}
+		assert_eq!(text(&ed), vec!["P ; Premise", "PvQ ; "]);
+		
+		// The typing is undone in one go, then the new line
+		ed.undo().unwrap();
+		assert_eq!(text(&ed), vec!["P ; Premise", " ; "]);
+		ed.undo().unwrap();
+		assert_eq!(text(&ed), start);
+		assert_eq!(ed.cursor(), &end);
+		ed.redo().unwrap();
+		ed.redo().unwrap();
+		assert!(ed.redo().is_err());
+		assert_eq!(text(&ed), vec!["P ; Premise", "PvQ ; "]);
+		
+		ed.move_line_up().unwrap();
+		ed.set_deps(0, vec![1]).unwrap();
+		assert_eq!(text(&ed), vec!["PvQ ; ", "P ; Premise"]);
+		ed.undo().unwrap();
+		assert!(!ed.lines()[0].manual_deps);
+		ed.undo().unwrap();
+		assert_eq!(text(&ed), vec!["P ; Premise", "PvQ ; "]);
+		
+		// An edit clears what can be redone
+		ed.backspace().unwrap();
+		assert!(ed.redo().is_err());
+		
+		// Loading a file is undone along with its header and comments
+		let before = text(&ed);
+		ed.load("title: Other\n# Note\n{1} 1. Q ; Premise\n").unwrap();
+		ed.undo().unwrap();
+		assert_eq!(text(&ed), before);
+		assert_eq!(ed.header().title, None);
+		assert!(ed.lines().comments().is_empty());
+		ed.redo().unwrap();
+		assert_eq!(ed.header().title, Some("Other".to_string()));
+		assert_eq!(ed.lines().comments(), &[(0, "Note".to_string())]);
+	}
+	
+	#[test]
+	fn test_cursor_navigation() {
+		let mut lines = Lines::from_vec(vec![
+			line(0, "P&Q", "Premise", vec![0]),
+			line(1, "P", "&E 1", vec![0]),
+		]);
+		lines.simplify();
+		let mut c = Cursor::new();
+		c.end(&lines);
+		assert_eq!((c.no, c.col, c.i), (0, Col::Step, 3));
+		c.down(&lines).unwrap();
+		assert_eq!((c.no, c.col, c.i), (1, Col::Step, 1));
+		assert!(c.down(&lines).is_err());
+		// Moving back up returns to where the cursor was, until it is moved sideways
+		c.up(&lines).unwrap();
+		assert_eq!((c.no, c.col, c.i), (0, Col::Step, 3));
+		c.down(&lines).unwrap();
+		c.left(&lines).unwrap();
+		c.up(&lines).unwrap();
+		assert_eq!((c.no, c.col, c.i), (0, Col::Step, 0));
+		c.down(&lines).unwrap();
+		c.next_col(&lines).unwrap();
+		assert_eq!((c.no, c.col, c.i), (1, Col::Method, 4));
+		c.next_col(&lines).unwrap();
+		assert_eq!((c.no, c.col, c.i), (1, Col::Deps, 1));
+		assert!(c.next_col(&lines).is_err());
+		c.prev_col(&lines).unwrap();
+		c.up(&lines).unwrap();
+		assert_eq!((c.no, c.col, c.i), (0, Col::Method, 4));
+		c.prev_col(&lines).unwrap();
+		assert!(c.prev_col(&lines).is_err());
+		
+		// Words are runs of letters and digits, or single operators
+		c.down(&lines).unwrap();
+		c.next_col(&lines).unwrap();
+		c.home();
+		let mut stops = Vec::new();
+		while c.no == 1 && c.col == Col::Method && c.i < 4 {
+			c.word_right(&lines).unwrap();
+			stops.push(c.i);
+		}
+		assert_eq!(stops, vec![1, 2, 4]);
+		c.word_left(&lines).unwrap();
+		assert_eq!(c.i, 3);
+		
+		c.newline_above(&mut lines).unwrap();
+		assert_eq!((c.no, c.col, c.i), (1, Col::Step, 0));
+		assert_eq!(lines.len(), 3);
+		assert_eq!(format!("{}", lines[2].method), "&E 1");
+		c.newline(&mut lines).unwrap();
+		assert_eq!(c.no, 2);
+		assert_eq!(format!("{}", lines[3].method), "&E 1");
+	}
+	
+	#[test]
+	fn test_deps_column() {
+		let mut ed = Editor::new();
+		ed.load("{1}  1. P ; Premise\n{2}  2. Q ; Premise\n{1, 2}  3. P&Q ; &I 1, 2\n").unwrap();
+		ed.cursor = Cursor { no: 2, col: Col::Deps, i: 0, anchor: None, goal: None };
+		ed.cursor.end(&ed.lines);
+		assert_eq!(ed.cursor.i, 4);
+		for _ in 0..3 {
+			ed.backspace().unwrap();
+		}
+		assert_eq!(ed.lines()[2].deps, vec![0]);
+		assert!(ed.lines()[2].manual_deps);
+		ed.update_deps();
+		assert_eq!(ed.dep_conflicts(), &[2]);
+		
+		// Only numbers can be typed, and they must be of this line or an earlier one
+		assert!(ed.insert(Token::Char('x')).is_err());
+		for c in ", 4".chars() {
+			ed.insert(Token::Char(c)).unwrap();
+		}
+		ed.update_deps();
+		println!("[test_deps_column]\n{}", ed);
+		assert_eq!(ed.dep_errors(), &[(2, DepsError::NotEarlier(3))]);
+		assert_eq!(ed.lines()[2].deps, vec![0]);
+		ed.backspace().unwrap();
+		ed.insert(Token::Char('2')).unwrap();
+		ed.update_deps();
+		assert!(ed.dep_errors().is_empty());
+		assert!(ed.dep_conflicts().is_empty());
+		assert_eq!(format!("{}", ed.lines()[2].deps_tokens()), "1, 2");
+		
+		// Clearing the column makes the dependencies automatic again, shown once the cursor leaves it
+		ed.set_deps(2, vec![0]).unwrap();
+		ed.cursor.home();
+		while ed.delete().is_ok() {}
+		ed.update_deps();
+		assert!(!ed.lines()[2].manual_deps);
+		assert_eq!(ed.lines()[2].deps, vec![0, 1]);
+		assert_eq!(format!("{}", ed.lines()[2].deps_tokens()), "");
+		ed.cursor.prev_col(&ed.lines).unwrap();
+		ed.update_deps();
+		assert_eq!(format!("{}", ed.lines()[2].deps_tokens()), "1, 2");
+	}
+	
+	#[test]
+	fn test_clipboard() {
+		fn text(ed: &Editor) -> Vec<String> {
+			ed.lines().iter().map(|l| format!("{:#} ; {:#}", l.step, l.method)).collect()
+		}
+		let mut ed = Editor::new();
+		ed.load("{1}  1. P ; Premise\n{2}  2. Q ; Premise\n{1, 2}  3. P&Q ; &I 1, 2\n").unwrap();
+		
+		// Within a column, the tokens are copied
+		ed.cursor = Cursor { no: 2, col: Col::Method, i: 0, anchor: None, goal: None };
+		ed.cursor.select();
+		for _ in 0..2 {
+			ed.cursor.right(&ed.lines).unwrap();
+		}
+		assert_eq!(ed.selection(), Some(Selection::Tokens { no: 2, col: Col::Method, start: 0, end: 2 }));
+		assert_eq!(ed.copy(), Some("∧I".to_string()));
+		
+		// Across lines, whole lines are copied as steps, and pasted with their references moved with them
+		ed.cursor.deselect();
+		ed.cursor.up(&ed.lines).unwrap();
+		ed.cursor.select();
+		ed.cursor.down(&ed.lines).unwrap();
+		assert_eq!(ed.selection(), Some(Selection::Lines(1, 3)));
+		let copied = ed.cut().unwrap();
+		println!("[test_clipboard]\n{}", copied);
+		assert_eq!(text(&ed), vec!["P ; Premise"]);
+		ed.paste(&copied).unwrap();
+		assert_eq!(text(&ed), vec!["P ; Premise", "Q ; Premise", "P∧Q ; ∧I 1, 2"]);
+		assert_eq!(ed.lines()[2].deps, vec![0, 1]);
+		
+		ed.cursor = Cursor::new();
+		ed.paste(&copied).unwrap();
+		assert_eq!(text(&ed), vec!["P ; Premise", "Q ; Premise", "P∧Q ; ∧I 1, 2", "Q ; Premise", "P∧Q ; ∧I 1, 4"]);
+		assert!(ed.paste("{1}  1. P ; Premise\n{1}  3. P ; Premise\n").is_err());
+		assert_eq!(ed.lines().len(), 5);
+		
+		// Cutting lines breaks the references to them
+		ed.cursor = Cursor { no: 1, col: Col::Step, i: 0, anchor: Some((0, Col::Step, 0)), goal: None };
+		ed.cut().unwrap();
+		assert_eq!(text(&ed)[0], "P∧Q ; ∧I ?, ?");
+		assert_eq!(ed.broken_refs(), &[0, 2]);
+		assert_eq!(ed.problem(2), ed.problem_with(2, &ed.check()));
+		assert_eq!(ed.problem_with(2, &[]), Some("line 3: cites a deleted line".to_string()));
+		ed.undo().unwrap();
+		
+		// Pasted text is typed in, in place of the selection
+		ed.cursor = Cursor { no: 0, col: Col::Step, i: 0, anchor: Some((0, Col::Step, 1)), goal: None };
+		ed.paste("R->S").unwrap();
+		assert_eq!(text(&ed)[0], "R→S ; Premise");
+		ed.undo().unwrap();
+		assert_eq!(text(&ed)[0], "P ; Premise");
+		ed.select_all();
+		ed.delete().unwrap();
+		assert_eq!(text(&ed), vec![" ; "]);
+	}
+	
+	#[test]
+	fn test_find_replace() {
+		fn steps(ed: &Editor) -> Vec<String> {
+			ed.lines().iter().map(|l| format!("{:#}", l.step)).collect()
+		}
+		let mut ed = Editor::new();
+		ed.load("sequent: ~~P, P->Q |- Q\n\n{1}  1. ~~P ; Premise\n{2}  2. P->Q ; Premise\n{1}  3. P ; DN 1\n\
+			{1, 2}  4. Q ; ->E 3, 2\n").unwrap();
+		
+		let found = ed.lines().find_formula(&Formula::new("~~P").unwrap());
+		assert_eq!(found.len(), 1);
+		assert_eq!(found[0].0, 0);
+		assert_eq!(found[0].2, vec![('P', Formula::new("P").unwrap())]);
+		assert_eq!(ed.lines().find_formula(&Formula::new("P->Q").unwrap()).iter().map(|f| f.0).collect::<Vec<_>>(), vec![1]);
+		assert_eq!(ed.lines().find_formula(&Formula::new("~P").unwrap()).len(), 2);
+		
+		// Tokens are found in order from the cursor, starting again at the top
+		let p = Tokens::from_str("P");
+		assert_eq!(ed.lines().find_tokens(&p).len(), 3);
+		ed.find_next(&p).unwrap();
+		assert_eq!(ed.selection(), Some(Selection::Tokens { no: 0, col: Col::Step, start: 2, end: 3 }));
+		ed.find_next(&p).unwrap();
+		assert_eq!(ed.selection(), Some(Selection::Tokens { no: 1, col: Col::Step, start: 0, end: 1 }));
+		for _ in 0..2 {
+			ed.find_next(&p).unwrap();
+		}
+		assert_eq!(ed.selection(), Some(Selection::Tokens { no: 0, col: Col::Step, start: 2, end: 3 }));
+		
+		// A uniform substitution gives a proof of the substitution instance
+		let skipped = ed.substitute(&[('P', Formula::new("R&S").unwrap()), ('Q', Formula::new("P").unwrap())]);
+		println!("[test_find_replace]\n{:#}", ed.lines());
+		assert!(skipped.is_empty());
+		assert_eq!(steps(&ed), vec!["¬¬(R∧S)", "(R∧S)→P", "R∧S", "P"]);
+		assert_eq!(format!("{:#}", ed.header().sequent.as_ref().unwrap()), "¬¬(R∧S), (R∧S)→P ⊢ P");
+		assert!(ed.check().is_empty());
+		
+		assert_eq!(ed.replace_all(&Tokens::from_str("R"), &Tokens::from_str("T")), 3);
+		assert_eq!(steps(&ed)[0], "¬¬(T∧S)");
+		assert_eq!(ed.replace_all(&Tokens::from_str("X"), &Tokens::from_str("T")), 0);
+		ed.undo().unwrap();
+		ed.undo().unwrap();
+		assert_eq!(steps(&ed), vec!["¬¬P", "P→Q", "P", "Q"]);
+		assert_eq!(format!("{:#}", ed.header().sequent.as_ref().unwrap()), "¬¬P, P→Q ⊢ Q");
+		ed.redo().unwrap();
+		assert_eq!(format!("{:#}", ed.header().sequent.as_ref().unwrap()), "¬¬(R∧S), (R∧S)→P ⊢ P");
+	}
+}
@@ -0,0 +1,420 @@
+use std::fmt::{self, Display, Formatter};
+
+use logic::{ParseError, Sequent, Tokens};
+
+use edit::{Line, Lines};
+
+/// How connectives are written in a `.plc` file.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Notation {
+	/// `~`, `&`, `v`, `->`, `<->`, `_|_` and `|-`.
+	Ascii,
+	/// `¬`, `∧`, `∨`, `→`, `↔`, `⊥` and `⊢`.
+	Unicode,
+}
+impl Notation {
+	fn name(&self) -> &'static str {
+		match *self {
+			Notation::Ascii   => "ascii",
+			Notation::Unicode => "unicode",
+		}
+	}
+}
+
+/// Why a `.plc` file couldn't be read.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FileErrorKind {
+	/// A header line has a key other than `title`, `sequent`, `notation` or `system`.
+	UnknownKey(String),
+	/// The notation isn't `ascii` or `unicode`.
+	Notation(String),
+	/// The sequent couldn't be parsed. Contains the index of the formula that failed and the error.
+	Sequent(usize, ParseError),
+	/// A header line comes after the first step.
+	LateHeader,
+	/// A step isn't of the form `{deps} N. formula ; justification`.
+	Syntax,
+	/// A dependency isn't a line number.
+	Deps(String),
+	/// A step is numbered out of order. Contains the number expected, starting at 0.
+	Number(usize),
+	/// The file is for a different proof system. Contains the system named in the file.
+	System(String),
+}
+
+/// An error found on line `line` of a `.plc` file. Line numbers start at 0.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileError {
+	pub line: usize,
+	pub kind: FileErrorKind,
+}
+impl Display for FileError {
+	fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+		try!(write!(f, "line {}: ", self.line + 1));
+		match self.kind {
+			FileErrorKind::UnknownKey(ref s) => write!(f, "unknown header `{}`", s),
+			FileErrorKind::Notation(ref s)   => write!(f, "unknown notation `{}`, expected `ascii` or `unicode`", s),
+			FileErrorKind::Sequent(i, ref e) => write!(f, "sequent formula {}: {}", i + 1, e),
+			FileErrorKind::LateHeader        => write!(f, "header lines must come before the proof"),
+			FileErrorKind::Syntax            => write!(f, "expected `{{deps}} N. formula ; justification`"),
+			FileErrorKind::Deps(ref s)       => write!(f, "expected a line number in the dependencies, found `{}`", s),
+			FileErrorKind::Number(no)        => write!(f, "expected step {}", no + 1),
+			FileErrorKind::System(ref s)     => write!(f, "the proof is for the {} system", s),
+		}
+	}
+}
+
+/// The header of a `.plc` file. Every field is optional.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Header {
+	pub title: Option<String>,
+	/// The sequent the proof is meant to prove.
+	pub sequent: Option<Sequent>,
+	/// How the formulas and justifications are written. Unicode if not given.
+	pub notation: Notation,
+	/// The name of the proof system the proof is written in, e.g. `Fitch`.
+	pub system: Option<String>,
+	/// The comments that come before or among the header lines, without the `#`. They are written before the
+	/// header.
+	pub comments: Vec<String>,
+}
+impl Header {
+	pub fn new() -> Header {
+		Header {
+			title: None,
+			sequent: None,
+			notation: Notation::Unicode,
+			system: None,
+			comments: Vec::new(),
+		}
+	}
+}
+
+/// A proof as stored in a `.plc` file:
+///
+/// ```text
+/// # Comments start with a hash
+/// title: Modus tollens
+/// sequent: P→Q, ¬Q ⊢ ¬P
+/// notation: unicode
+///
+/// {1}       1. P→Q                  ; Premise
+/// {2}       2. ¬Q                   ; Premise
+/// {1, 2}    3. ¬P                   ; MTT 1, 2
+/// ```
+///
+/// Each step gives the lines it depends on, its number, its formula and its justification. Steps inside Fitch
+/// subproofs have a `|` before the formula for each subproof they are in. Comments are kept with the header or
+/// the step they come before. Blank lines and the spacing within lines don't matter, so the file can be edited
+/// by hand, and writing a file that has been read gives the same text back as long as it was laid out the way
+/// it is written.
+#[derive(Clone)]
+pub struct ProofFile {
+	pub header: Header,
+	pub lines: Lines,
+	/// The comments after the header, without the `#`, and the number of steps before each.
+	pub comments: Vec<(usize, String)>,
+	/// The key of each header line read, and the line of the file it was on, starting at 0. Empty if the proof
+	/// wasn't read from a `.plc` file.
+	pub header_lines: Vec<(String, usize)>,
+}
+impl ProofFile {
+	/// Reads a `.plc` file.
+	pub fn parse(text: &str) -> Result<ProofFile, FileError> {
+		let mut file = ProofFile {
+			header: Header::new(),
+			lines: Lines::from_vec(Vec::new()),
+			comments: Vec::new(),
+			header_lines: Vec::new(),
+		};
+		for (i, s) in text.lines().enumerate() {
+			let err = |kind: FileErrorKind| Err(FileError {
+				line: i,
+				kind: kind,
+			});
+			let s = s.trim();
+			if s.is_empty() {
+				continue;
+			} else if s.starts_with('#') {
+				// Only the space after the `#` that the writer adds is taken off, so the rest is written back as it was
+				let c = &s[1..];
+				let c = if c.starts_with(' ') { &c[1..] } else { c };
+				file.comments.push((file.lines.len(), c.to_string()));
+			} else if s.starts_with('{') {
+				match parse_step(s, file.lines.len()) {
+					Ok(l) => file.lines.push(l),
+					Err(kind) => return err(kind),
+				}
+			} else {
+				let (key, value) = match s.find(':') {
+					Some(j) => (s[..j].trim(), s[j + 1..].trim()),
+					None => return err(FileErrorKind::Syntax),
+				};
+				if !file.lines.is_empty() {
+					return err(FileErrorKind::LateHeader);
+				}
+				// There are no steps yet, so the comments so far come before this header line
+				file.header.comments.extend(file.comments.drain(..).map(|c| c.1));
+				match key {
+					"title" => file.header.title = Some(value.to_string()),
+					"sequent" => match Sequent::parse(value) {
+						Ok(seq) => file.header.sequent = Some(seq),
+						Err((n, e)) => return err(FileErrorKind::Sequent(n, e)),
+					},
+					"notation" => file.header.notation = match value {
+						"ascii"   => Notation::Ascii,
+						"unicode" => Notation::Unicode,
+						_ => return err(FileErrorKind::Notation(value.to_string())),
+					},
+					"system" => file.header.system = Some(value.to_string()),
+					_ => return err(FileErrorKind::UnknownKey(key.to_string())),
+				}
+				file.header_lines.push((key.to_string(), i));
+			}
+		}
+		Ok(file)
+	}
+	/// The line of the file the header line with key `key` was on, if it was read from a `.plc` file.
+	pub fn header_line(&self, key: &str) -> Option<usize> {
+		self.header_lines.iter().rev().find(|h| h.0 == key).map(|h| h.1)
+	}
+}
+impl Display for ProofFile {
+	fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+		let uni = self.header.notation == Notation::Unicode;
+		let mut comments = self.comments.iter().peekable();
+		let mut write_comments = |f: &mut Formatter, no: usize| -> Result<(), fmt::Error> {
+			while let Some(&&(_, ref s)) = comments.peek().filter(|c| c.0 <= no) {
+				try!(write_comment(f, s));
+				comments.next();
+			}
+			Ok(())
+		};
+		
+		let h = &self.header;
+		for s in h.comments.iter() {
+			try!(write_comment(f, s));
+		}
+		if let Some(ref title) = h.title {
+			try!(writeln!(f, "title: {}", title));
+		}
+		if let Some(ref seq) = h.sequent {
+			if uni {
+				try!(writeln!(f, "sequent: {:#}", seq));
+			} else {
+				try!(writeln!(f, "sequent: {}", seq));
+			}
+		}
+		if h.notation != Notation::Unicode {
+			try!(writeln!(f, "notation: {}", h.notation.name()));
+		}
+		if let Some(ref system) = h.system {
+			try!(writeln!(f, "system: {}", system));
+		}
+		if h.title.is_some() || h.sequent.is_some() || h.notation != Notation::Unicode || h.system.is_some() {
+			try!(writeln!(f, ""));
+		}
+		
+		for (no, l) in self.lines.iter().enumerate() {
+			try!(write_comments(f, no));
+			try!(writeln!(f, "{}", step_text(l, no, uni)));
+		}
+		// Comments after the last step
+		write_comments(f, usize::max_value())
+	}
+}
+
+/// Writes a comment line, leaving out the space after the `#` if the comment is empty.
+fn write_comment(f: &mut Formatter, s: &str) -> Result<(), fmt::Error> {
+	if s.is_empty() {
+		writeln!(f, "#")
+	} else {
+		writeln!(f, "# {}", s)
+	}
+}
+
+/// Writes `lines` as the steps of a `.plc` file, keeping the number each has in the proof. This is how lines are
+/// copied to the clipboard.
+pub fn write_lines(lines: &[Line]) -> String {
+	let mut s = String::new();
+	for l in lines.iter() {
+		s.push_str(&step_text(l, l.no, true));
+		s.push('\n');
+	}
+	s
+}
+
+/// Reads steps written by `write_lines`, or copied from a `.plc` file. The steps must be numbered in order, but
+/// can start from any number, and each keeps its number. See `Lines::insert_lines`. Blank lines and comments are
+/// skipped.
+pub fn parse_lines(text: &str) -> Result<Lines, FileError> {
+	let mut lines = Lines::from_vec(Vec::new());
+	let mut first = None;
+	for (i, s) in text.lines().enumerate() {
+		let s = s.trim();
+		if s.is_empty() || s.starts_with('#') {
+			continue;
+		}
+		if first.is_none() {
+			first = Some(step_number(s).unwrap_or(0));
+		}
+		match parse_step(s, first.unwrap() + lines.len()) {
+			Ok(l) => lines.push(l),
+			Err(kind) => return Err(FileError {
+				line: i,
+				kind: kind,
+			}),
+		}
+	}
+	Ok(lines)
+}
+
+/// Writes line `l` as step `no`: `{deps} N. formula ; justification`.
+fn step_text(l: &Line, no: usize, uni: bool) -> String {
+	let mut deps = String::from("{");
+	for (i, d) in l.deps.iter().enumerate() {
+		deps.push_str(&format!("{}{}", if i == 0 { "" } else { ", " }, d + 1));
+	}
+	deps.push('}');
+	let (step, method) = if uni {
+		(format!("{:#}", l.step), format!("{:#}", l.method))
+	} else {
+		(format!("{}", l.step), format!("{}", l.method))
+	};
+	let step = "| ".repeat(l.depth) + step.trim();
+	format!("{: <8} {: >3}. {: <20} ; {}", deps, no + 1, step, method.trim())
+}
+
+/// The number of the step `{deps} N. ...`, starting at 0.
+fn step_number(s: &str) -> Option<usize> {
+	let s = match s.find('}') {
+		Some(close) => s[close + 1..].trim_left(),
+		None => return None,
+	};
+	match s.find('.').map(|dot| s[..dot].parse::<usize>()) {
+		Some(Ok(n)) if n > 0 => Some(n - 1),
+		_ => None,
+	}
+}
+
+/// Reads the step `{deps} N. formula ; justification`, which should be step `no`.
+fn parse_step(s: &str, no: usize) -> Result<Line, FileErrorKind> {
+	let close = try!(s.find('}').ok_or(FileErrorKind::Syntax));
+	let mut deps = Vec::new();
+	for d in s[1..close].split(',').map(|d| d.trim()).filter(|d| !d.is_empty()) {
+		match d.parse::<usize>() {
+			Ok(d) if d > 0 => deps.push(d - 1),
+			_ => return Err(FileErrorKind::Deps(d.to_string())),
+		}
+	}
+	
+	let s = s[close + 1..].trim_left();
+	let dot = try!(s.find('.').ok_or(FileErrorKind::Syntax));
+	if s[..dot].parse::<usize>() != Ok(no + 1) {
+		return Err(FileErrorKind::Number(no));
+	}
+	let semi = try!(s.find(';').ok_or(FileErrorKind::Syntax));
+	if semi < dot {
+		return Err(FileErrorKind::Syntax);
+	}
+	
+	let mut step = s[dot + 1..semi].trim();
+	let mut depth = 0;
+	while step.starts_with("| ") || step == "|" {
+		step = step[1..].trim_left();
+		depth += 1;
+	}
+	let mut l = Line::full(no, Tokens::from_str(step), Tokens::from_str(s[semi + 1..].trim()), deps);
+	l.depth = depth;
+	l.simplify();
+	Ok(l)
+}
+
+#[cfg(test)]
+mod tests {
+	use logic::Sequent;
+	
+	use edit::Editor;
+	use prove::prove;
+	use super::*;
+	
+	#[test]
+	fn test_file_round_trip() {
+		let text = "# Chapter 1\n\
+			title: Modus tollens\n\
+			sequent: P→Q, ¬Q ⊢ ¬P\n\
+			system: Fitch\n\
+			\n\
+			{}         1. P→Q                  ; Premise\n\
+			{}         2. ¬Q                   ; Premise\n\
+			# The subproof\n\
+			{}         3. | P                  ; A\n\
+			{}         4. | Q                  ; →E 1, 3\n\
+			{}         5. | ⊥                  ; ¬E 2, 4\n\
+			{}         6. ¬P                   ; ¬I 3-5\n\
+			# Done\n";
+		let file = ProofFile::parse(text).unwrap();
+		assert_eq!(file.lines.iter().map(|l| l.depth).collect::<Vec<_>>(), vec![0, 0, 1, 1, 1, 0]);
+		assert_eq!(file.header.system, Some("Fitch".to_string()));
+		assert_eq!(file.header_line("system"), Some(3));
+		assert_eq!(file.header.comments, vec!["Chapter 1".to_string()]);
+		assert_eq!(file.comments, vec![(2, "The subproof".to_string()), (6, "Done".to_string())]);
+		let written = format!("{}", file);
+		println!("[test_file_round_trip]\n{}", written);
+		assert_eq!(written, text);
+		
+		let mut file = ProofFile {
+			header: Header::new(),
+			lines: prove(&Sequent::parse("P^(QvR) |- (P^Q)v(P^R)").unwrap()).unwrap(),
+			comments: Vec::new(),
+			header_lines: Vec::new(),
+		};
+		file.header.notation = Notation::Ascii;
+		let written = format!("{}", file);
+		println!("[test_file_round_trip]\n{}", written);
+		let read = ProofFile::parse(&written).unwrap();
+		assert_eq!(read.header, file.header);
+		for (a, b) in read.lines.iter().zip(file.lines.iter()) {
+			assert_eq!((&a.step, &a.method, &a.deps), (&b.step, &b.method, &b.deps));
+		}
+		assert_eq!(format!("{}", read), written);
+		
+		// Comments after the header stay after it, and keep their spacing
+		let text = "title: T\n\
+			#   Indented\n\
+			\n\
+			{1}        1. P                    ; Premise\n\
+			#\n";
+		let file = ProofFile::parse(text).unwrap();
+		assert_eq!(file.comments, vec![(0, "  Indented".to_string()), (1, "".to_string())]);
+		let written = format!("{}", file);
+		assert_eq!(written, "title: T\n\n#   Indented\n{1}        1. P                    ; Premise\n#\n");
+		let read = ProofFile::parse(&written).unwrap();
+		assert_eq!((read.header, read.comments), (file.header, file.comments));
+		
+		// Dependencies that disagree with the justification are kept
+		let text = "{1}        1. P                    ; Premise\n\
+			{1, 2}     2. P∨Q                  ; ∨I 1\n";
+		let mut ed = Editor::new();
+		ed.load(text).unwrap();
+		assert_eq!(ed.lines().iter().map(|l| l.manual_deps).collect::<Vec<_>>(), vec![false, true]);
+		assert_eq!(ed.dep_conflicts(), &[1]);
+		assert_eq!(ed.save(), text);
+		assert_eq!(ed.load("# For Fitch\nsystem: Fitch").err().unwrap(),
+			FileError { line: 1, kind: FileErrorKind::System("Fitch".to_string()) });
+	}
+	
+	#[test]
+	fn test_file_errors() {
+		fn err(text: &str) -> FileError {
+			ProofFile::parse(text).err().unwrap()
+		}
+		assert_eq!(err("author: me"), FileError { line: 0, kind: FileErrorKind::UnknownKey("author".to_string()) });
+		assert_eq!(err("notation: latex").kind, FileErrorKind::Notation("latex".to_string()));
+		assert_eq!(err("{1} 1. P ; A\ntitle: x").kind, FileErrorKind::LateHeader);
+		assert_eq!(err("\n{1} 2. P ; A"), FileError { line: 1, kind: FileErrorKind::Number(0) });
+		assert_eq!(err("{1} 1. P ; A\n{1, x} 2. Q ; A").kind, FileErrorKind::Deps("x".to_string()));
+		assert_eq!(err("{1} 1. P A").kind, FileErrorKind::Syntax);
+		match err("sequent: P, Q& |- R").kind { FileErrorKind::Sequent(1, _) => {}, ref k => panic!("{:?}", k) }
+	}
+}
@@ -0,0 +1,521 @@
+use std::cmp;
+use std::fmt::{self, Formatter};
+
+use logic::Formula;
+use logic::form::{Not, And, Implies, Iff, Bottom};
+
+use check::{self, CheckError, Diagnostic, Parsed};
+use edit::{Line, Lines};
+use just::{Justification, Ref};
+use library::Library;
+use rule::Rule;
+use system::{Fitch, ConvertError};
+
+/// The rule of `l`, if its method can be read.
+fn rule(l: &Line) -> Option<Rule> {
+	l.justification().ok().map(|j| j.rule)
+}
+
+/// True if a horizontal line is drawn under line `no` of a Fitch proof, separating what is assumed from what is
+/// derived: under every assumption, and under the last premise.
+pub fn is_hypothesis_end(lines: &Lines, no: usize) -> bool {
+	match rule(&lines[no]) {
+		Some(Rule::Assumption) => true,
+		Some(Rule::Premise) => lines.get(no + 1).and_then(rule) != Some(Rule::Premise),
+		_ => false,
+	}
+}
+
+/// Works out the subproofs each line is inside, as the numbers of the assumptions that open them, outermost
+/// first. An assumption is inside the subproof it opens. Lines indented without an assumption to open the
+/// subproof are treated as if they were indented correctly, and their numbers are returned as well.
+fn scopes(lines: &Lines) -> (Vec<Vec<usize>>, Vec<usize>) {
+	let mut stack = Vec::new();
+	let mut scopes = Vec::with_capacity(lines.len());
+	let mut bad = Vec::new();
+	for (no, l) in lines.iter().enumerate() {
+		let rule = rule(l);
+		if rule == Some(Rule::Assumption) {
+			if l.depth == 0 || l.depth > stack.len() + 1 {
+				bad.push(no);
+			}
+			let depth = cmp::max(1, cmp::min(l.depth, stack.len() + 1));
+			stack.truncate(depth - 1);
+			stack.push(no);
+		} else {
+			if l.depth > stack.len() || (rule == Some(Rule::Premise) && l.depth != 0) {
+				bad.push(no);
+			}
+			let depth = cmp::min(l.depth, stack.len());
+			stack.truncate(depth);
+		}
+		scopes.push(stack.clone());
+	}
+	(scopes, bad)
+}
+
+/// The assumptions line `no` of a Fitch proof still rests on: those of the subproofs it is inside, outermost
+/// first.
+pub fn open_assumptions(lines: &Lines, no: usize) -> Vec<usize> {
+	scopes(lines).0.swap_remove(no)
+}
+
+/// Writes out a Fitch proof with a bar down the left of each subproof, and a line under its assumption.
+pub fn fmt_lines(lines: &Lines, f: &mut Formatter) -> Result<(), fmt::Error> {
+	for (no, l) in lines.iter().enumerate() {
+		let bars = "│ ".repeat(l.depth + 1);
+		let width = 20usize.saturating_sub(2 * l.depth);
+		if !f.alternate() {
+			try!(writeln!(f, "{: >3}. {}{: <w$} {}", no + 1, bars, l.step, l.method, w = width));
+		} else {
+			try!(writeln!(f, "{: >3}. {}{: <#w$} {:#}", no + 1, bars, l.step, l.method, w = width));
+		}
+		if is_hypothesis_end(lines, no) {
+			try!(writeln!(f, "     {}├───", "│ ".repeat(l.depth)));
+		}
+	}
+	Ok(())
+}
+
+/// How each Fitch rule cites: `l` for a line and `s` for a subproof.
+fn shape(rule: Rule) -> &'static str {
+	match rule {
+		Rule::Reit | Rule::AndE | Rule::OrI | Rule::X => "l",
+		Rule::AndI | Rule::ImpliesE | Rule::IffE | Rule::NotE => "ll",
+		Rule::OrE => "lss",
+		Rule::ImpliesI | Rule::NotI | Rule::Ip => "s",
+		Rule::IffI => "ss",
+		_ => "",
+	}
+}
+
+/// True if lines `a` to `b` are a whole subproof that line `no` can cite: `a` opens it, `b` is its last line,
+/// and the subproof around it is still open at `no`.
+fn is_subproof(scopes: &[Vec<usize>], a: usize, b: usize, no: usize) -> bool {
+	if a > b || b >= no || scopes[a].last() != Some(&a) {
+		return false;
+	}
+	let inner = &scopes[a];
+	let outer = &inner[..inner.len() - 1];
+	scopes[no].starts_with(outer) && scopes[b].starts_with(inner)
+		&& (b + 1 == scopes.len() || !scopes[b + 1].starts_with(inner))
+}
+
+/// Checks that `j` is a Fitch rule, and that the lines and subproofs it cites can be cited from line `no`.
+fn check_refs(j: &Justification, scopes: &[Vec<usize>], no: usize) -> Result<(), CheckError> {
+	let found: String = j.refs.iter().map(|r| match *r {
+		Ref::Line(_)     => 'l',
+		Ref::Range(_, _) => 's',
+	}).collect();
+	// Derived rules cite any number of single lines
+	let expected = if j.rule == Rule::Derived { "l".repeat(j.refs.len()) } else { shape(j.rule).to_string() };
+	if found != expected {
+		return Err(CheckError::Shape(j.rule));
+	}
+	for r in j.refs.iter() {
+		match *r {
+			Ref::Line(c) => if !scopes[no].starts_with(&scopes[c]) {
+				return Err(CheckError::OutOfScope(c));
+			},
+			Ref::Range(a, b) => if !is_subproof(scopes, a, b, no) {
+				return Err(CheckError::NotSubproof(a, b));
+			},
+		}
+	}
+	Ok(())
+}
+
+/// Checks every line of a Fitch-style proof against the rules, and that every line cited is in scope and
+/// every subproof cited is closed. Derived rules and forbidden rules are taken from `lib`. Returns every
+/// problem found, in line order.
+pub fn check(lines: &Lines, lib: &Library) -> Vec<Diagnostic> {
+	let parsed: Vec<Parsed> = lines.iter().map(|l| Parsed {
+		form: Formula::parse(&l.step),
+		just: l.justification_with(lib),
+	}).collect();
+	let (scopes, bad) = scopes(lines);
+	
+	let mut diags = Vec::new();
+	for no in 0..lines.len() {
+		let res = match parsed[no].just {
+			_ if bad.contains(&no) => Err(CheckError::BadDepth),
+			Ok(ref j) if !check::is_allowed(&Fitch, lib, j.rule) => Err(CheckError::NotAllowed(j.rule)),
+			Ok(ref j) => check::check_line(&parsed, lib, no).and_then(|_| check_refs(j, &scopes, no)),
+			Err(_) => check::check_line(&parsed, lib, no),
+		};
+		if let Err(err) = res {
+			diags.push(Diagnostic {
+				no: no,
+				err: err,
+			});
+		}
+	}
+	diags
+}
+
+/// Builds a Fitch proof a line at a time, keeping track of the open subproofs.
+struct Emitter {
+	lines: Vec<Line>,
+	forms: Vec<Formula>,
+	/// The subproofs each line is in, as the ids of the assumptions that open them.
+	scopes: Vec<Vec<usize>>,
+	/// The open subproofs. Ids below `first_id` are the Lemmon assumptions that open them.
+	stack: Vec<usize>,
+	next_id: usize,
+}
+impl Emitter {
+	fn new(first_id: usize) -> Emitter {
+		Emitter {
+			lines: Vec::new(),
+			forms: Vec::new(),
+			scopes: Vec::new(),
+			stack: Vec::new(),
+			next_id: first_id,
+		}
+	}
+	
+	fn emit(&mut self, form: Formula, rule: Rule, refs: Vec<Ref>) -> usize {
+		let mut l = Line::full(self.lines.len(), form.to_tokens(), Justification::new(rule, refs).to_tokens(), Vec::new());
+		l.depth = self.stack.len();
+		self.lines.push(l);
+		self.forms.push(form);
+		self.scopes.push(self.stack.clone());
+		self.lines.len() - 1
+	}
+	
+	/// Opens a subproof with the assumption `form`, under the id `id`.
+	fn assume(&mut self, id: usize, form: Formula) -> usize {
+		self.stack.push(id);
+		self.emit(form, Rule::Assumption, Vec::new())
+	}
+	
+	/// Opens a subproof for a derivation that Lemmon does in one step and Fitch can't.
+	fn open(&mut self, form: Formula) -> usize {
+		let id = self.next_id;
+		self.next_id += 1;
+		self.assume(id, form)
+	}
+	
+	/// Closes the innermost subproof so that it ends with line `concl`, reiterating it if it isn't the last line
+	/// already. Returns the subproof as a range, or `None` if `concl` is out of scope.
+	fn close(&mut self, concl: usize) -> Option<Ref> {
+		if !self.in_scope(concl) {
+			return None;
+		}
+		let last = self.lines.len() - 1;
+		let end = if concl == last && self.scopes[last] == self.stack {
+			concl
+		} else {
+			let f = self.forms[concl].clone();
+			self.emit(f, Rule::Reit, vec![Ref::Line(concl)])
+		};
+		let id = self.stack.pop().unwrap();
+		let start = self.scopes.iter().position(|s| s.last() == Some(&id)).unwrap();
+		Some(Ref::Range(start, end))
+	}
+	
+	/// True if line `no` can be cited from the current position.
+	fn in_scope(&self, no: usize) -> bool {
+		self.stack.starts_with(&self.scopes[no])
+	}
+	
+	/// True if the subproof `r` can be cited from the current position.
+	fn citable(&self, r: Ref) -> bool {
+		match r {
+			Ref::Range(a, _) => {
+				let inner = &self.scopes[a];
+				self.stack.starts_with(&inner[..inner.len() - 1])
+			},
+			Ref::Line(no) => self.in_scope(no),
+		}
+	}
+	
+	/// Derives `⊥` from the contradiction `ψ∧¬ψ` on line `c`.
+	fn bottom(&mut self, c: usize) -> usize {
+		let (p, q) = match self.forms[c] {
+			And(ref p, ref q) => ((**p).clone(), (**q).clone()),
+			_ => return c,
+		};
+		let p = self.emit(p, Rule::AndE, vec![Ref::Line(c)]);
+		let q = self.emit(q, Rule::AndE, vec![Ref::Line(c)]);
+		self.emit(Bottom, Rule::NotE, vec![Ref::Line(p), Ref::Line(q)])
+	}
+}
+
+/// Converts a Lemmon-style proof into a Fitch-style one. Each discharged assumption opens a subproof running
+/// to the conclusion it is discharged with. `MTT`, `DN`, `↔I` and the conditional form of `↔E` become short
+/// subproofs of their own, and `ψ∧¬ψ` contradictions are broken down into `⊥`.
+///
+/// The proof must check without errors. `SI` and `TI` can't be converted, and neither can proofs that cite a
+/// line after the assumption it depends on has been discharged.
+pub fn from_lemmon(lines: &Lines) -> Result<Lines, ConvertError> {
+	if let Some(d) = check::check(lines).into_iter().next() {
+		return Err(ConvertError::Invalid(d));
+	}
+	let forms: Vec<Formula> = lines.iter().map(|l| Formula::parse(&l.step).unwrap()).collect();
+	let justs: Vec<Justification> = lines.iter().map(|l| l.justification().unwrap()).collect();
+	
+	// The conclusion each assumption is first discharged with, and the rule that discharges it
+	let mut concl: Vec<Option<(usize, Rule)>> = vec![None; lines.len()];
+	for j in justs.iter() {
+		let cites = j.lines();
+		let pairs = match j.rule {
+			Rule::ImpliesI | Rule::NotI | Rule::Raa => vec![(cites[0], cites[1])],
+			Rule::OrE => vec![(cites[1], cites[2]), (cites[3], cites[4])],
+			_ => vec![],
+		};
+		for &(a, n) in pairs.iter() {
+			if concl[a].is_none() {
+				concl[a] = Some((n, j.rule));
+			}
+		}
+	}
+	let end = |a: usize| concl[a].map(|(n, _)| cmp::max(a, n));
+	
+	let mut em = Emitter::new(lines.len());
+	let mut map = vec![0; lines.len()];
+	// The subproof each assumption opened, once it has closed, with the Lemmon line it concludes
+	let mut ranges: Vec<Option<(Ref, usize)>> = vec![None; lines.len()];
+	for no in 0..lines.len() + 1 {
+		// Close the subproofs that ended on the line before
+		while let Some(&a) = em.stack.last() {
+			if end(a).unwrap() >= no {
+				break;
+			}
+			let (n, rule) = concl[a].unwrap();
+			if !em.in_scope(map[n]) {
+				return Err(ConvertError::OutOfScope(n));
+			}
+			let mut c = map[n];
+			if rule == Rule::NotI || rule == Rule::Raa {
+				c = em.bottom(c);
+			}
+			ranges[a] = Some((em.close(c).unwrap(), n));
+		}
+		if no == lines.len() {
+			break;
+		}
+		
+		let (f, j) = (forms[no].clone(), &justs[no]);
+		let cites = j.lines();
+		let line = |em: &Emitter, c: usize| if em.in_scope(map[c]) {
+			Ok(Ref::Line(map[c]))
+		} else {
+			Err(ConvertError::OutOfScope(no))
+		};
+		let range = |em: &Emitter, a: usize, n: usize| match ranges[a] {
+			Some((r, concl)) if concl == n && em.citable(r) => Ok(r),
+			_ => Err(ConvertError::OutOfScope(no)),
+		};
+		
+		map[no] = match j.rule {
+			Rule::Premise | Rule::Assumption => match end(no) {
+				Some(e) => {
+					if let Some(&t) = em.stack.last() {
+						if end(t).unwrap() < e {
+							return Err(ConvertError::NotNested(no));
+						}
+					}
+					em.assume(no, f)
+				},
+				None if em.stack.is_empty() => em.emit(f, Rule::Premise, Vec::new()),
+				None => return Err(ConvertError::NotNested(no)),
+			},
+			Rule::AndI | Rule::AndE | Rule::OrI | Rule::ImpliesE | Rule::NotE | Rule::IffE if j.rule != Rule::IffE
+					|| cites.len() == 2 => {
+				let mut refs = Vec::with_capacity(cites.len());
+				for &c in cites.iter() {
+					refs.push(try!(line(&em, c)));
+				}
+				em.emit(f, j.rule, refs)
+			},
+			Rule::ImpliesI | Rule::NotI | Rule::Raa => {
+				let r = try!(range(&em, cites[0], cites[1]));
+				let rule = match (j.rule, &forms[cites[0]]) {
+					(Rule::Raa, &Not(ref p)) if **p == f => Rule::Ip,
+					(Rule::Raa, _) => Rule::NotI,
+					(rule, _) => rule,
+				};
+				em.emit(f, rule, vec![r])
+			},
+			Rule::OrE => {
+				let refs = vec![
+					try!(line(&em, cites[0])),
+					try!(range(&em, cites[1], cites[2])),
+					try!(range(&em, cites[3], cites[4])),
+				];
+				em.emit(f, Rule::OrE, refs)
+			},
+			Rule::IffE => {
+				// φ↔ψ ⊢ φ→ψ: assume φ, get ψ by ↔E, discharge by →I
+				let c = try!(line(&em, cites[0]));
+				let (p, q) = match f {
+					Implies(ref p, ref q) => ((**p).clone(), (**q).clone()),
+					_ => unreachable!(),
+				};
+				let s = em.open(p);
+				let t = em.emit(q, Rule::IffE, vec![c, Ref::Line(s)]);
+				let r = em.close(t).unwrap();
+				em.emit(f, Rule::ImpliesI, vec![r])
+			},
+			Rule::IffI => {
+				// φ→ψ, ψ→φ ⊢ φ↔ψ: get each direction in a subproof by →E
+				let (p, q) = match f {
+					Iff(ref p, ref q) => ((**p).clone(), (**q).clone()),
+					_ => unreachable!(),
+				};
+				let pq = Implies(Box::new(p.clone()), Box::new(q.clone()));
+				let (a, b) = if forms[cites[0]] == pq { (cites[0], cites[1]) } else { (cites[1], cites[0]) };
+				let (a, b) = (try!(line(&em, a)), try!(line(&em, b)));
+				let s = em.open(p.clone());
+				let t = em.emit(q.clone(), Rule::ImpliesE, vec![a, Ref::Line(s)]);
+				let r1 = em.close(t).unwrap();
+				let s = em.open(q);
+				let t = em.emit(p, Rule::ImpliesE, vec![b, Ref::Line(s)]);
+				let r2 = em.close(t).unwrap();
+				em.emit(f, Rule::IffI, vec![r1, r2])
+			},
+			Rule::Mtt => {
+				// φ→ψ, ¬ψ ⊢ ¬φ: assume φ, get ψ by →E, then ⊥ by ¬E, discharge by ¬I
+				let (cond, neg) = match forms[cites[0]] {
+					Implies(_, _) => (cites[0], cites[1]),
+					_ => (cites[1], cites[0]),
+				};
+				let (cond_ref, neg_ref) = (try!(line(&em, cond)), try!(line(&em, neg)));
+				let (p, q) = match forms[cond] {
+					Implies(ref p, ref q) => ((**p).clone(), (**q).clone()),
+					_ => unreachable!(),
+				};
+				let s = em.open(p);
+				let t = em.emit(q, Rule::ImpliesE, vec![cond_ref, Ref::Line(s)]);
+				let b = em.emit(Bottom, Rule::NotE, vec![Ref::Line(t), neg_ref]);
+				let r = em.close(b).unwrap();
+				em.emit(f, Rule::NotI, vec![r])
+			},
+			Rule::Dn => {
+				// Assume the negation of the conclusion, contradict the cited line, and discharge by IP or ¬I
+				let c = try!(line(&em, cites[0]));
+				let (assumed, rule) = match f {
+					Not(ref p) if Not(Box::new(forms[cites[0]].clone())) == **p => ((**p).clone(), Rule::NotI),
+					_ => (Not(Box::new(f.clone())), Rule::Ip),
+				};
+				let s = em.open(assumed);
+				let b = em.emit(Bottom, Rule::NotE, vec![c, Ref::Line(s)]);
+				let r = em.close(b).unwrap();
+				em.emit(f, rule, vec![r])
+			},
+			rule => return Err(ConvertError::Unsupported(no, rule)),
+		};
+	}
+	Ok(Lines::from_vec(em.lines))
+}
+
+/// Converts a Fitch-style proof into a Lemmon-style one, with the dependencies filled in. Reiterated lines are
+/// left out and the original cited instead, `IP` becomes `RAA`, and `X` and the Fitch form of `↔I` take extra
+/// lines.
+///
+/// The proof must check without errors.
+pub fn to_lemmon(proof: &Lines) -> Result<Lines, ConvertError> {
+	if let Some(d) = check(proof, &Library::new()).into_iter().next() {
+		return Err(ConvertError::Invalid(d));
+	}
+	fn push(out: &mut Vec<Line>, f: Formula, rule: Rule, cites: &[usize]) -> usize {
+		let no = out.len();
+		out.push(Line::full(no, f.to_tokens(), Justification::with_lines(rule, cites).to_tokens(), Vec::new()));
+		no
+	}
+	
+	let mut out = Vec::with_capacity(proof.len());
+	let mut map = vec![0; proof.len()];
+	for (no, l) in proof.iter().enumerate() {
+		let f = Formula::parse(&l.step).unwrap();
+		let j = l.justification().unwrap();
+		let cites: Vec<usize> = j.lines().iter().map(|&c| map[c]).collect();
+		map[no] = match j.rule {
+			Rule::Reit => cites[0],
+			Rule::Ip => push(&mut out, f, Rule::Raa, &cites),
+			Rule::IffI => {
+				let (p, q) = match f {
+					Iff(ref p, ref q) => ((**p).clone(), (**q).clone()),
+					_ => unreachable!(),
+				};
+				let pq = push(&mut out, Implies(Box::new(p.clone()), Box::new(q.clone())), Rule::ImpliesI, &cites[..2]);
+				let qp = push(&mut out, Implies(Box::new(q), Box::new(p)), Rule::ImpliesI, &cites[2..]);
+				push(&mut out, f, Rule::IffI, &[pq, qp])
+			},
+			Rule::X if f == Bottom => cites[0],
+			Rule::X => {
+				// Assume the opposite of the conclusion and discharge it straight away
+				let (assumed, rule) = match f {
+					Not(ref p) => ((**p).clone(), Rule::NotI),
+					ref f => (Not(Box::new(f.clone())), Rule::Raa),
+				};
+				let a = push(&mut out, assumed, Rule::Assumption, &[]);
+				push(&mut out, f, rule, &[a, cites[0]])
+			},
+			rule => push(&mut out, f, rule, &cites),
+		};
+	}
+	let mut lines = Lines::from_vec(out);
+	lines.update_deps();
+	Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+	use logic::{Sequent, Tokens};
+	
+	use check::{self, CheckError, Diagnostic};
+	use edit::{Line, Lines};
+	use library::Library;
+	use prove::prove;
+	use rule::Rule;
+	use system::{ProofSystem, Fitch};
+	use super::*;
+	
+	#[test]
+	fn test_fitch_convert() {
+		for s in ["P, P->Q |- Q", "|- Pv~P", "P->Q, ~Q |- ~P", "~(P^Q) |- ~Pv~Q", "P<->Q |- Q<->P",
+				"P^(QvR) |- (P^Q)v(P^R)", "P, ~P |- Q", "|- ((P->Q)->P)->P", "~~P |- P"].iter() {
+			let lines = prove(&Sequent::parse(s).unwrap()).unwrap();
+			let proof = from_lemmon(&lines).unwrap();
+			println!("[test_fitch_convert] {}\n{:#}", s, Fitch.show(&proof));
+			assert_eq!(check(&proof, &Library::new()), vec![]);
+			let back = to_lemmon(&proof).unwrap();
+			println!("[test_fitch_convert] back\n{:#}", back);
+			assert_eq!(check::check(&back), vec![]);
+			assert_eq!(back.last().unwrap().step, lines.last().unwrap().step);
+		}
+	}
+	
+	#[test]
+	fn test_fitch_check() {
+		fn line(depth: usize, step: &str, method: &str) -> Line {
+			let mut l = Line::full(0, Tokens::from_str(step), Tokens::from_str(method), vec![]);
+			l.depth = depth;
+			l
+		}
+		let proof = Lines::from_vec(vec![
+			line(0, "P->Q", "PR"),
+			line(0, "~Q", "PR"),
+			line(1, "P", "AS"),
+			line(1, "Q", "->E 1, 3"),
+			line(1, "_|_", "~E 4, 2"),
+			line(0, "~P", "~I 3-5"),
+			line(0, "Q", "R 4"),
+			line(0, "~P", "MTT 1, 2"),
+			line(0, "~P", "~I 3, 5"),
+			line(0, "~P", "~I 3-4"),
+			line(2, "P", "R 3"),
+		]);
+		let diags = check(&proof, &Library::new());
+		println!("[test_fitch_check] {:?}", diags);
+		assert_eq!(diags, vec![
+			Diagnostic { no: 6, err: CheckError::OutOfScope(3) },
+			Diagnostic { no: 7, err: CheckError::NotAllowed(Rule::Mtt) },
+			Diagnostic { no: 8, err: CheckError::Shape(Rule::NotI) },
+			Diagnostic { no: 9, err: CheckError::Mismatch(Rule::NotI) },
+			Diagnostic { no: 10, err: CheckError::BadDepth },
+		]);
+	}
+}
@@ -0,0 +1,472 @@
+//! Grading a class's proofs for an assignment, started with `plc grade`.
+//!
+//! An assignment is described by a spec file listing the sequents to prove and the rules allowed:
+//!
+//! ```text
+//! # Week 3: negation
+//! system Lemmon
+//! allow ∧I, ∧E, →I, →E, ¬I, ¬E, DN
+//! exercise mt: P→Q, ¬Q ⊢ ¬P
+//! exercise contra: P→Q ⊢ ¬Q→¬P
+//! ```
+//!
+//! - `system NAME` is the proof system the proofs are checked in. If it isn't given, each proof is checked in
+//!   the system its file names, or in Lemmon's.
+//! - `allow RULE, ...` limits the built-in rules to those listed. Premises and assumptions are always allowed.
+//! - `exercise NAME: premises |- conclusion` is a sequent to prove. Its name is the name of the file the proof is
+//!   handed in as.
+//!
+//! Any other line is read as in a rule file (see `Library::load_rules`), so an assignment can add derived rules,
+//! forbid rules or add sequents for `SI`. Each student hands in a directory named after them, containing a `.plc`
+//! or `.json` file for each exercise, e.g. `ada/mt.plc`.
+//!
+//! A conclusion counts as proved up to the names of its variables: a proof of `R→S, ¬S ⊢ ¬R` proves `mt`.
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::path::Path;
+
+use logic::{Formula, Sequent};
+use logic::json::{self, Json, ToJson};
+
+use check::CheckError;
+use cli;
+use edit::Lines;
+use file::ProofFile;
+use fitch;
+use library::{Library, LibError};
+use rule::Rule;
+use system::{self, ProofSystem, Structure, Lemmon};
+
+/// Why an assignment spec couldn't be read.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SpecError {
+	/// A line couldn't be read, or an exercise can't be proved.
+	Lib(LibError),
+	/// There is no proof system with this name.
+	UnknownSystem(String),
+	/// An exercise has a name that can't be used as a file name.
+	BadName(String),
+	/// Two exercises have the same name.
+	Duplicate(String),
+}
+impl Display for SpecError {
+	fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+		match self {
+			&SpecError::Lib(ref e)           => e.fmt(f),
+			&SpecError::UnknownSystem(ref s) => write!(f, "unknown proof system `{}`", s),
+			&SpecError::BadName(ref s)       => write!(f, "`{}` can't be used as the name of an exercise", s),
+			&SpecError::Duplicate(ref s)     => write!(f, "there is already an exercise called `{}`", s),
+		}
+	}
+}
+
+/// An assignment: the sequents to prove, and the rules that can be used to prove them.
+#[derive(Clone, Debug)]
+pub struct Assignment {
+	/// The name of the proof system the proofs must be written in, if the assignment gives one.
+	pub system: Option<String>,
+	/// The name of each exercise and the sequent to prove, in the order they are listed.
+	pub exercises: Vec<(String, Sequent)>,
+	/// The standard library, changed by the rules of the assignment.
+	pub lib: Library,
+}
+impl Assignment {
+	/// Reads an assignment spec. On error, returns the line number (starting at 0) and the error.
+	pub fn parse(text: &str) -> Result<Assignment, (usize, SpecError)> {
+		let mut a = Assignment {
+			system: None,
+			exercises: Vec::new(),
+			lib: Library::standard(),
+		};
+		let mut allowed = None;
+		for (i, line) in text.lines().enumerate() {
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+			let (kw, rest) = match line.find(char::is_whitespace) {
+				Some(j) => (&line[..j], line[j..].trim()),
+				None => return Err((i, SpecError::Lib(LibError::Syntax))),
+			};
+			match kw {
+				"system" => match system::from_name(rest) {
+					Some(sys) => a.system = Some(sys.name().to_string()),
+					None => return Err((i, SpecError::UnknownSystem(rest.to_string()))),
+				},
+				"allow" => {
+					let mut rules = allowed.unwrap_or(Vec::new());
+					for name in rest.split(',').map(|s| s.trim()) {
+						match Rule::from_name(name) {
+							Some(rule) => rules.push(rule),
+							None => return Err((i, SpecError::Lib(LibError::UnknownRule(name.to_string())))),
+						}
+					}
+					allowed = Some(rules);
+				},
+				"exercise" => {
+					let (name, seq) = match rest.find(':') {
+						Some(j) => (rest[..j].trim(), &rest[j + 1..]),
+						None => return Err((i, SpecError::Lib(LibError::Syntax))),
+					};
+					if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_') {
+						return Err((i, SpecError::BadName(name.to_string())));
+					}
+					if a.exercises.iter().any(|e| e.0 == name) {
+						return Err((i, SpecError::Duplicate(name.to_string())));
+					}
+					let seq = try!(Sequent::parse(seq).map_err(|(n, e)| (i, SpecError::Lib(LibError::Parse(n, e)))));
+					if let Some(vals) = seq.counterexample() {
+						return Err((i, SpecError::Lib(LibError::Invalid(vals))));
+					}
+					a.exercises.push((name.to_string(), seq));
+				},
+				_ => try!(a.lib.load_rules(line).map_err(|(_, e)| (i, SpecError::Lib(e)))),
+			}
+		}
+		if let Some(allowed) = allowed {
+			for &rule in Rule::all().iter().filter(|r| !r.is_assumption() && !allowed.contains(r)) {
+				a.lib.forbid(rule);
+			}
+		}
+		Ok(a)
+	}
+	
+	/// Grades a proof handed in for the exercise `seq`, in the assignment's proof system or else the one the
+	/// file names.
+	pub fn grade_file(&self, seq: &Sequent, file: &ProofFile) -> Grade {
+		let name = self.system.as_ref().or(file.header.system.as_ref());
+		let sys: Box<ProofSystem> = match name {
+			Some(name) => match system::from_name(name) {
+				Some(sys) => sys,
+				None => return Grade::Unreadable(format!("unknown proof system `{}`", name)),
+			},
+			None => Box::new(Lemmon),
+		};
+		grade(&*sys, &self.lib, seq, &file.lines)
+	}
+}
+
+/// The result of grading one proof.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Grade {
+	/// The proof is correct.
+	Proved,
+	/// No proof was handed in.
+	Missing,
+	/// The proof couldn't be read. Contains the error.
+	Unreadable(String),
+	/// The proof doesn't end with the conclusion of the sequent. Contains the formula it ends with, or `None` if
+	/// the proof is empty.
+	WrongConclusion(Option<Formula>),
+	/// The conclusion rests on these lines, which aren't premises of the sequent.
+	ExtraPremises(Vec<usize>),
+	/// A line doesn't follow by its rule.
+	InvalidLine(usize, CheckError),
+	/// A line uses a rule the assignment doesn't allow.
+	DisallowedRule(usize, Rule),
+}
+impl Grade {
+	/// A short description of the grade, as written in the `result` column of a report.
+	pub fn result(&self) -> &'static str {
+		match self {
+			&Grade::Proved             => "proved",
+			&Grade::Missing            => "missing",
+			&Grade::Unreadable(_)      => "unreadable",
+			&Grade::WrongConclusion(_) => "wrong conclusion",
+			&Grade::ExtraPremises(_)   => "extra premises",
+			&Grade::InvalidLine(..)    => "invalid line",
+			&Grade::DisallowedRule(..) => "disallowed rule",
+		}
+	}
+	
+	/// The line the problem is on, if there is one.
+	pub fn line(&self) -> Option<usize> {
+		match self {
+			&Grade::ExtraPremises(ref nos) => nos.first().cloned(),
+			&Grade::InvalidLine(no, _) | &Grade::DisallowedRule(no, _) => Some(no),
+			_ => None,
+		}
+	}
+}
+impl Display for Grade {
+	fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+		match self {
+			&Grade::Proved => write!(f, "the proof is correct"),
+			&Grade::Missing => write!(f, "no proof was handed in"),
+			&Grade::Unreadable(ref e) => write!(f, "{}", e),
+			&Grade::WrongConclusion(Some(ref concl)) => write!(f, "the proof ends with {:#}", concl),
+			&Grade::WrongConclusion(None) => write!(f, "the proof is empty"),
+			&Grade::ExtraPremises(ref nos) => {
+				try!(write!(f, "the conclusion rests on line{}", if nos.len() == 1 { "" } else { "s" }));
+				for (i, no) in nos.iter().enumerate() {
+					try!(write!(f, "{}{}", if i == 0 { " " } else { ", " }, no + 1));
+				}
+				write!(f, ", which {} of the sequent", if nos.len() == 1 { "isn't a premise" } else { "aren't premises" })
+			},
+			&Grade::InvalidLine(no, ref e) => write!(f, "line {}: {}", no + 1, e),
+			&Grade::DisallowedRule(no, rule) => write!(f, "line {}: {:#} isn't allowed in this assignment", no + 1, rule),
+		}
+	}
+}
+
+/// Grades `lines` as a proof of `seq` in `sys`. The first mistake in the lines is reported before a wrong
+/// conclusion or extra premises, as the conclusion of a proof with mistakes in it means nothing.
+pub fn grade(sys: &ProofSystem, lib: &Library, seq: &Sequent, lines: &Lines) -> Grade {
+	if let Some(d) = sys.check(lines, lib).into_iter().next() {
+		return match d.err {
+			CheckError::NotAllowed(rule) => Grade::DisallowedRule(d.no, rule),
+			e => Grade::InvalidLine(d.no, e),
+		};
+	}
+	// Every line parses, as the proof checked.
+	let form = |no: usize| Formula::parse(&lines[no].step).unwrap();
+	let last = match lines.last() {
+		Some(l) => l,
+		None => return Grade::WrongConclusion(None),
+	};
+	let mut renaming = Vec::new();
+	if !seq.conclusion.renames(&form(last.no), &mut renaming) {
+		return Grade::WrongConclusion(Some(form(last.no)));
+	}
+	let mut rests_on = match sys.structure() {
+		Structure::Deps => last.deps.clone(),
+		Structure::Subproofs => {
+			let is_premise = |no: &usize| lines[*no].justification().ok().map(|j| j.rule) == Some(Rule::Premise);
+			let mut nos: Vec<usize> = (0..lines.len()).filter(is_premise).collect();
+			nos.extend(fitch::open_assumptions(lines, last.no));
+			nos
+		},
+	};
+	rests_on.sort();
+	rests_on.dedup();
+	let forms: Vec<(usize, Formula)> = rests_on.into_iter().map(|no| (no, form(no))).collect();
+	let extra = match_premises(&seq.premises, &forms, &renaming);
+	if !extra.is_empty() {
+		return Grade::ExtraPremises(extra);
+	}
+	Grade::Proved
+}
+
+/// Matches each of the numbered formulas `forms` to a premise it renames, so that one renaming, extending
+/// `renaming`, works for them all. Every way of matching them is tried, as the first premise a formula renames
+/// may not be the one that lets the rest match. Returns the lines of the formulas left unmatched, as few as
+/// possible.
+fn match_premises(premises: &[Formula], forms: &[(usize, Formula)], renaming: &[(char, char)]) -> Vec<usize> {
+	let (&(no, ref f), rest) = match forms.split_first() {
+		Some(first) => first,
+		None => return Vec::new(),
+	};
+	let mut best: Option<Vec<usize>> = None;
+	for p in premises.iter() {
+		let mut r = renaming.to_vec();
+		if p.renames(f, &mut r) {
+			let extra = match_premises(premises, rest, &r);
+			if extra.is_empty() {
+				return extra;
+			}
+			if best.as_ref().map_or(true, |b| extra.len() < b.len()) {
+				best = Some(extra);
+			}
+		}
+	}
+	let mut extra = vec![no];
+	extra.extend(match_premises(premises, rest, renaming));
+	match best {
+		Some(b) if b.len() <= extra.len() => b,
+		_ => extra,
+	}
+}
+
+/// The grade of one student's proof of one exercise.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mark {
+	pub student: String,
+	pub exercise: String,
+	pub grade: Grade,
+}
+impl ToJson for Mark {
+	fn to_json(&self) -> Json {
+		Json::Object(vec![
+			("student".to_string(), Json::String(self.student.clone())),
+			("exercise".to_string(), Json::String(self.exercise.clone())),
+			("result".to_string(), Json::String(self.grade.result().to_string())),
+			("line".to_string(), self.grade.line().map(|no| Json::Number((no + 1) as f64)).unwrap_or(Json::Null)),
+			("detail".to_string(), Json::String(self.grade.to_string())),
+		])
+	}
+}
+
+/// The grades of a class, by student and then by exercise.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Report {
+	pub marks: Vec<Mark>,
+}
+impl Report {
+	/// True if every proof was correct.
+	pub fn all_proved(&self) -> bool {
+		self.marks.iter().all(|m| m.grade == Grade::Proved)
+	}
+	
+	/// Writes the report as CSV, with a heading and then a row for each mark. Line numbers start at 1, and are
+	/// left empty if the grade isn't about a line.
+	pub fn to_csv(&self) -> String {
+		let mut s = "student,exercise,result,line,detail\n".to_string();
+		for m in self.marks.iter() {
+			let line = m.grade.line().map(|no| (no + 1).to_string()).unwrap_or(String::new());
+			s.push_str(&format!("{},{},{},{},{}\n", csv_field(&m.student), csv_field(&m.exercise), m.grade.result(), line,
+				csv_field(&m.grade.to_string())));
+		}
+		s
+	}
+	
+	/// Writes the report as a versioned JSON document, `{"version": 1, "grades": [MARK, ...]}`, pretty printed.
+	pub fn write_json(&self) -> String {
+		format!("{:#}", json::to_document("grades", self))
+	}
+}
+/// Written as an array of marks, each as
+/// `{"student": "ada", "exercise": "mt", "result": "invalid line", "line": 3 | null, "detail": "..."}`.
+impl ToJson for Report {
+	fn to_json(&self) -> Json {
+		Json::Array(self.marks.iter().map(|m| m.to_json()).collect())
+	}
+}
+
+/// Quotes a CSV field if it needs it.
+fn csv_field(s: &str) -> String {
+	if s.contains(|c| c == ',' || c == '"' || c == '\n') {
+		format!("\"{}\"", s.replace('"', "\"\""))
+	} else {
+		s.to_string()
+	}
+}
+
+/// Grades the proofs handed in to `dir`, which has a directory for each student. Students are listed in order
+/// of name. Returns `Err` if `dir` can't be read.
+pub fn grade_dir(a: &Assignment, dir: &Path) -> Result<Report, String> {
+	let mut students = Vec::new();
+	for entry in try!(fs::read_dir(dir).map_err(|e| e.to_string())) {
+		let entry = try!(entry.map_err(|e| e.to_string()));
+		if entry.path().is_dir() {
+			students.push(entry.file_name().to_string_lossy().into_owned());
+		}
+	}
+	students.sort();
+	let mut marks = Vec::new();
+	for student in students.iter() {
+		for &(ref name, ref seq) in a.exercises.iter() {
+			let path = ["plc", "json"].iter().map(|ext| dir.join(student).join(format!("{}.{}", name, ext)))
+				.find(|path| path.is_file());
+			let grade = match path {
+				Some(path) => match cli::read_proof(&path) {
+					Ok(file) => a.grade_file(seq, &file),
+					Err(e) => Grade::Unreadable(e),
+				},
+				None => Grade::Missing,
+			};
+			marks.push(Mark {
+				student: student.clone(),
+				exercise: name.clone(),
+				grade: grade,
+			});
+		}
+	}
+	Ok(Report {
+		marks: marks,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{env, process};
+	
+	use check::CheckError;
+	use file::ProofFile;
+	use library::LibError;
+	use rule::Rule;
+	use super::*;
+	
+	#[test]
+	fn test_grade() {
+		let a = Assignment::parse("# Negation\n\
+			system Lemmon\n\
+			allow →I, →E, ¬I, ¬E, DN\n\
+			exercise mt: P→Q, ¬Q ⊢ ¬P\n").unwrap();
+		let seq = &a.exercises[0].1;
+		let grade = |text: &str| {
+			let g = a.grade_file(seq, &ProofFile::parse(text).unwrap());
+			println!("[test_grade] {}: {}", g.result(), g);
+			g
+		};
+		assert_eq!(grade("{1} 1. R→S ; Premise\n{2} 2. ¬S ; Premise\n{3} 3. R ; A\n{1, 3} 4. S ; →E 1, 3\n\
+			{1, 2, 3} 5. ⊥ ; ¬E 2, 4\n{1, 2} 6. ¬R ; ¬I 3, 5\n"), Grade::Proved);
+		assert_eq!(grade("{1} 1. P→Q ; Premise\n{2} 2. ¬Q ; Premise\n{1, 2} 3. ¬P ; MTT 1, 2\n"),
+			Grade::DisallowedRule(2, Rule::Mtt));
+		assert_eq!(grade("{1} 1. P→Q ; Premise\n{2} 2. ¬Q ; Premise\n{1, 2} 3. ¬P ; →E 1, 2\n"),
+			Grade::InvalidLine(2, CheckError::Mismatch(Rule::ImpliesE)));
+		assert_eq!(grade("{1} 1. P→Q ; Premise\n"), Grade::WrongConclusion(Some(Formula::new("P→Q").unwrap())));
+		assert_eq!(grade(""), Grade::WrongConclusion(None));
+		// `¬Q` renames to `¬P`, but then `¬Q` isn't a premise.
+		assert_eq!(grade("{1} 1. ¬Q ; Premise\n"), Grade::ExtraPremises(vec![0]));
+		assert_eq!(grade("{1} 1. ¬P ; A\n").to_string(), "the conclusion rests on line 1, which isn't a premise of the sequent");
+		
+		// The premises can be in any order, and the first premise a line renames isn't always the right one
+		let or = Assignment::parse("exercise or: P∨Q, P→R, Q→R ⊢ R").unwrap();
+		let g = or.grade_file(&or.exercises[0].1, &ProofFile::parse("{1} 1. B→C ; Premise\n{2} 2. A∨B ; Premise\n\
+			{3} 3. A→C ; Premise\n{4} 4. A ; A\n{3, 4} 5. C ; →E 3, 4\n{6} 6. B ; A\n{1, 6} 7. C ; →E 1, 6\n\
+			{1, 2, 3} 8. C ; ∨E 2, 4, 5, 6, 7\n").unwrap());
+		assert_eq!(g, Grade::Proved);
+		
+		let fitch = Assignment::parse("system fitch\nexercise or: P ⊢ P∨Q").unwrap();
+		let seq = &fitch.exercises[0].1;
+		let grade = |text: &str| fitch.grade_file(seq, &ProofFile::parse(text).unwrap());
+		assert_eq!(grade("{} 1. P ; Premise\n{} 2. P∨Q ; ∨I 1\n"), Grade::Proved);
+		assert_eq!(grade("{} 1. P ; Premise\n{} 2. | Q ; A\n{} 3. | P∨Q ; ∨I 1\n"), Grade::ExtraPremises(vec![1]));
+		
+		assert_eq!(Assignment::parse("exercise a: P ⊢ Q").err(), Some((0, SpecError::Lib(LibError::Invalid(vec![('P', true), ('Q', false)])))));
+		assert_eq!(Assignment::parse("exercise a: P ⊢ P\nexercise a: Q ⊢ Q").err(), Some((1, SpecError::Duplicate("a".to_string()))));
+		assert_eq!(Assignment::parse("exercise ../a: P ⊢ P").err(), Some((0, SpecError::BadName("../a".to_string()))));
+		assert_eq!(Assignment::parse("\nsystem Gentzen").err(), Some((1, SpecError::UnknownSystem("Gentzen".to_string()))));
+		assert!(Assignment::parse("forbid MTT\nrule DS: P∨Q, ¬P ⊢ Q\nexercise a: P ⊢ P").unwrap().lib.has_rule("DS"));
+		
+		let report = Report {
+			marks: vec![
+				Mark { student: "ada".to_string(), exercise: "mt".to_string(), grade: Grade::Proved },
+				Mark { student: "bob".to_string(), exercise: "mt".to_string(), grade: Grade::ExtraPremises(vec![0, 2]) },
+			],
+		};
+		assert!(!report.all_proved());
+		assert_eq!(report.to_csv(), "student,exercise,result,line,detail\n\
+			ada,mt,proved,,the proof is correct\n\
+			bob,mt,extra premises,1,\"the conclusion rests on lines 1, 3, which aren't premises of the sequent\"\n");
+		assert_eq!(format!("{}", report.to_json().as_array().unwrap()[0]),
+			r#"{"student":"ada","exercise":"mt","result":"proved","line":null,"detail":"the proof is correct"}"#);
+	}
+	
+	#[test]
+	fn test_grade_dir() {
+		let dir = env::temp_dir().join(format!("plc_test_grade_dir_{}", process::id()));
+		let _ = fs::remove_dir_all(&dir);
+		for student in ["ada", "bob", "cy"].iter() {
+			fs::create_dir_all(dir.join(student)).unwrap();
+		}
+		fs::write(dir.join("ada").join("mt.plc"), "{1} 1. P→Q ; Premise\n{2} 2. ¬Q ; Premise\n{1, 2} 3. ¬P ; MTT 1, 2\n")
+			.unwrap();
+		fs::write(dir.join("cy").join("mt.json"), "{\"version\": 1").unwrap();
+		// Files next to the students' directories are skipped
+		fs::write(dir.join("notes.txt"), "").unwrap();
+		
+		let a = Assignment::parse("exercise mt: P→Q, ¬Q ⊢ ¬P").unwrap();
+		let report = grade_dir(&a, &dir);
+		let _ = fs::remove_dir_all(&dir);
+		let report = report.unwrap();
+		println!("[test_grade_dir]\n{}", report.to_csv());
+		let grades: Vec<(&str, &Grade)> = report.marks.iter().map(|m| (&m.student[..], &m.grade)).collect();
+		assert_eq!(grades.len(), 3);
+		assert_eq!(grades[0], ("ada", &Grade::Proved));
+		assert_eq!(grades[1], ("bob", &Grade::Missing));
+		assert_eq!(grades[2].0, "cy");
+		assert_eq!(grades[2].1.result(), "unreadable");
+		assert!(grade_dir(&a, &dir).is_err());
+	}
+}
@@ -1,19 +1,43 @@
 
-use edit::Editor;
+use logic::Tokens;
 
-use std::fmt::Write;
+use check::Diagnostic;
+use cli;
+use edit::{Col, Editor, Lines, Selection};
+use fitch;
+use input::{self, Change, EditorCommand, Key, KeyInput};
+use system::{ProofSystem, Structure, Lemmon, ConvertError};
+use tree::Tree;
 
-use gtk::{self, Widget, Window, Frame, EventBox, DrawingArea, WindowPosition};
+use std::cmp;
+use std::env;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::mem;
+
+use gtk::{self, Clipboard, Widget, Window, Frame, EventBox, DrawingArea, WindowPosition};
 use gtk::signal::Inhibit;
 use gtk::traits::*;
-use gdk::EventType;
+use gdk::{self, EventKey, EventType};
+use gdk::enums::{event_mask, key, modifier_type};
 use cairo::{Context, Antialias};
 use cairo::enums::{FontSlant, FontWeight};
 
 pub struct Gui {
 	#[allow(dead_code)]
 	win: &'static mut Window,
-	edit: Editor,
+	edit: Editor<Box<ProofSystem>>,
+	/// True if a proof in a system with dependencies is shown in Fitch style. Toggled with F2.
+	fitch_view: bool,
+	/// True if the proof is shown as a Gentzen-style tree. Toggled with F3.
+	tree_view: bool,
+	/// The `.plc` file given on the command line, which Ctrl+S saves to.
+	path: Option<String>,
+	/// Where the lines of the proof were drawn by the last `render`, so that clicks can be mapped back to them.
+	/// Empty if the proof isn't shown as it is edited, e.g. as a tree.
+	layout: Vec<LineLayout>,
+	/// A message shown in the status area until the next key press, e.g. a problem with the rule file.
+	message: Option<String>,
 }
 impl Gui {
 	pub fn new(win: &'static mut Window) -> Gui {
@@ -33,11 +57,47 @@ impl Gui {
 			let eb = EventBox::new().unwrap();
 			win.connect_key_press_event(|_, e| {
 				if e._type == EventType::KeyPress {
-					let ih = ::get_gui().edit.handle_input(e);
-					if ih == Inhibit(true) {
-						::get_gui().dirty();
+					::get_gui().message = None;
+					match unsafe { mem::transmute(e.keyval) } {
+						key::F2 => {
+							let gui = ::get_gui();
+							gui.fitch_view = !gui.fitch_view;
+							gui.dirty();
+							return Inhibit(true);
+						},
+						key::F3 => {
+							let gui = ::get_gui();
+							gui.tree_view = !gui.tree_view;
+							gui.dirty();
+							return Inhibit(true);
+						},
+						_ => {},
+					}
+					if e.state.contains(modifier_type::ControlMask) {
+						match unsafe { mem::transmute(e.keyval) } {
+							key::s => {
+								::get_gui().save();
+								return Inhibit(true);
+							},
+							key::c => {
+								::get_gui().copy();
+								return Inhibit(true);
+							},
+							key::x => {
+								::get_gui().cut();
+								return Inhibit(true);
+							},
+							key::v => {
+								::get_gui().paste();
+								return Inhibit(true);
+							},
+							_ => {},
+						}
+					}
+					match key_input(e) {
+						Some(k) => Inhibit(::get_gui().edit.handle_key(&k)),
+						None => Inhibit(false),
 					}
-					ih
 				} else {
 					Inhibit(false)
 				}
@@ -48,6 +108,26 @@ impl Gui {
 				
 				Inhibit(false)
 			});
+			// Clicking places the cursor, and dragging with the button held selects
+			eb.add_events(event_mask::Button1MotionMask.bits() as i32);
+			eb.connect_button_press_event(|_, e| {
+				if e.button == 1 {
+					let gui = ::get_gui();
+					if let Some((no, col, i)) = gui.hit(e.x, e.y) {
+						gui.edit.execute(EditorCommand::MoveTo(no, col, i, e.state.contains(modifier_type::ShiftMask)));
+					}
+				}
+				Inhibit(true)
+			});
+			eb.connect_motion_notify_event(|_, e| {
+				if e.state.contains(modifier_type::Button1Mask) {
+					let gui = ::get_gui();
+					if let Some((no, col, i)) = gui.hit(e.x, e.y) {
+						gui.edit.execute(EditorCommand::MoveTo(no, col, i, true));
+					}
+				}
+				Inhibit(true)
+			});
 			eb.add(&da);
 			da_frame.add(&eb);
 		}
@@ -55,24 +135,126 @@ impl Gui {
 		
 		win.show_all();
 		
+		// Problems with the environment variables are shown in the status area as well as on stderr
+		let mut problems = Vec::new();
+		let mut edit = Editor::with_system(cli::env_system().unwrap_or_else(|e| {
+			problems.push(e);
+			Box::new(Lemmon)
+		}));
+		if let Err(e) = cli::env_rules(edit.library_mut()) {
+			problems.push(e);
+		}
+		edit.update_deps();
+		for e in problems.iter() {
+			eprintln!("{}", e);
+		}
+		let message = if problems.is_empty() { None } else { Some(problems.join("; ")) };
+		let path = env::args().nth(1);
+		if let Some(ref path) = path {
+			Gui::load(&mut edit, path);
+		}
+		// Added after loading, as there is no `Gui` to redraw until this returns
+		edit.on_change(|change| {
+			let broken = change.broken();
+			if !broken.is_empty() {
+				let nos: Vec<String> = broken.iter().map(|no| (no + 1).to_string()).collect();
+				eprintln!("warning: lines {} cite a deleted line", nos.join(", "));
+			}
+			::get_gui().dirty();
+		});
 		Gui {
 			win: win,
-			edit: Editor::new(),
+			edit: edit,
+			fitch_view: false,
+			tree_view: false,
+			path: path,
+			layout: Vec::new(),
+			message: message,
 		}
 	}
 	
+	/// Loads the proof in the `.plc` file at `path`. A file that doesn't exist yet is created when the proof
+	/// is saved, so the sample proof is kept.
+	fn load(edit: &mut Editor<Box<ProofSystem>>, path: &str) {
+		let mut text = String::new();
+		match File::open(path).and_then(|mut f| f.read_to_string(&mut text)) {
+			Ok(_) => if let Err(e) = edit.load(&text) {
+				eprintln!("{}: {}", path, e);
+			},
+			Err(e) => eprintln!("{}: {}", path, e),
+		}
+	}
+	
+	/// Saves the proof to the file given on the command line.
+	pub fn save(&self) {
+		let path = match self.path {
+			Some(ref path) => path,
+			None => {
+				eprintln!("no file to save to: give one on the command line");
+				return;
+			},
+		};
+		if let Err(e) = File::create(path).and_then(|mut f| f.write_all(self.edit.save().as_bytes())) {
+			eprintln!("{}: {}", path, e);
+		}
+	}
+	
+	/// Copies the selection to the clipboard.
+	pub fn copy(&mut self) {
+		if let Some(Change::Copy(text)) = self.edit.execute(EditorCommand::Copy) {
+			Gui::clipboard().set_text(&text);
+		}
+	}
+	
+	/// Cuts the selection to the clipboard.
+	pub fn cut(&mut self) {
+		if let Some(text) = self.edit.cut() {
+			Gui::clipboard().set_text(&text);
+		}
+	}
+	
+	/// Pastes the text on the clipboard at the cursor. Lines that can't be read are reported, and not pasted.
+	pub fn paste(&mut self) {
+		if let Some(text) = Gui::clipboard().wait_for_text() {
+			if let Err(e) = self.edit.paste(&text) {
+				eprintln!("paste: {}", e);
+			}
+		}
+	}
+	
+	fn clipboard() -> Clipboard {
+		Clipboard::get(&gdk::SELECTION_CLIPBOARD)
+	}
+	
 	pub fn dirty(&self) {
 		self.win.queue_draw();
 	}
 	
-	pub fn render(&self, w: Widget, c: Context) {
-		let (_alloc_w, _alloc_h) = (w.get_allocated_width(), w.get_allocated_height());
+	pub fn render(&mut self, w: Widget, c: Context) {
+		// Checked once here, as both the proof and the status use the diagnostics
+		let diagnostics = self.edit.check();
+		let mut layout = Vec::new();
+		self.render_proof(w, c, &diagnostics, &mut layout);
+		self.layout = layout;
+	}
+	
+	/// The line, column and token of the proof nearest to `(x, y)` in the drawing area, as it was last drawn.
+	/// `None` if the proof isn't shown as it is edited.
+	fn hit(&self, x: f64, y: f64) -> Option<(usize, Col, usize)> {
+		find_token(&self.layout, x, y)
+	}
+	
+	/// Renders the proof with the problems in `diagnostics` marked, adding where each line of the proof being
+	/// edited is drawn to `layout`.
+	fn render_proof(&self, w: Widget, c: Context, diagnostics: &[Diagnostic], layout: &mut Vec<LineLayout>) {
+		let (_alloc_w, alloc_h) = (w.get_allocated_width(), w.get_allocated_height());
 		
 		const FONT_SIZE: f64 = 17.0;
 		const SCALE: f64 = FONT_SIZE * 1.0; // 1.0 for Helvetica, 
 		c.select_font_face("Times New Roman", FontSlant::Normal, FontWeight::Normal);
 		c.set_antialias(Antialias::Best);
 		c.set_font_size(FONT_SIZE);
+		self.render_status(&c, alloc_h as f64, diagnostics);
 		c.new_path();
 		c.translate((SCALE * 0.1).floor(), (SCALE + 10.0).floor());
 		
@@ -80,8 +262,35 @@ impl Gui {
 		let lines_len = if self.edit.lines().len() < 10 { 10 } else { self.edit.lines().len() };
 		let start_offset = ((SCALE / 2.5) + SCALE * 0.5 * ((lines_len as f64).log10().floor() + 1.0)).floor();
 		c.translate(start_offset, 0.0);
+		if self.tree_view {
+			match self.tree() {
+				Ok(tree) => {
+					self.render_tree(&c, &tree, SCALE);
+					return;
+				},
+				Err(e) => {
+					// The proof is shown as it is, under the reason it can't be drawn as a tree
+					c.new_path();
+					c.text_path(&format!("No proof tree: {}", e));
+					c.fill();
+					c.translate(0.0, SCALE + 10.0);
+				},
+			}
+		}
+		if self.edit.system().structure() == Structure::Subproofs {
+			self.render_fitch(&c, self.edit.lines(), diagnostics, Some(layout), SCALE);
+			return;
+		}
+		if self.fitch_view {
+			// Proofs that can't be converted are shown as they are. The diagnostics are for the proof as it is,
+			// so aren't shown on the converted one.
+			if let Ok(proof) = fitch::from_lemmon(self.edit.lines()) {
+				self.render_fitch(&c, &proof, &[], None, SCALE);
+				return;
+			}
+		}
 		for l in self.edit.lines().iter() {
-			let mut undo_x = 0.0;
+			let spans = spans(self.edit.lines(), diagnostics, l.no);
 			{ // Render the line number (Align the points all at the same x co-ordinate)
 				let s = format!("{}.", l.no + 1);
 				c.new_path();
@@ -91,54 +300,351 @@ impl Gui {
 				c.new_path();
 				//print!("ex0: {}, ex1: {}, ex2: {}, ex3: {}", ex.0, ex.1, ex.2, ex.3);
 				let offset = -ex.2.floor();
-				//println!(", undo_x: {}", undo_x);
 				c.translate(offset, 0.0);
 				c.append_path(&p);
 				c.translate(-offset, 0.0);
-				undo_x = 0.0;
 				c.fill();
 			}
 			
+			let mut cols = Vec::with_capacity(3);
 			{ // Render the `step` part of the line
 				let s = l.step.to_gui_string(true);
+				let x = lemmon_x(Col::Step, SCALE);
+				let edges = edges(&c, x, &l.step, |t| t.to_gui_string(true));
+				self.render_selection(&c, l.no, Col::Step, &edges, SCALE);
 				c.new_path();
-				let trans_x = SCALE * 0.5;
-				undo_x += trans_x;
-				c.translate(trans_x, 0.0);
+				c.move_to(x, 0.0);
 				c.text_path(&s);
 				c.fill();
+				for &(_, start, end) in spans.iter().filter(|s| s.0 == Col::Step) {
+					underline(&c, &edges, start, end, SCALE);
+				}
+				self.render_cursor(&c, l.no, Col::Step, &edges, SCALE);
+				cols.push((Col::Step, edges));
 			}
 			
 			{ // Render the `method` part of the line
 				let s = l.method.to_gui_string(false);
+				let x = lemmon_x(Col::Method, SCALE);
+				let edges = edges(&c, x, &l.method, |t| t.to_gui_string(false));
+				self.render_selection(&c, l.no, Col::Method, &edges, SCALE);
 				c.new_path();
-				let trans_x = SCALE * 20.0;
-				undo_x += trans_x;
-				c.translate(trans_x, 0.0);
+				c.move_to(x, 0.0);
 				c.text_path(&s);
-				c.fill();
+				if self.edit.broken_refs().contains(&l.no) || spans.iter().any(|s| s.0 == Col::Method) {
+					// References to a deleted line, or a justification that is wrong
+					c.set_source_rgb(0.8, 0.0, 0.0);
+					c.fill();
+					c.set_source_rgb(0.0, 0.0, 0.0);
+				} else {
+					c.fill();
+				}
+				self.render_cursor(&c, l.no, Col::Method, &edges, SCALE);
+				cols.push((Col::Method, edges));
 			}
 			
 			{ // Render the dependencies of the line
-				let mut s = String::with_capacity(32);
-				s.push('{');
-				let max = l.deps.len().wrapping_sub(1);
-				for (i, dep) in l.deps.iter().enumerate() {
-					let _ = write!(s, "{}", dep + 1);
-					if i != max {
-						s.push(',');
-						s.push(' ');
-					}
+				let deps = l.deps_tokens();
+				let s = format!("{{{}}}", deps);
+				let x = lemmon_x(Col::Deps, SCALE);
+				// The tokens start after the `{`
+				let edges = edges(&c, x, &deps, |t| format!("{{{}", t));
+				self.render_selection(&c, l.no, Col::Deps, &edges, SCALE);
+				c.new_path();
+				c.move_to(x, 0.0);
+				c.text_path(&s);
+				if self.edit.dep_conflicts().contains(&l.no) || self.edit.dep_errors().iter().any(|&(no, _)| no == l.no)
+						|| spans.iter().any(|s| s.0 == Col::Deps) {
+					// Hand-entered dependencies that disagree with the justification or can't be read, or wrong
+					// dependencies
+					c.set_source_rgb(0.8, 0.0, 0.0);
+					c.fill();
+					c.set_source_rgb(0.0, 0.0, 0.0);
+				} else {
+					c.fill();
 				}
-				s.push('}');
-				let trans_x = SCALE * 8.0;
-				undo_x += trans_x;
-				c.translate(trans_x, 0.0);
+				self.render_cursor(&c, l.no, Col::Deps, &edges, SCALE);
+				cols.push((Col::Deps, edges));
+			}
+			layout.push(LineLayout::new(|x, y| c.user_to_device(x, y), l.no, cols));
+			
+			c.translate(0.0, SCALE + 10.0);
+		}
+	}
+	
+	/// Draws the selection behind the tokens of column `col` of line `no`, if they are selected, where `edges`
+	/// are the edges between the tokens.
+	fn render_selection(&self, c: &Context, no: usize, col: Col, edges: &[f64], scale: f64) {
+		let (start, end) = match self.edit.selection() {
+			Some(Selection::Tokens { no: n, col: sel_col, start, end }) if n == no && sel_col == col => (start, end),
+			Some(Selection::Lines(start, end)) if start <= no && no < end => (0, edges.len() - 1),
+			_ => return,
+		};
+		c.new_path();
+		c.rectangle(edges[start], -scale, edges[end] - edges[start], scale + 5.0);
+		c.set_source_rgb(0.7, 0.8, 1.0);
+		c.fill();
+		c.set_source_rgb(0.0, 0.0, 0.0);
+	}
+	
+	/// Draws the cursor as a caret at its token, if it is in column `col` of line `no`.
+	fn render_cursor(&self, c: &Context, no: usize, col: Col, edges: &[f64], scale: f64) {
+		let (cur_no, cur_col, i) = self.edit.cursor().position();
+		if cur_no != no || cur_col != col {
+			return;
+		}
+		let x = edges[cmp::min(i, edges.len() - 1)];
+		c.new_path();
+		c.move_to(x, -scale * 0.9);
+		c.line_to(x, 4.0);
+		c.stroke();
+	}
+	
+	/// Draws the message, or else what is wrong with the line the cursor is on, if anything, in red along the
+	/// bottom of the drawing area, `height` high. `diagnostics` are the problems the checker found.
+	fn render_status(&self, c: &Context, height: f64, diagnostics: &[Diagnostic]) {
+		let (no, _, _) = self.edit.cursor().position();
+		if let Some(msg) = self.message.clone().or_else(|| self.edit.problem_with(no, diagnostics)) {
+			c.new_path();
+			c.move_to(10.0, height - 10.0);
+			c.text_path(&msg);
+			c.set_source_rgb(0.8, 0.0, 0.0);
+			c.fill();
+			c.set_source_rgb(0.0, 0.0, 0.0);
+		}
+	}
+	
+	/// Builds the tree of the last line of the proof, converting Fitch proofs to Lemmon's system first.
+	fn tree(&self) -> Result<Tree, ConvertError> {
+		let lines = self.edit.lines();
+		let lemmon = if self.edit.system().structure() == Structure::Subproofs {
+			try!(fitch::to_lemmon(lines))
+		} else {
+			lines.clone()
+		};
+		Tree::from_line(&lemmon, self.edit.library(), lemmon.len().saturating_sub(1))
+	}
+	
+	/// Renders `tree` as text in a fixed width font, so that the inference lines line up with the formulas.
+	fn render_tree(&self, c: &Context, tree: &Tree, scale: f64) {
+		c.select_font_face("Monospace", FontSlant::Normal, FontWeight::Normal);
+		for row in format!("{:#}", tree).lines() {
+			c.new_path();
+			c.move_to(0.0, 0.0);
+			c.text_path(row);
+			c.fill();
+			c.translate(0.0, scale + 4.0);
+		}
+	}
+	
+	/// Renders `proof` in Fitch style, with a bar down the left of each subproof and a short line under each
+	/// assumption and under the premises. The problems in `diagnostics` are marked as in the Lemmon view. If
+	/// `proof` is the proof being edited, `layout` is given, and the cursor and selection are drawn too.
+	fn render_fitch(&self, c: &Context, proof: &Lines, diagnostics: &[Diagnostic], mut layout: Option<&mut Vec<LineLayout>>,
+			scale: f64) {
+		let step_x = scale * 0.5;
+		let bar_gap = (scale * 0.6).floor();
+		c.set_line_width(1.0);
+		for (no, l) in proof.iter().enumerate() {
+			let spans = spans(proof, diagnostics, no);
+			{ // Render the line number (Align the points all at the same x co-ordinate)
+				let s = format!("{}.", no + 1);
+				c.new_path();
 				c.text_path(&s);
+				let p = c.copy_path();
+				let ex = c.fill_extents();
+				c.new_path();
+				let offset = -ex.2.floor();
+				c.translate(offset, 0.0);
+				c.append_path(&p);
+				c.translate(-offset, 0.0);
 				c.fill();
 			}
 			
-			c.translate(-undo_x, SCALE + 10.0);
+			{ // Render a bar for each subproof the line is in, spanning the whole line height
+				c.new_path();
+				for d in 0..l.depth + 1 {
+					let x = step_x + bar_gap * d as f64;
+					c.move_to(x, -scale);
+					c.line_to(x, 10.0);
+				}
+				if fitch::is_hypothesis_end(proof, no) {
+					let x = step_x + bar_gap * l.depth as f64;
+					c.move_to(x, 5.0);
+					c.line_to(x + scale * 3.0, 5.0);
+				}
+				c.stroke();
+			}
+			
+			let mut cols = Vec::with_capacity(2);
+			{ // Render the `step` part of the line, just inside its innermost bar
+				let x = step_x + bar_gap * (l.depth as f64 + 0.5);
+				let edges = edges(c, x, &l.step, |t| t.to_gui_string(true));
+				if layout.is_some() {
+					self.render_selection(c, no, Col::Step, &edges, scale);
+				}
+				c.new_path();
+				c.move_to(x, 0.0);
+				c.text_path(&l.step.to_gui_string(true));
+				c.fill();
+				for &(_, start, end) in spans.iter().filter(|s| s.0 == Col::Step) {
+					underline(c, &edges, start, end, scale);
+				}
+				if layout.is_some() {
+					self.render_cursor(c, no, Col::Step, &edges, scale);
+				}
+				cols.push((Col::Step, edges));
+			}
+			
+			{ // Render the `method` part of the line, in the same column as in the Lemmon view
+				let x = step_x + scale * 20.0;
+				let edges = edges(c, x, &l.method, |t| t.to_gui_string(false));
+				if layout.is_some() {
+					self.render_selection(c, no, Col::Method, &edges, scale);
+				}
+				c.new_path();
+				c.move_to(x, 0.0);
+				c.text_path(&l.method.to_gui_string(false));
+				if spans.iter().any(|s| s.0 == Col::Method) {
+					c.set_source_rgb(0.8, 0.0, 0.0);
+					c.fill();
+					c.set_source_rgb(0.0, 0.0, 0.0);
+				} else {
+					c.fill();
+				}
+				if layout.is_some() {
+					self.render_cursor(c, no, Col::Method, &edges, scale);
+				}
+				cols.push((Col::Method, edges));
+			}
+			if let Some(ref mut layout) = layout {
+				layout.push(LineLayout::new(|x, y| c.user_to_device(x, y), no, cols));
+			}
+			
+			c.translate(0.0, scale + 10.0);
 		}
 	}
 }
+
+/// The parts of line `no` that the diagnostics for it are about. See `Diagnostic::span`.
+fn spans(lines: &Lines, diagnostics: &[Diagnostic], no: usize) -> Vec<(Col, usize, usize)> {
+	diagnostics.iter().filter(|d| d.no == no).map(|d| d.span(lines)).collect()
+}
+
+/// Where a line of the proof was drawn, in the co-ordinates of the drawing area.
+struct LineLayout {
+	no: usize,
+	/// The bottom of the line, which is the top of the next.
+	bottom: f64,
+	/// Where the edges between the tokens of each column shown are. See `input::nearest_edge`.
+	cols: Vec<(Col, Vec<f64>)>,
+}
+impl LineLayout {
+	/// The layout of line `no`, drawn on the baseline of the user co-ordinates that `to_device` converts, with the
+	/// edges between the tokens of its columns in `cols`. Every column must be measured from the same origin.
+	fn new<F: Fn(f64, f64) -> (f64, f64)>(to_device: F, no: usize, cols: Vec<(Col, Vec<f64>)>) -> LineLayout {
+		LineLayout {
+			no: no,
+			bottom: to_device(0.0, 5.0).1,
+			cols: cols.into_iter().map(|(col, edges)| (col, edges.iter().map(|&x| to_device(x, 0.0).0).collect()))
+				.collect(),
+		}
+	}
+}
+
+/// The line, column and token of the proof nearest to `(x, y)` in the drawing area, given where its lines were
+/// drawn.
+fn find_token(layout: &[LineLayout], x: f64, y: f64) -> Option<(usize, Col, usize)> {
+	let line = match layout.iter().find(|l| y < l.bottom).or(layout.last()) {
+		Some(l) => l,
+		None => return None,
+	};
+	// The column clicked is the last one that starts to the left of the click
+	match line.cols.iter().rev().find(|&&(_, ref edges)| edges[0] <= x).or(line.cols.first()) {
+		Some(&(col, ref edges)) => Some((line.no, col, input::nearest_edge(edges, x))),
+		None => None,
+	}
+}
+
+/// Where each column of a line starts in the Lemmon view, measured from the line number.
+fn lemmon_x(col: Col, scale: f64) -> f64 {
+	match col {
+		Col::Step   => scale * 0.5,
+		Col::Method => scale * 20.5,
+		Col::Deps   => scale * 28.5,
+	}
+}
+
+/// How far drawing `s` in the current font moves along. Spaces count, unlike in the ink of the text.
+fn text_width(c: &Context, s: &str) -> f64 {
+	c.text_extents(s).x_advance
+}
+
+/// Where the edges between the tokens of `toks` are drawn, from the left of the first to the right of the
+/// last, when they are drawn from `x` as `show` writes them.
+fn edges<F: Fn(&Tokens) -> String>(c: &Context, x: f64, toks: &Tokens, show: F) -> Vec<f64> {
+	(0..toks.len() + 1).map(|n| x + text_width(c, &show(&Tokens::from_vec(toks[..n].to_vec())))).collect()
+}
+
+/// Underlines tokens `start..end` in red, where `edges` are the edges between the tokens. A range past the last
+/// token underlines the gap after them, where a formula that ends too soon is missing something.
+fn underline(c: &Context, edges: &[f64], start: usize, end: usize, scale: f64) {
+	let last = edges.len() - 1;
+	let x0 = edges[cmp::min(start, last)];
+	let x1 = if end > last { edges[last] + scale * 0.5 } else { edges[end] };
+	c.new_path();
+	c.move_to(x0, 4.0);
+	c.line_to(x1, 4.0);
+	c.set_source_rgb(0.8, 0.0, 0.0);
+	c.stroke();
+	c.set_source_rgb(0.0, 0.0, 0.0);
+}
+
+/// Translates a gdk key press into a `KeyInput` for the editor. Returns `None` for keys that type nothing and
+/// that the editor has no use for.
+fn key_input(e: &EventKey) -> Option<KeyInput> {
+	let c = gdk::keyval_to_unicode(e.keyval);
+	let key = match unsafe { mem::transmute(e.keyval) } {
+		key::Left => Key::Left,
+		key::Right => Key::Right,
+		key::Up => Key::Up,
+		key::Down => Key::Down,
+		key::Home => Key::Home,
+		key::End => Key::End,
+		key::Tab => Key::Tab,
+		key::ISO_Left_Tab => Key::BackTab,
+		key::Delete => Key::Delete,
+		key::BackSpace => Key::Backspace,
+		key::Return => Key::Return,
+		_ => match c {
+			Some(c) => Key::Char(c),
+			None => return None,
+		},
+	};
+	Some(KeyInput {
+		key: key,
+		shift: e.state.contains(modifier_type::ShiftMask),
+		ctrl: e.state.contains(modifier_type::ControlMask),
+		alt: e.state.contains(modifier_type::Mod1Mask),
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use edit::Col;
+	use super::*;
+	
+	#[test]
+	fn test_find_token() {
+		// A line laid out as in the Lemmon view, with tokens 10 wide, drawn 40 across and 100 down
+		const SCALE: f64 = 17.0;
+		let cols = [Col::Step, Col::Method, Col::Deps].iter()
+			.map(|&col| (col, (0..4).map(|n| lemmon_x(col, SCALE) + n as f64 * 10.0).collect())).collect();
+		let layout = vec![LineLayout::new(|x, y| (x + 40.0, y + 100.0), 2, cols)];
+		assert_eq!(find_token(&layout, 40.0 + lemmon_x(Col::Step, SCALE) + 21.0, 100.0), Some((2, Col::Step, 2)));
+		assert_eq!(find_token(&layout, 40.0 + lemmon_x(Col::Method, SCALE) + 9.0, 100.0), Some((2, Col::Method, 1)));
+		assert_eq!(find_token(&layout, 40.0 + lemmon_x(Col::Deps, SCALE) + 100.0, 200.0), Some((2, Col::Deps, 3)));
+		assert_eq!(find_token(&layout, 0.0, 0.0), Some((2, Col::Step, 0)));
+		assert_eq!(find_token(&[], 0.0, 0.0), None);
+	}
+}
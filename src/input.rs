@@ -0,0 +1,266 @@
+//! Input to the `Editor`, independent of the toolkit it comes from.
+//!
+//! A frontend translates its key presses into `KeyInput`s and passes them to `Editor::handle_key`, or gives the
+//! editor `EditorCommand`s directly with `Editor::execute`. The editor tells its listeners what each command
+//! changed, with a `Change`, so the frontend knows when to redraw.
+use logic::Token;
+use logic::consts::ALLOWED_CHARS;
+
+use edit::Col;
+
+/// A key that the editor responds to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Key {
+	/// A key that types a character.
+	Char(char),
+	Left,
+	Right,
+	Up,
+	Down,
+	Home,
+	End,
+	Tab,
+	/// Shift+Tab.
+	BackTab,
+	Delete,
+	Backspace,
+	Return,
+}
+
+/// A key press, with the modifier keys held down.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct KeyInput {
+	pub key: Key,
+	pub shift: bool,
+	pub ctrl: bool,
+	pub alt: bool,
+}
+impl KeyInput {
+	/// A key pressed without any modifiers.
+	pub fn new(key: Key) -> KeyInput {
+		KeyInput {
+			key: key,
+			shift: false,
+			ctrl: false,
+			alt: false,
+		}
+	}
+	/// The key pressed with Shift held down as well.
+	pub fn with_shift(mut self) -> KeyInput {
+		self.shift = true;
+		self
+	}
+	/// The key pressed with Ctrl held down as well.
+	pub fn with_ctrl(mut self) -> KeyInput {
+		self.ctrl = true;
+		self
+	}
+	/// The key pressed with Alt held down as well.
+	pub fn with_alt(mut self) -> KeyInput {
+		self.alt = true;
+		self
+	}
+}
+
+/// A way of moving the cursor. See `Cursor` for what each does.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Motion {
+	Left,
+	Right,
+	Up,
+	Down,
+	WordLeft,
+	WordRight,
+	Home,
+	End,
+}
+
+/// Something the editor can be told to do.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EditorCommand {
+	Undo,
+	Redo,
+	SelectAll,
+	MoveLineUp,
+	MoveLineDown,
+	/// Moves the cursor, extending the selection if the flag is set and clearing it otherwise.
+	Move(Motion, bool),
+	/// Moves the cursor to a token of a column of a line, e.g. where the proof was clicked. The selection is
+	/// extended or cleared as with `Move`.
+	MoveTo(usize, Col, usize, bool),
+	NextCol,
+	PrevCol,
+	Delete,
+	Backspace,
+	Newline,
+	NewlineAbove,
+	/// Types a token at the cursor, replacing the selection.
+	Insert(Token),
+	/// Copies the selection. The text for the clipboard is given to the listeners with `Change::Copy`.
+	Copy,
+	/// Deletes the selection. The text for the clipboard is given to the listeners with `Change::Cut`.
+	Cut,
+	/// Pastes text from the clipboard at the cursor. See `Cursor::paste`.
+	Paste(String),
+}
+impl EditorCommand {
+	/// The command bound to a key, or `None` if the key isn't used by the editor:
+	///
+	/// - the arrow keys, Home and End move the cursor, and Ctrl+Left and Ctrl+Right move by words. With Shift
+	///   held down they extend the selection.
+	/// - Tab and Shift+Tab move between the columns.
+	/// - Alt+Up and Alt+Down move the line up and down.
+	/// - Ctrl+Z undoes, Ctrl+Shift+Z redoes, and Ctrl+A selects everything.
+	/// - Return starts a new line below, and Shift+Return one above.
+	/// - Letters and the characters in `ALLOWED_CHARS` are typed, unless Ctrl or Alt is held down.
+	pub fn from_key(k: &KeyInput) -> Option<EditorCommand> {
+		use self::EditorCommand::*;
+		Some(match k.key {
+			Key::Char('z') | Key::Char('Z') if k.ctrl => if k.shift { Redo } else { Undo },
+			Key::Char('a') if k.ctrl => SelectAll,
+			Key::Up if k.alt => MoveLineUp,
+			Key::Down if k.alt => MoveLineDown,
+			Key::Right if k.ctrl => Move(Motion::WordRight, k.shift),
+			Key::Left if k.ctrl => Move(Motion::WordLeft, k.shift),
+			Key::Right => Move(Motion::Right, k.shift),
+			Key::Left  => Move(Motion::Left, k.shift),
+			Key::Up    => Move(Motion::Up, k.shift),
+			Key::Down  => Move(Motion::Down, k.shift),
+			Key::Home  => Move(Motion::Home, k.shift),
+			Key::End   => Move(Motion::End, k.shift),
+			Key::Tab     => NextCol,
+			Key::BackTab => PrevCol,
+			Key::Delete  => Delete,
+			Key::Return if k.shift => NewlineAbove,
+			Key::Return    => Newline,
+			Key::Backspace => Backspace,
+			_ if k.ctrl || k.alt => return None,
+			Key::Char(c) if c.is_alphabetic() || ALLOWED_CHARS.contains(c) => Insert(Token::Char(c)),
+			Key::Char(_) => return None,
+		})
+	}
+}
+
+/// The index of the edge in `edges` nearest to `x`, where `edges` holds where the edges between the tokens of a
+/// column were drawn, from the left of the first token to the right of the last. This maps a click on the
+/// column back to where the cursor goes in it.
+pub fn nearest_edge(edges: &[f64], x: f64) -> usize {
+	let mut nearest = 0;
+	for (i, &e) in edges.iter().enumerate() {
+		if (e - x).abs() < (edges[nearest] - x).abs() {
+			nearest = i;
+		}
+	}
+	nearest
+}
+
+/// What a command changed, as told to the editor's listeners.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change {
+	/// The cursor moved or the selection changed, but the proof didn't.
+	Cursor,
+	/// The proof was edited. `broken` holds the lines that cite a deleted line and didn't before the edit.
+	Edit {
+		broken: Vec<usize>,
+	},
+	/// The selection was copied. Contains the text for the frontend to put on its clipboard.
+	Copy(String),
+	/// The selection was cut, which edits the proof as with `Edit`. `text` is for the frontend to put on its
+	/// clipboard.
+	Cut {
+		text: String,
+		broken: Vec<usize>,
+	},
+}
+impl Change {
+	/// True if the proof was changed, so that it needs saving.
+	pub fn is_edit(&self) -> bool {
+		match *self {
+			Change::Edit { .. } | Change::Cut { .. } => true,
+			Change::Cursor | Change::Copy(_) => false,
+		}
+	}
+	/// The lines that cite a deleted line and didn't before the change.
+	pub fn broken(&self) -> &[usize] {
+		match *self {
+			Change::Edit { ref broken } | Change::Cut { ref broken, .. } => broken,
+			Change::Cursor | Change::Copy(_) => &[],
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::cell::RefCell;
+	use std::rc::Rc;
+	
+	use logic::Token;
+	
+	use edit::{Col, Editor, Selection};
+	use super::*;
+	
+	#[test]
+	fn test_key_input() {
+		assert_eq!(EditorCommand::from_key(&KeyInput::new(Key::Char('z')).with_ctrl().with_shift()), Some(EditorCommand::Redo));
+		assert_eq!(EditorCommand::from_key(&KeyInput::new(Key::Left).with_ctrl().with_shift()),
+			Some(EditorCommand::Move(Motion::WordLeft, true)));
+		assert_eq!(EditorCommand::from_key(&KeyInput::new(Key::Char('∧'))), Some(EditorCommand::Insert(Token::Char('∧'))));
+		assert_eq!(EditorCommand::from_key(&KeyInput::new(Key::Char('q')).with_alt()), None);
+		assert_eq!(EditorCommand::from_key(&KeyInput::new(Key::Char('$'))), None);
+		
+		let mut ed = Editor::new();
+		ed.load("{1}  1. P ; Premise\n{1}  2. PvQ ; vI 1\n").unwrap();
+		let changes = Rc::new(RefCell::new(Vec::new()));
+		{
+			let changes = changes.clone();
+			ed.on_change(move |c| changes.borrow_mut().push(c.clone()));
+		}
+		let keys = [
+			KeyInput::new(Key::End),
+			KeyInput::new(Key::Char('&')),
+			KeyInput::new(Key::Char('R')),
+			KeyInput::new(Key::Down).with_alt(),
+			KeyInput::new(Key::Char('z')).with_ctrl(),
+			KeyInput::new(Key::Up),
+		];
+		for k in keys.iter() {
+			assert!(ed.handle_key(k));
+		}
+		assert!(!ed.handle_key(&KeyInput::new(Key::Char('q')).with_ctrl()));
+		println!("[test_key_input] {:?}\n{}", changes.borrow(), ed);
+		assert_eq!(format!("{}", ed.lines()[0].step), "P&R");
+		assert_eq!(format!("{}", ed.lines()[1].method), "vI 1");
+		assert_eq!(*changes.borrow(), vec![Change::Cursor, Change::Edit { broken: vec![] }, Change::Edit { broken: vec![] },
+			Change::Edit { broken: vec![] }, Change::Edit { broken: vec![] }]);
+		
+		// Deleting cited lines breaks the lines citing them
+		ed.load("{1}  1. P ; Premise\n{2}  2. Q ; Premise\n{1, 2}  3. P&Q ; &I 1, 2\n").unwrap();
+		assert_eq!(ed.execute(EditorCommand::Move(Motion::Down, true)), Some(Change::Cursor));
+		assert_eq!(ed.execute(EditorCommand::Delete), Some(Change::Edit { broken: vec![0] }));
+		assert_eq!(changes.borrow().len(), 8);
+		
+		// Copying and cutting give the listeners the text for the clipboard
+		ed.load("{1}  1. P ; Premise\n{1}  2. PvQ ; vI 1\n").unwrap();
+		ed.execute(EditorCommand::Move(Motion::End, true));
+		assert_eq!(ed.execute(EditorCommand::Copy), Some(Change::Copy("P".to_string())));
+		assert_eq!(ed.execute(EditorCommand::Cut), Some(Change::Cut { text: "P".to_string(), broken: vec![] }));
+		assert_eq!(ed.execute(EditorCommand::Copy), None);
+		assert_eq!(ed.execute(EditorCommand::Paste("Q".to_string())), Some(Change::Edit { broken: vec![] }));
+		assert_eq!(format!("{}", ed.lines()[0].step), "Q");
+		let edits: Vec<bool> = changes.borrow().iter().rev().take(5).map(Change::is_edit).collect();
+		assert_eq!(edits, vec![true, true, false, false, true]);
+		
+		// Clicking, and dragging to select
+		assert_eq!(nearest_edge(&[0.0, 10.0, 18.0, 30.0], 15.0), 2);
+		assert_eq!(nearest_edge(&[0.0, 10.0, 18.0, 30.0], -4.0), 0);
+		assert_eq!(nearest_edge(&[0.0, 10.0, 18.0, 30.0], 100.0), 3);
+		ed.load("{1}  1. P ; Premise\n{1}  2. PvQ ; vI 1\n").unwrap();
+		assert_eq!(ed.execute(EditorCommand::MoveTo(5, Col::Step, 1, false)), Some(Change::Cursor));
+		assert_eq!(ed.cursor().position(), (1, Col::Step, 1));
+		ed.execute(EditorCommand::MoveTo(1, Col::Step, 3, true));
+		assert_eq!(ed.selection(), Some(Selection::Tokens { no: 1, col: Col::Step, start: 1, end: 3 }));
+		ed.execute(EditorCommand::MoveTo(1, Col::Method, 99, true));
+		assert_eq!(ed.selection(), Some(Selection::Lines(1, 2)));
+		assert_eq!(ed.cursor().position(), (1, Col::Method, 4));
+	}
+}
@@ -0,0 +1,204 @@
+//! The JSON schema for proof lines and whole proofs, extending the one in `logic::json`.
+//!
+//! A line is written as
+//!
+//! ```text
+//! {"number": 3, "step": TOKENS, "formula": F | null, "method": TOKENS, "deps": [1, 2], "manual_deps": false,
+//!  "depth": 0}
+//! ```
+//!
+//! Line numbers, in `number` and `deps`, start at 1 as they are displayed. `formula` is the parsed step, or null
+//! if it isn't a valid formula; it is only written, for the convenience of other programs. A whole proof is the
+//! contents of a `.plc` file (see `file`), written in a document of kind `proof`:
+//!
+//! ```text
+//! {"version": 1, "proof": {"title": "...", "sequent": SEQUENT, "notation": "unicode", "system": "Lemmon",
+//!  "header_comments": ["..."], "lines": [LINE, ...], "comments": [{"before": 3, "text": "..."}]}}
+//! ```
+//!
+//! where `title`, `sequent`, `system` and `header_comments` can be left out, and `before` is the number of lines
+//! before a comment.
+use logic::{Formula, Sequent, Tokens};
+use logic::json::{self, Json, JsonError, ToJson, FromJson};
+
+use edit::{Line, Lines};
+use file::{Header, Notation, ProofFile};
+
+fn object(fields: Vec<(&str, Json)>) -> Json {
+	Json::Object(fields.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+}
+
+fn number(n: usize) -> Json {
+	Json::Number(n as f64)
+}
+
+/// Reads a line number, which starts at 1, and returns it starting at 0.
+fn line_number(j: &Json) -> Result<usize, JsonError> {
+	match try!(j.as_u64()) {
+		0 => Err(JsonError::Expected("a line number, starting at 1")),
+		n => Ok(n as usize - 1),
+	}
+}
+
+impl ToJson for Line {
+	fn to_json(&self) -> Json {
+		object(vec![
+			("number", number(self.no + 1)),
+			("step", self.step.to_json()),
+			("formula", Formula::parse(&self.step).map(|f| f.to_json()).unwrap_or(Json::Null)),
+			("method", self.method.to_json()),
+			("deps", Json::Array(self.deps.iter().map(|&d| number(d + 1)).collect())),
+			("manual_deps", Json::Bool(self.manual_deps)),
+			("depth", number(self.depth)),
+		])
+	}
+}
+impl FromJson for Line {
+	fn from_json(j: &Json) -> Result<Line, JsonError> {
+		let mut deps = Vec::new();
+		for d in try!(try!(j.field("deps")).as_array()) {
+			deps.push(try!(line_number(d)));
+		}
+		let mut l = Line::full(try!(line_number(try!(j.field("number")))), try!(Tokens::from_json(try!(j.field("step")))),
+			try!(Tokens::from_json(try!(j.field("method")))), deps);
+		if let Some(m) = j.get("manual_deps") {
+			l.manual_deps = try!(m.as_bool());
+		}
+		if let Some(d) = j.get("depth") {
+			l.depth = try!(d.as_u64()) as usize;
+		}
+		Ok(l)
+	}
+}
+
+impl ToJson for Lines {
+	fn to_json(&self) -> Json {
+		Json::Array(self.iter().map(|l| l.to_json()).collect())
+	}
+}
+/// Lines must be numbered in order, starting at 1.
+impl FromJson for Lines {
+	fn from_json(j: &Json) -> Result<Lines, JsonError> {
+		let lines: Vec<Line> = try!(j.to_vec());
+		if lines.iter().enumerate().any(|(no, l)| l.no != no) {
+			return Err(JsonError::Expected("lines numbered in order, starting at 1"));
+		}
+		Ok(Lines::from_vec(lines))
+	}
+}
+
+impl ToJson for ProofFile {
+	fn to_json(&self) -> Json {
+		let h = &self.header;
+		let mut fields = Vec::new();
+		if let Some(ref title) = h.title {
+			fields.push(("title", Json::String(title.clone())));
+		}
+		if let Some(ref seq) = h.sequent {
+			fields.push(("sequent", seq.to_json()));
+		}
+		fields.push(("notation", Json::String(match h.notation {
+			Notation::Ascii   => "ascii",
+			Notation::Unicode => "unicode",
+		}.to_string())));
+		if let Some(ref system) = h.system {
+			fields.push(("system", Json::String(system.clone())));
+		}
+		if !h.comments.is_empty() {
+			fields.push(("header_comments", Json::Array(h.comments.iter().map(|c| Json::String(c.clone())).collect())));
+		}
+		fields.push(("lines", self.lines.to_json()));
+		fields.push(("comments", Json::Array(self.comments.iter().map(|&(before, ref text)| object(vec![
+			("before", number(before)),
+			("text", Json::String(text.clone())),
+		])).collect())));
+		object(fields)
+	}
+}
+impl FromJson for ProofFile {
+	fn from_json(j: &Json) -> Result<ProofFile, JsonError> {
+		let mut header = Header::new();
+		if let Some(title) = j.get("title") {
+			header.title = Some(try!(title.as_str()).to_string());
+		}
+		if let Some(seq) = j.get("sequent") {
+			header.sequent = Some(try!(Sequent::from_json(seq)));
+		}
+		if let Some(notation) = j.get("notation") {
+			header.notation = match try!(notation.as_str()) {
+				"ascii"   => Notation::Ascii,
+				"unicode" => Notation::Unicode,
+				_ => return Err(JsonError::Expected("`ascii` or `unicode`")),
+			};
+		}
+		if let Some(system) = j.get("system") {
+			header.system = Some(try!(system.as_str()).to_string());
+		}
+		if let Some(cs) = j.get("header_comments") {
+			for c in try!(cs.as_array()) {
+				header.comments.push(try!(c.as_str()).to_string());
+			}
+		}
+		let mut comments = Vec::new();
+		if let Some(cs) = j.get("comments") {
+			for c in try!(cs.as_array()) {
+				comments.push((try!(try!(c.field("before")).as_u64()) as usize, try!(try!(c.field("text")).as_str()).to_string()));
+			}
+		}
+		Ok(ProofFile {
+			header: header,
+			lines: try!(Lines::from_json(try!(j.field("lines")))),
+			comments: comments,
+			header_lines: Vec::new(),
+		})
+	}
+}
+
+/// Writes a proof as a versioned JSON document. Pretty prints it if `pretty` is set.
+pub fn write_proof(file: &ProofFile, pretty: bool) -> String {
+	let doc = json::to_document("proof", file);
+	if pretty {
+		format!("{:#}", doc)
+	} else {
+		format!("{}", doc)
+	}
+}
+
+/// Reads a proof from a versioned JSON document made by `write_proof`.
+pub fn read_proof(text: &str) -> Result<ProofFile, JsonError> {
+	json::from_document("proof", &try!(Json::parse(text)))
+}
+
+#[cfg(test)]
+mod tests {
+	use logic::json::{Json, JsonError};
+	
+	use file::ProofFile;
+	use super::*;
+	
+	#[test]
+	fn test_json_proof() {
+		let text = "# From the textbook\n\
+			title: Modus tollens\n\
+			sequent: P→Q, ¬Q ⊢ ¬P\n\
+			\n\
+			{1}        1. P→Q                  ; Premise\n\
+			{2}        2. ¬Q                   ; Premise\n\
+			{1, 2}     3. ¬P                   ; MTT 1, 2\n";
+		let file = ProofFile::parse(text).unwrap();
+		let s = write_proof(&file, true);
+		println!("[test_json_proof]\n{}", s);
+		let read = read_proof(&s).unwrap();
+		assert_eq!(format!("{}", read), text);
+		assert_eq!(read_proof(&write_proof(&file, false)).unwrap().header, file.header);
+		
+		let doc = Json::parse(&s).unwrap();
+		let line = &doc.get("proof").unwrap().get("lines").unwrap().as_array().unwrap()[2];
+		assert_eq!(format!("{}", line.get("formula").unwrap()), r#"{"op":"not","arg":{"op":"var","name":"P"}}"#);
+		assert_eq!(format!("{}", line.get("deps").unwrap()), "[1,2]");
+		
+		assert_eq!(read_proof(r#"{"version": 1, "proof": {"lines": [{"number": 2, "step": [], "method": [], "deps": []}]}}"#).err(),
+			Some(JsonError::Expected("lines numbered in order, starting at 1")));
+		assert_eq!(read_proof(r#"{"version": 1, "proof": {}}"#).err(), Some(JsonError::Missing("lines")));
+	}
+}
@@ -0,0 +1,276 @@
+use std::fmt::{self, Display, Formatter};
+
+use logic::{Token, Tokens};
+
+use library::Library;
+use rule::Rule;
+
+/// Why the method column couldn't be read as a justification.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum JustError {
+	/// There is no rule name.
+	Empty,
+	/// The rule name isn't a built-in rule or a derived rule in the library.
+	UnknownRule(String),
+	/// Something that isn't a line number or range was found where one should be.
+	BadRef(String),
+	/// The rule needs a sequent name in brackets and doesn't have one, or has one and doesn't take one.
+	Name(Rule),
+}
+impl Display for JustError {
+	fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+		match self {
+			&JustError::Empty             => write!(f, "no rule given"),
+			&JustError::UnknownRule(ref s) => write!(f, "unknown rule `{}`", s),
+			&JustError::BadRef(ref s)      => write!(f, "expected a line number, found `{}`", s),
+			&JustError::Name(rule) => if rule.is_introduction() {
+				write!(f, "{:#} needs the name of a sequent in brackets, e.g. `{:#} (DM)`", rule, rule)
+			} else {
+				write!(f, "{:#} doesn't take a sequent name", rule)
+			},
+		}
+	}
+}
+
+/// A reference to earlier lines of a proof. Line numbers start at 0.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Ref {
+	/// A single line, e.g. `3`.
+	Line(usize),
+	/// A subproof from an assumption to a conclusion, e.g. `4-6`.
+	Range(usize, usize),
+}
+impl Display for Ref {
+	fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+		match *self {
+			Ref::Line(a)     => write!(f, "{}", a + 1),
+			Ref::Range(a, b) => write!(f, "{}-{}", a + 1, b + 1),
+		}
+	}
+}
+
+/// The justification of a line of a proof: the rule used, and the lines it was applied to.
+/// e.g. `→E 1, 3`, `∨E 3, 4-6, 7-9`, `SI (DM) 3` or `DS 2, 3` for a derived rule.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Justification {
+	pub rule: Rule,
+	/// The name of the library sequent cited by `SI` and `TI`, or of the derived rule.
+	pub name: Option<String>,
+	pub refs: Vec<Ref>,
+}
+impl Justification {
+	pub fn new(rule: Rule, refs: Vec<Ref>) -> Justification {
+		Justification {
+			rule: rule,
+			name: None,
+			refs: refs,
+		}
+	}
+	/// Constructs a justification that cites the library sequent `name`.
+	pub fn sequent(rule: Rule, name: &str, lines: &[usize]) -> Justification {
+		let mut j = Justification::with_lines(rule, lines);
+		j.name = Some(name.to_string());
+		j
+	}
+	/// Constructs a justification that cites single lines only.
+	pub fn with_lines(rule: Rule, lines: &[usize]) -> Justification {
+		Justification::new(rule, lines.iter().map(|&l| Ref::Line(l)).collect())
+	}
+	
+	/// Parses a justification from the tokens of the method column. Only built-in rules are recognised.
+	pub fn parse(toks: &[Token]) -> Result<Justification, JustError> {
+		Justification::parse_with(toks, &Library::new())
+	}
+	/// Parses a justification like `parse`, also recognising the derived rules in `lib`.
+	pub fn parse_with(toks: &[Token], lib: &Library) -> Result<Justification, JustError> {
+		let split = toks.iter().position(|t| match t {
+			&Token::Char(c) => c.is_digit(10) || c == '(',
+			_ => false,
+		}).unwrap_or(toks.len());
+		let name = format!("{:#}", Tokens::from_vec(toks[..split].to_vec()));
+		let name = name.trim();
+		if name.is_empty() {
+			return Err(JustError::Empty);
+		}
+		// Derived rule names are in ascii, like sequent names
+		let ascii = format!("{}", Tokens::from_vec(toks[..split].to_vec()));
+		let ascii = ascii.trim();
+		let mut derived = None;
+		let rule = match Rule::from_name(name) {
+			Some(rule) => rule,
+			None if lib.has_rule(ascii) => {
+				derived = Some(ascii.to_string());
+				Rule::Derived
+			},
+			None => return Err(JustError::UnknownRule(name.to_string())),
+		};
+		
+		let mut rest = &toks[split..];
+		let mut name = None;
+		if rest.first() == Some(&Token::Char('(')) {
+			match rest.iter().position(|t| *t == Token::Char(')')) {
+				Some(end) => {
+					// Sequent names are in ascii so that e.g. the `v` in `Inv` isn't shown as `∨`
+					name = Some(format!("{}", Tokens::from_vec(rest[1..end].to_vec())).trim().to_string());
+					rest = &rest[end + 1..];
+				},
+				None => return Err(JustError::Name(rule)),
+			}
+		}
+		if rule.is_introduction() != name.is_some() {
+			return Err(JustError::Name(rule));
+		}
+		if derived.is_some() {
+			name = derived;
+		}
+		
+		let s = format!("{}", Tokens::from_vec(rest.to_vec()));
+		let mut refs = Vec::new();
+		for part in s.split(',') {
+			let part = part.trim();
+			if part.is_empty() {
+				continue;
+			}
+			let mut nos = Vec::with_capacity(2);
+			for n in part.split('-') {
+				match n.trim().parse::<usize>() {
+					Ok(n) if n > 0 => nos.push(n - 1),
+					_ => return Err(JustError::BadRef(part.to_string())),
+				}
+			}
+			match nos.len() {
+				1 => refs.push(Ref::Line(nos[0])),
+				2 => refs.push(Ref::Range(nos[0], nos[1])),
+				_ => return Err(JustError::BadRef(part.to_string())),
+			}
+		}
+		Ok(Justification {
+			rule: rule,
+			name: name,
+			refs: refs,
+		})
+	}
+	
+	/// Every line cited, in order. A range counts as the two lines at either end of it.
+	pub fn lines(&self) -> Vec<usize> {
+		let mut lines = Vec::with_capacity(self.refs.len() + 2);
+		for r in self.refs.iter() {
+			match *r {
+				Ref::Line(a)     => lines.push(a),
+				Ref::Range(a, b) => { lines.push(a); lines.push(b); },
+			}
+		}
+		lines
+	}
+	
+	/// Renumbers the lines cited, using `f` to find the new number of each.
+	pub fn map_lines<F: Fn(usize) -> usize>(&self, f: F) -> Justification {
+		let mut j = self.clone();
+		for r in j.refs.iter_mut() {
+			*r = match *r {
+				Ref::Line(a)     => Ref::Line(f(a)),
+				Ref::Range(a, b) => Ref::Range(f(a), f(b)),
+			};
+		}
+		j
+	}
+	
+	/// Computes the dependencies of line `no` when it is justified by `self`, given the dependencies of each
+	/// cited line: the union of those, less any assumptions the rule discharges. Returns `None` if the lines
+	/// cited can't be used by the rule.
+	pub fn deps<'a, F: Fn(usize) -> &'a [usize]>(&self, no: usize, deps_of: F) -> Option<Vec<usize>> {
+		let cites = self.lines();
+		if cites.iter().any(|&c| c >= no) {
+			return None;
+		}
+		let discharged: &[usize] = match (self.rule, cites.len()) {
+			(r, 0) if r.is_assumption() => return Some(vec![no]),
+			(r, _) if r.is_assumption() => return None,
+			(Rule::ImpliesI, 2) | (Rule::NotI, 2) | (Rule::Raa, 2) | (Rule::Ip, 2) => &cites[..1],
+			(Rule::ImpliesI, _) | (Rule::NotI, _) | (Rule::Raa, _) | (Rule::Ip, _) => return None,
+			(Rule::OrE, 5) | (Rule::IffI, 4) => {
+				// The assumptions are only discharged from the branch they start
+				let (mut deps, branches) = if cites.len() == 5 {
+					(deps_of(cites[0]).to_vec(), &cites[1..])
+				} else {
+					(Vec::new(), &cites[..])
+				};
+				for &(a, c) in [(branches[0], branches[1]), (branches[2], branches[3])].iter() {
+					deps.extend(deps_of(c).iter().filter(|&&d| d != a));
+				}
+				deps.sort();
+				deps.dedup();
+				return Some(deps);
+			},
+			(Rule::OrE, _) => return None,
+			_ => &[],
+		};
+		let mut deps = Vec::new();
+		for &c in cites.iter() {
+			deps.extend(deps_of(c).iter().filter(|d| !discharged.contains(d)));
+		}
+		deps.sort();
+		deps.dedup();
+		Some(deps)
+	}
+	
+	/// Converts the justification back into tokens for the method column.
+	pub fn to_tokens(&self) -> Tokens {
+		Tokens::from_str(&format!("{:#}", self))
+	}
+}
+impl Display for Justification {
+	fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+		match self.name {
+			Some(ref name) if self.rule == Rule::Derived => try!(write!(f, "{}", name)),
+			ref name => {
+				if !f.alternate() {
+					try!(write!(f, "{}", self.rule));
+				} else {
+					try!(write!(f, "{:#}", self.rule));
+				}
+				if let &Some(ref name) = name {
+					try!(write!(f, " ({})", name));
+				}
+			},
+		}
+		for (i, r) in self.refs.iter().enumerate() {
+			try!(write!(f, "{}{}", if i == 0 { " " } else { ", " }, r));
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use logic::Tokens;
+	
+	use rule::Rule;
+	use super::*;
+	
+	#[test]
+	fn test_justification_parse() {
+		fn test(s: &str, expected: &str) {
+			let j = Justification::parse(&Tokens::from_str(s)).unwrap();
+			let j_sp = format!("{:#}", j);
+			println!("[test_justification_parse] {} -> {} == {}", s, j_sp, expected);
+			assert_eq!(j_sp, expected);
+			assert_eq!(Justification::parse(&j.to_tokens()).unwrap(), j);
+		}
+		
+		test("A", "A");
+		test("->E 1, 3", "→E 1, 3");
+		test("MPP 1,3", "→E 1, 3");
+		test("vE 3, 4-6, 7 - 9", "∨E 3, 4-6, 7-9");
+		let j = Justification::parse(&Tokens::from_str("vE 3, 4-6, 7-9")).unwrap();
+		assert_eq!(j.rule, Rule::OrE);
+		assert_eq!(j.lines(), vec![2, 3, 5, 6, 8]);
+		assert_eq!(Justification::parse(&Tokens::from_str("")), Err(JustError::Empty));
+		assert_eq!(Justification::parse(&Tokens::from_str("XYZ 1")), Err(JustError::UnknownRule("XYZ".to_string())));
+		assert_eq!(Justification::parse(&Tokens::from_str("&I 1, 2-")), Err(JustError::BadRef("2-".to_string())));
+		test("SI (Inv) 3", "SI (Inv) 3");
+		test("TI(LEM)", "TI (LEM)");
+		assert_eq!(Justification::parse(&Tokens::from_str("SI 3")), Err(JustError::Name(Rule::Si)));
+		assert_eq!(Justification::parse(&Tokens::from_str("MTT (DM) 3")), Err(JustError::Name(Rule::Mtt)));
+	}
+}
@@ -0,0 +1,240 @@
+use std::fmt::{self, Display, Formatter};
+
+use logic::{Formula, ParseError, Sequent};
+
+use rule::Rule;
+
+/// The sequents every library starts with, under their usual names. Several sequents may share a name.
+const STANDARD: &'static [(&'static str, &'static str)] = &[
+	("MTT",   "P->Q, ~Q |- ~P"),
+	("HS",    "P->Q, Q->R |- P->R"),
+	("DS",    "PvQ, ~P |- Q"),
+	("DS",    "PvQ, ~Q |- P"),
+	("DM",    "~(PvQ) |- ~P&~Q"),
+	("DM",    "~P&~Q |- ~(PvQ)"),
+	("DM",    "~(P&Q) |- ~Pv~Q"),
+	("DM",    "~Pv~Q |- ~(P&Q)"),
+	("Imp",   "P->Q |- ~PvQ"),
+	("Imp",   "~PvQ |- P->Q"),
+	("NegImp", "~(P->Q) |- P&~Q"),
+	("NegImp", "P&~Q |- ~(P->Q)"),
+	("Com",   "P&Q |- Q&P"),
+	("Com",   "PvQ |- QvP"),
+	("EFQ",   "P, ~P |- Q"),
+	("LEM",   "|- Pv~P"),
+	("LNC",   "|- ~(P&~P)"),
+];
+
+/// Why a sequent couldn't be added to a library.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LibError {
+	/// A line isn't of the form `NAME: sequent`.
+	Syntax,
+	/// The sequent couldn't be parsed. Contains the index of the formula that failed and the error.
+	Parse(usize, ParseError),
+	/// The sequent is invalid, so can't have been proved. Contains a counterexample.
+	Invalid(Vec<(char, bool)>),
+	/// A derived rule has the name of a built-in rule, or a name that can't be written in the method column.
+	BadName(String),
+	/// A built-in rule to forbid doesn't exist.
+	UnknownRule(String),
+}
+impl Display for LibError {
+	fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+		match self {
+			&LibError::Syntax => write!(f, "expected `NAME: premises |- conclusion`"),
+			&LibError::Parse(i, ref e) => write!(f, "formula {}: {}", i + 1, e),
+			&LibError::Invalid(ref vals) => {
+				try!(write!(f, "the sequent is invalid, counterexample:"));
+				for &(c, b) in vals.iter() {
+					try!(write!(f, " {}={}", c, if b { 'T' } else { 'F' }));
+				}
+				Ok(())
+			},
+			&LibError::BadName(ref s) => write!(f, "`{}` can't be used as the name of a rule", s),
+			&LibError::UnknownRule(ref s) => write!(f, "unknown rule `{}`", s),
+		}
+	}
+}
+
+/// A library of named, proved sequents that can be cited with `SI (NAME)`, or `TI (NAME)` for theorems.
+///
+/// The variables of a sequent in the library stand for any formula, so `SI (DM) 3` can derive `¬R∨¬(S→T)` from
+/// `¬(R∧(S→T))`.
+///
+/// A library also holds the derived rules an assignment adds, which are cited by name like built-in rules, e.g.
+/// `DS 2, 3`, and the built-in rules it forbids.
+#[derive(Clone, Debug)]
+pub struct Library {
+	entries: Vec<(String, Sequent)>,
+	rules: Vec<(String, Sequent)>,
+	forbidden: Vec<Rule>,
+}
+impl Library {
+	/// Constructs an empty library.
+	pub fn new() -> Library {
+		Library {
+			entries: Vec::new(),
+			rules: Vec::new(),
+			forbidden: Vec::new(),
+		}
+	}
+	/// Constructs a library with the standard sequents: De Morgan's laws, disjunctive syllogism, etc.
+	pub fn standard() -> Library {
+		let mut lib = Library::new();
+		for &(name, s) in STANDARD.iter() {
+			lib.add(name, Sequent::parse(s).unwrap()).unwrap();
+		}
+		lib
+	}
+	
+	/// Adds `seq` to the library under `name`. Only valid sequents can be added, so the library can't be used
+	/// to prove anything that doesn't follow.
+	pub fn add(&mut self, name: &str, seq: Sequent) -> Result<(), LibError> {
+		if let Some(vals) = seq.counterexample() {
+			return Err(LibError::Invalid(vals));
+		}
+		self.entries.push((name.to_string(), seq));
+		Ok(())
+	}
+	
+	/// Adds each sequent in `text`, written one per line as `NAME: premises |- conclusion`. Blank lines and
+	/// lines starting with `#` are skipped. On error, returns the line number (starting at 0) and the error;
+	/// the sequents before it have been added.
+	pub fn load(&mut self, text: &str) -> Result<(), (usize, LibError)> {
+		for (i, line) in text.lines().enumerate() {
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+			let (name, seq) = match line.find(':') {
+				Some(j) => (line[..j].trim(), &line[j + 1..]),
+				None => return Err((i, LibError::Syntax)),
+			};
+			if name.is_empty() {
+				return Err((i, LibError::Syntax));
+			}
+			let seq = try!(Sequent::parse(seq).map_err(|(n, e)| (i, LibError::Parse(n, e))));
+			try!(self.add(name, seq).map_err(|e| (i, e)));
+		}
+		Ok(())
+	}
+	
+	/// Adds a derived rule that takes the premises of `seq` to its conclusion, cited as `name`. Like sequents,
+	/// only valid rules can be added, so an unsound rule can't be used.
+	pub fn add_rule(&mut self, name: &str, seq: Sequent) -> Result<(), LibError> {
+		if name.is_empty() || !name.chars().all(|c| c.is_alphabetic()) || Rule::from_name(name).is_some() {
+			return Err(LibError::BadName(name.to_string()));
+		}
+		if let Some(vals) = seq.counterexample() {
+			return Err(LibError::Invalid(vals));
+		}
+		self.rules.push((name.to_string(), seq));
+		Ok(())
+	}
+	
+	/// Stops the built-in rule `rule` being used.
+	pub fn forbid(&mut self, rule: Rule) {
+		if !self.forbidden.contains(&rule) {
+			self.forbidden.push(rule);
+		}
+	}
+	
+	/// Reads a rule file, which changes the rules for an assignment. Each line is one of:
+	///
+	/// - `rule NAME: premises |- conclusion`, to add a derived rule, e.g. `rule DS: φ∨ψ, ¬φ ⊢ ψ`
+	/// - `forbid RULE, ...`, to stop built-in rules being used, e.g. `forbid MTT, DN`
+	/// - `sequent NAME: premises |- conclusion`, to add a sequent for `SI` and `TI`
+	///
+	/// Blank lines and lines starting with `#` are skipped. On error, returns the line number (starting at 0) and
+	/// the error; the lines before it have taken effect.
+	pub fn load_rules(&mut self, text: &str) -> Result<(), (usize, LibError)> {
+		for (i, line) in text.lines().enumerate() {
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+			let (kw, rest) = match line.find(char::is_whitespace) {
+				Some(j) => (&line[..j], line[j..].trim()),
+				None => return Err((i, LibError::Syntax)),
+			};
+			if kw == "forbid" {
+				for name in rest.split(',').map(|s| s.trim()) {
+					match Rule::from_name(name) {
+						Some(rule) => self.forbid(rule),
+						None => return Err((i, LibError::UnknownRule(name.to_string()))),
+					}
+				}
+				continue;
+			}
+			let (name, seq) = match rest.find(':') {
+				Some(j) => (rest[..j].trim(), &rest[j + 1..]),
+				None => return Err((i, LibError::Syntax)),
+			};
+			let seq = try!(Sequent::parse(seq).map_err(|(n, e)| (i, LibError::Parse(n, e))));
+			try!(match kw {
+				"rule"    => self.add_rule(name, seq),
+				"sequent" if !name.is_empty() => self.add(name, seq),
+				_ => Err(LibError::Syntax),
+			}.map_err(|e| (i, e)));
+		}
+		Ok(())
+	}
+	
+	/// Gets every sequent called `name`.
+	pub fn get(&self, name: &str) -> Vec<&Sequent> {
+		self.entries.iter().filter(|e| e.0 == name).map(|e| &e.1).collect()
+	}
+	
+	/// True if there is a sequent called `name`.
+	pub fn contains(&self, name: &str) -> bool {
+		self.entries.iter().any(|e| e.0 == name)
+	}
+	
+	/// True if there is a derived rule called `name`.
+	pub fn has_rule(&self, name: &str) -> bool {
+		self.rules.iter().any(|e| e.0 == name)
+	}
+	
+	/// True if the built-in rule `rule` has been forbidden.
+	pub fn forbids(&self, rule: Rule) -> bool {
+		self.forbidden.contains(&rule)
+	}
+	
+	/// Returns true if the sequent `name` has a substitution instance with the premises `prems`, in any order,
+	/// and the conclusion `concl`.
+	pub fn instance(&self, name: &str, prems: &[&Formula], concl: &Formula) -> bool {
+		instance(&self.entries, name, prems, concl)
+	}
+	
+	/// Returns true if the derived rule `name` takes the premises `prems`, in any order, to `concl`.
+	pub fn rule_instance(&self, name: &str, prems: &[&Formula], concl: &Formula) -> bool {
+		instance(&self.rules, name, prems, concl)
+	}
+}
+
+/// True if a sequent in `entries` called `name` has a substitution instance with the premises `prems`, in any
+/// order, and the conclusion `concl`.
+fn instance(entries: &[(String, Sequent)], name: &str, prems: &[&Formula], concl: &Formula) -> bool {
+	entries.iter().filter(|e| e.0 == name).any(|&(_, ref seq)| seq.premises.len() == prems.len() && {
+		let mut subst = Vec::new();
+		seq.conclusion.matches(concl, &mut subst) && match_premises(&seq.premises, prems, &subst)
+	})
+}
+
+/// Matches each pattern in `pats` against a different formula in `fs`, trying every order.
+fn match_premises(pats: &[Formula], fs: &[&Formula], subst: &Vec<(char, Formula)>) -> bool {
+	if pats.is_empty() {
+		return true;
+	}
+	for i in 0..fs.len() {
+		let mut s = subst.clone();
+		if pats[0].matches(fs[i], &mut s) {
+			let rest: Vec<&Formula> = fs.iter().enumerate().filter(|&(j, _)| j != i).map(|(_, f)| *f).collect();
+			if match_premises(&pats[1..], &rest, &s) {
+				return true;
+			}
+		}
+	}
+	false
+}
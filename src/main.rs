@@ -1,19 +1,49 @@
 #![feature(const_fn)]
+#[cfg(feature = "gui")]
 extern crate gtk;
+#[cfg(feature = "gui")]
 extern crate gdk;
+#[cfg(feature = "gui")]
 extern crate cairo;
 
 extern crate logic;
+#[cfg(feature = "repl")]
+extern crate rustyline;
+#[cfg(feature = "tui")]
+extern crate termion;
 
+use std::{env, process};
+#[cfg(feature = "gui")]
 use std::{mem, ptr};
+#[cfg(feature = "gui")]
 use gtk::{Window, WindowType};
+#[cfg(feature = "gui")]
 use gui::Gui;
 
+#[cfg(feature = "gui")]
 pub mod gui;
+pub mod cli;
+pub mod grade;
+pub mod repl;
+#[cfg(feature = "tui")]
+pub mod tui;
 pub mod edit;
+pub mod input;
+pub mod rule;
+pub mod just;
+pub mod library;
+pub mod prove;
+pub mod check;
+pub mod fitch;
+pub mod system;
+pub mod file;
+pub mod json;
+pub mod tree;
 
+#[cfg(feature = "gui")]
 static mut g_gui: *mut Gui = ptr::null_mut();
 
+#[cfg(feature = "gui")]
 pub fn get_gui() -> &'static mut Gui {
 	unsafe { if g_gui.is_null() {
 		panic!("gui not initialized.");
@@ -23,6 +53,15 @@ pub fn get_gui() -> &'static mut Gui {
 }
 
 fn main() {
+	let args: Vec<String> = env::args().skip(1).collect();
+	if let Some(code) = cli::run(&args) {
+		process::exit(code);
+	}
+	run_gui();
+}
+
+#[cfg(feature = "gui")]
+fn run_gui() {
 	match gtk::init() {
 		Err(()) => panic!("GTK cannot be initialized."),
 		_ => {}
@@ -36,3 +75,10 @@ fn main() {
 		gtk::main();
 	}
 }
+
+/// Without the editor, anything that isn't a command is a mistake.
+#[cfg(not(feature = "gui"))]
+fn run_gui() {
+	eprintln!("{}", cli::USAGE);
+	process::exit(cli::ERROR);
+}
@@ -0,0 +1,457 @@
+use std::fmt::{self, Display, Formatter};
+
+use logic::{Formula, Sequent};
+use logic::form::{Var, Not, And, Or, Implies, Iff, Bottom};
+
+use edit::{Line, Lines};
+use just::Justification;
+use rule::Rule;
+use system::ConvertError;
+
+/// The deepest the search will go before giving up.
+const MAX_DEPTH: usize = 24;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProveError {
+	/// The sequent is invalid, so has no proof. Contains a valuation that makes every premise true and the
+	/// conclusion false.
+	Invalid(Vec<(char, bool)>),
+	/// The search gave up before it found a proof.
+	Exhausted,
+	/// A proof was found, but couldn't be written in the proof system asked for.
+	Convert(ConvertError),
+}
+impl Display for ProveError {
+	fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+		match self {
+			&ProveError::Invalid(ref vals) => {
+				try!(f.write_str("the sequent is invalid, counterexample:"));
+				for &(c, b) in vals.iter() {
+					try!(write!(f, " {}={}", c, if b { 'T' } else { 'F' }));
+				}
+				Ok(())
+			},
+			&ProveError::Exhausted => f.write_str("no proof found within the search limit"),
+			&ProveError::Convert(ref e) => write!(f, "the proof found can't be converted: {}", e),
+		}
+	}
+}
+
+/// A step of a proof that is being searched for. Citations and dependencies are indices into `Search.steps`.
+struct Step {
+	form: Formula,
+	rule: Rule,
+	cites: Vec<usize>,
+	/// Indices of the assumptions that this step depends on, sorted.
+	deps: Vec<usize>,
+}
+
+/// A point that the search can be rolled back to.
+#[derive(Copy, Clone)]
+struct Mark {
+	steps: usize,
+	scope: usize,
+	split: usize,
+}
+
+/// Goal-directed proof search. Introduction rules are tried on the shape of the goal, elimination rules are
+/// applied eagerly to everything in scope, and `∨E` and `RAA` are used when neither is enough.
+///
+/// Steps are never removed from `steps` when a subproof is closed, only from `scope`, so that the linear
+/// order of the finished proof is the order the steps were found in.
+struct Search {
+	steps: Vec<Step>,
+	/// The steps that can be cited at the current point of the search.
+	scope: Vec<usize>,
+	/// Disjunctions that are being eliminated in the current branch.
+	split: Vec<usize>,
+}
+impl Search {
+	fn new() -> Search {
+		Search {
+			steps: Vec::new(),
+			scope: Vec::new(),
+			split: Vec::new(),
+		}
+	}
+	
+	fn mark(&self) -> Mark {
+		Mark {
+			steps: self.steps.len(),
+			scope: self.scope.len(),
+			split: self.split.len(),
+		}
+	}
+	/// Undoes everything that happened since `m`.
+	fn rollback(&mut self, m: Mark) {
+		self.steps.truncate(m.steps);
+		self.scope.truncate(m.scope);
+		self.split.truncate(m.split);
+	}
+	/// Closes the subproofs opened since `m`, keeping their steps.
+	fn close(&mut self, m: Mark) {
+		self.scope.truncate(m.scope);
+		self.split.truncate(m.split);
+	}
+	
+	/// Finds a step in scope that proves `f`.
+	fn find(&self, f: &Formula) -> Option<usize> {
+		self.scope.iter().map(|&i| i).find(|&i| self.steps[i].form == *f)
+	}
+	
+	/// The union of the dependencies of `cites`.
+	fn union(&self, cites: &[usize]) -> Vec<usize> {
+		let mut deps = Vec::new();
+		for &c in cites.iter() {
+			for &d in self.steps[c].deps.iter() {
+				if let Err(i) = deps.binary_search(&d) {
+					deps.insert(i, d);
+				}
+			}
+		}
+		deps
+	}
+	
+	fn push(&mut self, form: Formula, rule: Rule, cites: Vec<usize>, deps: Vec<usize>) -> usize {
+		let i = self.steps.len();
+		self.steps.push(Step {
+			form: form,
+			rule: rule,
+			cites: cites,
+			deps: deps,
+		});
+		self.scope.push(i);
+		i
+	}
+	
+	/// Adds an assumption to the current scope.
+	fn assume(&mut self, form: Formula, rule: Rule) -> usize {
+		let i = self.steps.len();
+		self.push(form, rule, Vec::new(), vec![i]);
+		self.saturate(i);
+		i
+	}
+	
+	/// Adds a step that depends on everything `cites` depends on, except for the assumptions in `discharged`.
+	fn derive(&mut self, form: Formula, rule: Rule, cites: Vec<usize>, discharged: &[usize]) -> usize {
+		let mut deps = self.union(&cites);
+		deps.retain(|d| !discharged.contains(d));
+		let i = self.push(form, rule, cites, deps);
+		self.saturate(i);
+		i
+	}
+	
+	/// Applies the elimination rules to step `i` and anything derived from it, until nothing new can be
+	/// derived. Only subformulas (and the conditionals of biconditionals) are ever added, so this terminates.
+	fn saturate(&mut self, i: usize) {
+		let mut work = vec![i];
+		while let Some(i) = work.pop() {
+			let form = self.steps[i].form.clone();
+			let mut new = Vec::new();
+			match form {
+				And(ref p, ref q) => {
+					new.push(((**p).clone(), Rule::AndE, vec![i]));
+					new.push(((**q).clone(), Rule::AndE, vec![i]));
+				},
+				Implies(ref p, ref q) => if let Some(j) = self.find(p) {
+					new.push(((**q).clone(), Rule::ImpliesE, vec![i, j]));
+				},
+				Iff(ref p, ref q) => {
+					new.push((Implies(p.clone(), q.clone()), Rule::IffE, vec![i]));
+					new.push((Implies(q.clone(), p.clone()), Rule::IffE, vec![i]));
+				},
+				Not(ref p) => {
+					if let Not(ref p) = **p {
+						new.push(((**p).clone(), Rule::Dn, vec![i]));
+					}
+					if let Some(j) = self.find(p) {
+						new.push((Bottom, Rule::NotE, vec![j, i]));
+					}
+				},
+				Var(_) | Or(_, _) | Bottom => {},
+			}
+			// Steps already in scope that this one completes
+			for &j in self.scope.iter() {
+				match self.steps[j].form {
+					Implies(ref p, ref q) if **p == form => new.push(((**q).clone(), Rule::ImpliesE, vec![j, i])),
+					Not(ref p) if **p == form => new.push((Bottom, Rule::NotE, vec![i, j])),
+					_ => {},
+				}
+			}
+			for (f, rule, cites) in new.into_iter() {
+				if self.find(&f).is_none() {
+					let deps = self.union(&cites);
+					work.push(self.push(f, rule, cites, deps));
+				}
+			}
+		}
+	}
+	
+	/// Derives `goal` from the contradiction at step `b`.
+	fn absurd(&mut self, goal: &Formula, b: usize) -> usize {
+		let m = self.mark();
+		let (assumed, rule) = match goal {
+			&Not(ref p) => ((**p).clone(), Rule::NotI),
+			_           => (Not(Box::new(goal.clone())), Rule::Raa),
+		};
+		let a = self.assume(assumed, Rule::Assumption);
+		self.close(m);
+		self.derive(goal.clone(), rule, vec![a, b], &[a])
+	}
+	
+	/// Tries to prove `goal` from the steps in scope, without going deeper than `depth`. On success, returns
+	/// the step that proves it, which is in scope. On failure, the search is left as it was.
+	fn prove(&mut self, goal: &Formula, depth: usize) -> Option<usize> {
+		if let Some(i) = self.find(goal) {
+			return Some(i);
+		}
+		if depth == 0 {
+			return None;
+		}
+		let d = depth - 1;
+		
+		// Anything follows from a contradiction
+		if let Some(b) = self.find(&Bottom) {
+			return Some(self.absurd(goal, b));
+		}
+		
+		// Introduction rules
+		let m = self.mark();
+		match goal {
+			&And(ref p, ref q) => {
+				if let Some(i) = self.prove(p, d) {
+					if let Some(j) = self.prove(q, d) {
+						return Some(self.derive(goal.clone(), Rule::AndI, vec![i, j], &[]));
+					}
+				}
+				self.rollback(m);
+			},
+			&Or(ref p, ref q) => {
+				for side in [p, q].iter() {
+					if let Some(i) = self.prove(side, d) {
+						return Some(self.derive(goal.clone(), Rule::OrI, vec![i], &[]));
+					}
+					self.rollback(m);
+				}
+			},
+			&Implies(ref p, ref q) => {
+				let a = self.assume((**p).clone(), Rule::Assumption);
+				if let Some(i) = self.prove(q, d) {
+					self.close(m);
+					return Some(self.derive(goal.clone(), Rule::ImpliesI, vec![a, i], &[a]));
+				}
+				self.rollback(m);
+			},
+			&Iff(ref p, ref q) => {
+				if let Some(i) = self.prove(&Implies(p.clone(), q.clone()), d) {
+					if let Some(j) = self.prove(&Implies(q.clone(), p.clone()), d) {
+						return Some(self.derive(goal.clone(), Rule::IffI, vec![i, j], &[]));
+					}
+				}
+				self.rollback(m);
+			},
+			&Not(ref p) => {
+				let a = self.assume((**p).clone(), Rule::Assumption);
+				if let Some(i) = self.prove(&Bottom, d) {
+					self.close(m);
+					return Some(self.derive(goal.clone(), Rule::NotI, vec![a, i], &[a]));
+				}
+				self.rollback(m);
+			},
+			&Bottom => {
+				// Prove the opposite of a negation in scope, which makes saturation find `⊥`
+				let negs: Vec<Formula> = self.scope.iter().filter_map(|&j| match self.steps[j].form {
+					Not(ref p) if **p != Bottom => Some((**p).clone()),
+					_ => None,
+				}).collect();
+				for p in negs.iter() {
+					if self.prove(p, d).is_some() {
+						if let Some(b) = self.find(&Bottom) {
+							return Some(b);
+						}
+					}
+					self.rollback(m);
+				}
+			},
+			&Var(_) => {},
+		}
+		
+		// →E backwards, on conditionals whose consequent leads to the goal
+		let conds: Vec<Formula> = self.scope.iter().filter_map(|&j| match self.steps[j].form {
+			Implies(ref p, ref q) if self.find(q).is_none() && leads_to(q, goal) => Some((**p).clone()),
+			_ => None,
+		}).collect();
+		for p in conds.iter() {
+			if self.prove(p, d).is_some() {
+				if let Some(i) = self.prove(goal, d) {
+					return Some(i);
+				}
+			}
+			self.rollback(m);
+		}
+		
+		// ∨E on each disjunction in scope
+		let disjs: Vec<usize> = self.scope.iter().map(|&j| j).filter(|&j| match self.steps[j].form {
+			Or(_, _) => !self.split.contains(&j),
+			_ => false,
+		}).collect();
+		for &j in disjs.iter() {
+			let (p, q) = match self.steps[j].form {
+				Or(ref p, ref q) => ((**p).clone(), (**q).clone()),
+				_ => unreachable!(),
+			};
+			self.split.push(j);
+			let m1 = self.mark();
+			let a1 = self.assume(p, Rule::Assumption);
+			if let Some(i1) = self.prove(goal, d) {
+				self.close(m1);
+				let m2 = self.mark();
+				let a2 = self.assume(q, Rule::Assumption);
+				if let Some(i2) = self.prove(goal, d) {
+					self.close(m2);
+					self.split.truncate(m.split);
+					return Some(self.derive(goal.clone(), Rule::OrE, vec![j, a1, i1, a2, i2], &[a1, a2]));
+				}
+			}
+			self.rollback(m);
+		}
+		
+		// RAA, which is only needed for goals without an invertible introduction rule
+		match goal {
+			&Var(_) | &Or(_, _) => {
+				let neg = Not(Box::new(goal.clone()));
+				if self.find(&neg).is_none() {
+					let a = self.assume(neg, Rule::Assumption);
+					if let Some(i) = self.prove(&Bottom, d) {
+						self.close(m);
+						return Some(self.derive(goal.clone(), Rule::Raa, vec![a, i], &[a]));
+					}
+					self.rollback(m);
+				}
+			},
+			_ => {},
+		}
+		
+		None
+	}
+	
+	/// Converts the search into editor lines, keeping only the premises and the steps that `concl` relies on.
+	fn into_lines(self, concl: usize) -> Lines {
+		let mut used = vec![false; self.steps.len()];
+		let mut work = vec![concl];
+		for (i, s) in self.steps.iter().enumerate() {
+			if s.rule == Rule::Premise {
+				work.push(i);
+			}
+		}
+		while let Some(i) = work.pop() {
+			if !used[i] {
+				used[i] = true;
+				work.extend(self.steps[i].cites.iter().map(|&c| c));
+			}
+		}
+		
+		let mut nos = vec![0; self.steps.len()];
+		let mut no = 0;
+		for i in 0..self.steps.len() {
+			if used[i] {
+				nos[i] = no;
+				no += 1;
+			}
+		}
+		
+		let mut lines = Vec::with_capacity(no);
+		for (i, s) in self.steps.into_iter().enumerate() {
+			if !used[i] {
+				continue;
+			}
+			let mut cites: Vec<usize> = s.cites.iter().map(|&c| nos[c]).collect();
+			if !discharges(s.rule) {
+				cites.sort();
+			}
+			let just = Justification::with_lines(s.rule, &cites);
+			let deps = s.deps.iter().map(|&d| nos[d]).collect();
+			lines.push(Line::full(nos[i], s.form.to_tokens(), just.to_tokens(), deps));
+		}
+		Lines::from_vec(lines)
+	}
+}
+
+/// True if the citations of `rule` must stay in the order assumption, conclusion.
+fn discharges(rule: Rule) -> bool {
+	match rule {
+		Rule::ImpliesI | Rule::NotI | Rule::Raa | Rule::OrE => true,
+		_ => false,
+	}
+}
+
+/// True if eliminating `f` could end up at `goal`.
+fn leads_to(f: &Formula, goal: &Formula) -> bool {
+	if f == goal {
+		return true;
+	}
+	match f {
+		&And(ref p, ref q)    => leads_to(p, goal) || leads_to(q, goal),
+		&Implies(_, ref q)    => leads_to(q, goal),
+		&Not(ref p)           => *goal == Bottom || match **p {
+			Not(ref p) => leads_to(p, goal),
+			_ => false,
+		},
+		_ => false,
+	}
+}
+
+/// Searches for a Lemmon-style natural deduction proof of `seq`. The premises are the first lines of the proof.
+pub fn prove(seq: &Sequent) -> Result<Lines, ProveError> {
+	prove_within(seq, MAX_DEPTH)
+}
+
+/// Searches for a proof of `seq`, going no deeper than `max_depth`.
+fn prove_within(seq: &Sequent, max_depth: usize) -> Result<Lines, ProveError> {
+	if let Some(vals) = seq.counterexample() {
+		return Err(ProveError::Invalid(vals));
+	}
+	for depth in 1..max_depth + 1 {
+		let mut s = Search::new();
+		for (i, p) in seq.premises.iter().enumerate() {
+			s.push(p.clone(), Rule::Premise, Vec::new(), vec![i]);
+		}
+		for i in 0..seq.premises.len() {
+			s.saturate(i);
+		}
+		if let Some(i) = s.prove(&seq.conclusion, depth) {
+			return Ok(s.into_lines(i));
+		}
+	}
+	Err(ProveError::Exhausted)
+}
+
+#[cfg(test)]
+mod tests {
+	use logic::Sequent;
+	
+	use check::check;
+	use super::*;
+	
+	#[test]
+	fn test_prove() {
+		let seq = Sequent::parse("P->Q, Q->R |- P->R").unwrap();
+		let lines = prove(&seq).unwrap();
+		println!("[test_prove] {}\n{:#}", seq, lines);
+		assert_eq!(check(&lines), vec![]);
+		assert_eq!(Formula::parse(&lines[lines.len() - 1].step), Ok(seq.conclusion.clone()));
+		assert_eq!(lines[lines.len() - 1].deps, vec![0, 1]);
+		
+		match prove(&Sequent::parse("P->Q |- Q").unwrap()) {
+			Err(ProveError::Invalid(vals)) => assert_eq!(vals, vec![('P', false), ('Q', false)]),
+			Err(e) => panic!("{}", e),
+			Ok(_) => panic!("proved an invalid sequent"),
+		}
+		// Peirce's law needs more than one step of search
+		match prove_within(&Sequent::parse("|- ((P->Q)->P)->P").unwrap(), 1) {
+			Err(ProveError::Exhausted) => {},
+			Err(e) => panic!("{}", e),
+			Ok(_) => panic!("proved within a step"),
+		}
+	}
+}
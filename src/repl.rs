@@ -0,0 +1,222 @@
+//! An interactive prompt for quick checks of formulas and sequents, started with `plc repl`.
+//!
+//! A formula on its own is classified as a tautology, a contradiction or contingent, and a sequent is checked
+//! for validity. Formulas can be typed in any notation the parser accepts. The commands are listed in `HELP`.
+//! Input lines can be edited, and are kept in the history file `~/.plc_history` between sessions.
+#[cfg(feature = "repl")]
+use std::env;
+use std::fmt::Display;
+#[cfg(feature = "repl")]
+use std::path::PathBuf;
+
+#[cfg(feature = "repl")]
+use rustyline;
+#[cfg(feature = "repl")]
+use rustyline::error::ReadlineError;
+
+use logic::{Formula, Sequent};
+
+use cli;
+#[cfg(feature = "repl")]
+use cli::{SUCCESS, ERROR};
+use prove;
+
+pub const HELP: &'static str = "\
+FORMULA             classify a formula as a tautology, a contradiction or contingent
+PREMISES |- FORMULA check that a sequent is valid
+:tt FORMULA         print the truth table of a formula
+:cnf FORMULA        convert a formula to conjunctive normal form
+:sat FORMULA        find a valuation that makes a formula true
+:equiv A ; B        check that two formulas are equivalent
+:prove SEQUENT      find a natural deduction proof of a sequent
+:ascii, :unicode    choose how results are written
+:help               show this
+:quit               leave";
+
+/// A session of the REPL.
+pub struct Repl {
+	/// True if results are written in unicode, and false if in ascii.
+	pub unicode: bool,
+}
+impl Repl {
+	pub fn new() -> Repl {
+		Repl {
+			unicode: true,
+		}
+	}
+	
+	/// Runs a line of input, returning what to print. Returns `None` if the session should end.
+	pub fn eval(&mut self, line: &str) -> Option<String> {
+		let line = line.trim();
+		if !line.starts_with(':') {
+			return Some(if line.is_empty() {
+				String::new()
+			} else if line.contains("|-") || line.contains('⊢') {
+				self.valid(line)
+			} else {
+				match parse(line) {
+					Ok(f) => format!("{}: {}", self.show(&f), cli::classify(&f)),
+					Err(e) => e,
+				}
+			});
+		}
+		let (cmd, arg) = match line.find(char::is_whitespace) {
+			Some(i) => (&line[..i], line[i..].trim()),
+			None => (line, ""),
+		};
+		Some(match cmd {
+			":q" | ":quit" => return None,
+			":h" | ":help" => HELP.to_string(),
+			":ascii" => {
+				self.unicode = false;
+				"results are written in ascii".to_string()
+			},
+			":unicode" => {
+				self.unicode = true;
+				"results are written in unicode".to_string()
+			},
+			":tt" => self.formula(arg, |r, f| cli::truth_table(f, r.unicode).trim_right().to_string()),
+			":cnf" => self.formula(arg, |r, f| r.show(&f.cnf())),
+			":sat" => self.formula(arg, |_, f| match f.satisfy() {
+				Some(vals) => format!("satisfiable:{}", valuation(&vals)),
+				None => "unsatisfiable".to_string(),
+			}),
+			":equiv" => self.equiv(arg),
+			":prove" => self.prove(arg),
+			_ => format!("unknown command `{}`, :help lists them", cmd),
+		})
+	}
+	
+	/// Runs a command that takes a formula.
+	fn formula<F: Fn(&Repl, &Formula) -> String>(&self, arg: &str, f: F) -> String {
+		match parse(arg) {
+			Ok(form) => f(self, &form),
+			Err(e) => e,
+		}
+	}
+	
+	fn valid(&self, s: &str) -> String {
+		match Sequent::parse(s) {
+			Ok(seq) => match seq.counterexample() {
+				None => format!("{}: valid", self.show(&seq)),
+				Some(vals) => format!("{}: invalid, counterexample:{}", self.show(&seq), valuation(&vals)),
+			},
+			Err((n, e)) => format!("`{}`: formula {}: {}", s, n + 1, e),
+		}
+	}
+	
+	fn equiv(&self, arg: &str) -> String {
+		let (a, b) = match arg.find(';') {
+			Some(i) => (&arg[..i], &arg[i + 1..]),
+			None => return "usage: :equiv A ; B".to_string(),
+		};
+		let (a, b) = match (parse(a), parse(b)) {
+			(Ok(a), Ok(b)) => (a, b),
+			(Err(e), _) | (_, Err(e)) => return e,
+		};
+		let iff = Formula::Iff(Box::new(a.clone()), Box::new(b.clone()));
+		match Formula::Not(Box::new(iff)).satisfy() {
+			None => format!("{} and {} are equivalent", self.show(&a), self.show(&b)),
+			Some(vals) => format!("{} and {} differ at:{}", self.show(&a), self.show(&b), valuation(&vals)),
+		}
+	}
+	
+	fn prove(&self, arg: &str) -> String {
+		let seq = match Sequent::parse(arg) {
+			Ok(seq) => seq,
+			Err((n, e)) => return format!("`{}`: formula {}: {}", arg, n + 1, e),
+		};
+		match prove::prove(&seq) {
+			Ok(lines) => self.show(&lines).trim_right().to_string(),
+			Err(e) => e.to_string(),
+		}
+	}
+	
+	/// Writes `t` in the notation chosen.
+	fn show<T: Display>(&self, t: &T) -> String {
+		if self.unicode {
+			format!("{:#}", t)
+		} else {
+			format!("{}", t)
+		}
+	}
+}
+
+fn parse(s: &str) -> Result<Formula, String> {
+	Formula::new(s.trim()).map_err(|e| format!("`{}`: {}", s.trim(), e))
+}
+
+/// Writes a valuation as ` P=T Q=F`, as in `ProveError`.
+fn valuation(vals: &[(char, bool)]) -> String {
+	vals.iter().map(|&(c, b)| format!(" {}={}", c, if b { 'T' } else { 'F' })).collect()
+}
+
+/// The file the input history is kept in, in the home directory.
+#[cfg(feature = "repl")]
+fn history_path() -> Option<PathBuf> {
+	env::var_os("HOME").map(|home| PathBuf::from(home).join(".plc_history"))
+}
+
+/// Runs the REPL until the input ends or `:quit` is entered. Returns the exit code.
+#[cfg(feature = "repl")]
+pub fn run() -> i32 {
+	let mut rl = rustyline::Editor::<()>::new();
+	let history = history_path();
+	if let Some(ref path) = history {
+		let _ = rl.load_history(path);
+	}
+	let mut repl = Repl::new();
+	println!("Propositional Logic Calculator. :help lists the commands.");
+	let code = loop {
+		let line = match rl.readline("plc> ") {
+			Ok(line) => line,
+			Err(ReadlineError::Interrupted) => continue,
+			Err(ReadlineError::Eof) => break SUCCESS,
+			Err(e) => {
+				println!("{}", e);
+				break ERROR;
+			},
+		};
+		if !line.trim().is_empty() {
+			rl.add_history_entry(line.as_str());
+		}
+		match repl.eval(&line) {
+			Some(ref out) if out.is_empty() => {},
+			Some(out) => println!("{}", out),
+			None => break SUCCESS,
+		}
+	};
+	if let Some(ref path) = history {
+		let _ = rl.save_history(path);
+	}
+	code
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn test_repl() {
+		let mut repl = Repl::new();
+		let mut eval = |s: &str| {
+			let out = repl.eval(s).unwrap();
+			println!("[test_repl] {}\n{}", s, out);
+			out
+		};
+		assert_eq!(eval("P v ~P"), "P∨¬P: tautology");
+		assert_eq!(eval("P -> Q, ~Q |- ~P"), "P→Q, ¬Q ⊢ ¬P: valid");
+		assert_eq!(eval("P -> Q |- Q"), "P→Q ⊢ Q: invalid, counterexample: P=F Q=F");
+		assert_eq!(eval(":sat P & ~Q"), "satisfiable: P=T Q=F");
+		assert_eq!(eval(":sat P & ~P"), "unsatisfiable");
+		assert_eq!(eval(":equiv ~(P & Q) ; ~P v ~Q"), "¬(P∧Q) and ¬P∨¬Q are equivalent");
+		assert_eq!(eval(":equiv P -> Q ; Q -> P"), "P→Q and Q→P differ at: P=T Q=F");
+		assert_eq!(eval(":tt P & Q").lines().count(), 5);
+		assert!(eval(":prove P -> Q, P |- Q").ends_with("{1, 2}"));
+		assert_eq!(eval(":ascii"), "results are written in ascii");
+		assert_eq!(eval(":cnf P v (Q & R)"), "(PvQ)&(PvR)");
+		assert_eq!(eval("P &"), "`P &`: formula ends unexpectedly");
+		assert!(eval(":foo").starts_with("unknown command"));
+		assert_eq!(repl.eval(":q"), None);
+	}
+}
@@ -0,0 +1,117 @@
+use std::fmt::{self, Display, Formatter};
+
+/// The rules of inference of every proof system. `ProofSystem::rules` gives the rules each system allows.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Rule {
+	/// A premise of the sequent being proved. Depends only on itself.
+	Premise,
+	/// An assumption. Depends only on itself.
+	Assumption,
+	AndI,
+	AndE,
+	OrI,
+	OrE,
+	ImpliesI,
+	/// Modus ponendo ponens.
+	ImpliesE,
+	/// Modus tollendo tollens.
+	Mtt,
+	NotI,
+	NotE,
+	/// Reductio ad absurdum.
+	Raa,
+	/// Double negation, introduction or elimination.
+	Dn,
+	IffI,
+	IffE,
+	/// Sequent introduction: a substitution instance of a sequent in the `Library`.
+	Si,
+	/// Theorem introduction: a substitution instance of a theorem in the `Library`.
+	Ti,
+	/// Reiteration: repeats a line that is in scope. Fitch only.
+	Reit,
+	/// Explosion: anything follows from `⊥`. Fitch only.
+	X,
+	/// Indirect proof: `φ` from a subproof assuming `¬φ` that ends in `⊥`. Fitch only.
+	Ip,
+	/// Tautological implication: anything that follows truth-functionally from the cited lines. Suppes only.
+	Taut,
+	/// A derived rule from the `Library`, cited by its own name. The name is kept in the `Justification`.
+	Derived,
+}
+impl Rule {
+	/// Every built-in rule, in the order they are usually taught. `Derived` isn't included, as it can't be
+	/// cited by its own name.
+	pub fn all() -> &'static [Rule] {
+		use self::Rule::*;
+		const ALL: &'static [Rule] = &[Premise, Assumption, AndI, AndE, OrI, OrE, ImpliesI, ImpliesE, Mtt, NotI, NotE, Raa,
+			Dn, IffI, IffE, Si, Ti, Reit, X, Ip, Taut];
+		ALL
+	}
+	
+	/// Looks up a rule by its ascii or pretty name, or by the traditional names `MPP`, `CP`, `PR`, `P` and `AS`.
+	pub fn from_name(s: &str) -> Option<Rule> {
+		for r in Rule::all().iter() {
+			let (ascii, pretty) = r.names();
+			if s == ascii || s == pretty {
+				return Some(*r);
+			}
+		}
+		match s {
+			"MPP"      => Some(Rule::ImpliesE),
+			"CP"       => Some(Rule::ImpliesI),
+			"PR" | "P" => Some(Rule::Premise),
+			"AS"       => Some(Rule::Assumption),
+			_ => None,
+		}
+	}
+	
+	/// The ascii and pretty forms of the rule's name, as they appear in the method column.
+	pub fn names(&self) -> (&'static str, &'static str) {
+		use self::Rule::*;
+		match *self {
+			Premise    => ("Premise", "Premise"),
+			Assumption => ("A", "A"),
+			AndI       => ("&I", "∧I"),
+			AndE       => ("&E", "∧E"),
+			OrI        => ("vI", "∨I"),
+			OrE        => ("vE", "∨E"),
+			ImpliesI   => ("->I", "→I"),
+			ImpliesE   => ("->E", "→E"),
+			Mtt        => ("MTT", "MTT"),
+			NotI       => ("~I", "¬I"),
+			NotE       => ("~E", "¬E"),
+			Raa        => ("RAA", "RAA"),
+			Dn         => ("DN", "DN"),
+			IffI       => ("<->I", "↔I"),
+			IffE       => ("<->E", "↔E"),
+			Si         => ("SI", "SI"),
+			Ti         => ("TI", "TI"),
+			Reit       => ("R", "R"),
+			X          => ("X", "X"),
+			Ip         => ("IP", "IP"),
+			Taut       => ("T", "T"),
+			Derived    => ("Derived", "Derived"),
+		}
+	}
+	
+	/// True if the rule cites a sequent from the `Library` by name.
+	pub fn is_introduction(&self) -> bool {
+		*self == Rule::Si || *self == Rule::Ti
+	}
+	
+	/// True if the rule introduces a line that depends only on itself.
+	pub fn is_assumption(&self) -> bool {
+		*self == Rule::Premise || *self == Rule::Assumption
+	}
+}
+impl Display for Rule {
+	fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+		let (ascii, pretty) = self.names();
+		if !f.alternate() {
+			f.pad(ascii)
+		} else {
+			f.pad(pretty)
+		}
+	}
+}
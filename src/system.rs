@@ -0,0 +1,285 @@
+use std::fmt::{self, Display, Formatter};
+
+use logic::Formula;
+use logic::form::Implies;
+
+use check::{self, Diagnostic};
+use edit::{Line, Lines};
+use fitch;
+use just::Justification;
+use library::Library;
+use rule::Rule;
+
+/// How the lines of a proof keep track of the premises and assumptions they rest on.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Structure {
+	/// Each line lists the lines it depends on, as in Lemmon and Suppes.
+	Deps,
+	/// Assumptions open indented subproofs, which close when they are discharged, as in Fitch.
+	Subproofs,
+}
+
+/// Why a proof couldn't be converted from one proof system to another.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConvertError {
+	/// The proof has a mistake in it. Contains the first one found.
+	Invalid(Diagnostic),
+	/// Line `no` opens or closes a subproof that overlaps another without being inside it.
+	NotNested(usize),
+	/// Line `no` cites a line that would be inside a closed subproof in the Fitch proof.
+	OutOfScope(usize),
+	/// Line `no` uses a rule that can't be expressed in the other system.
+	Unsupported(usize, Rule),
+}
+impl Display for ConvertError {
+	fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+		match self {
+			&ConvertError::Invalid(ref d)        => d.fmt(f),
+			&ConvertError::NotNested(no)         => write!(f, "line {}: subproofs overlap", no + 1),
+			&ConvertError::OutOfScope(no)        => write!(f, "line {}: cites a line from a closed subproof", no + 1),
+			&ConvertError::Unsupported(no, rule) => write!(f, "line {}: {:#} has no counterpart", no + 1, rule),
+		}
+	}
+}
+
+/// A style of natural deduction proof: how the lines are laid out, which rules can be used, and how the
+/// premises and assumptions each line rests on are tracked. `Editor` and the checker work with any system,
+/// so the textbook's variant can be picked without changing anything else.
+pub trait ProofSystem {
+	/// The name of the system, e.g. `Lemmon`.
+	fn name(&self) -> &'static str;
+	
+	/// How the lines of a proof keep track of what they rest on.
+	fn structure(&self) -> Structure;
+	
+	/// The rules that can be used.
+	fn rules(&self) -> &'static [Rule];
+	
+	/// The dependencies line `no` of `lines` should have when it is justified by `j`. Returns `None` if the
+	/// lines cited can't be used by the rule, or if the system doesn't track dependencies.
+	fn deps(&self, j: &Justification, no: usize, lines: &Lines) -> Option<Vec<usize>> {
+		match self.structure() {
+			Structure::Deps      => j.deps(no, |c| &lines[c].deps),
+			Structure::Subproofs => None,
+		}
+	}
+	
+	/// Checks every line of a proof, returning the problems found in line order. `SI` and `TI` lines cite
+	/// sequents in `lib`.
+	fn check(&self, lines: &Lines, lib: &Library) -> Vec<Diagnostic> {
+		check::check_deps(self, lines, lib)
+	}
+	
+	/// Rewrites a Lemmon-style proof, such as one found by `prove::prove`, in this system.
+	fn from_lemmon(&self, lines: &Lines) -> Result<Lines, ConvertError>;
+	
+	/// Writes out a proof in this system's layout.
+	fn fmt_lines(&self, lines: &Lines, f: &mut Formatter) -> Result<(), fmt::Error> {
+		Display::fmt(lines, f)
+	}
+	
+	/// Wraps `lines` so that they display in this system's layout.
+	fn show<'a>(&'a self, lines: &'a Lines) -> Show<'a, Self> where Self: Sized {
+		Show {
+			sys: self,
+			lines: lines,
+		}
+	}
+}
+impl<S: ProofSystem + ?Sized> ProofSystem for Box<S> {
+	fn name(&self) -> &'static str {
+		(**self).name()
+	}
+	fn structure(&self) -> Structure {
+		(**self).structure()
+	}
+	fn rules(&self) -> &'static [Rule] {
+		(**self).rules()
+	}
+	fn deps(&self, j: &Justification, no: usize, lines: &Lines) -> Option<Vec<usize>> {
+		(**self).deps(j, no, lines)
+	}
+	fn check(&self, lines: &Lines, lib: &Library) -> Vec<Diagnostic> {
+		(**self).check(lines, lib)
+	}
+	fn from_lemmon(&self, lines: &Lines) -> Result<Lines, ConvertError> {
+		(**self).from_lemmon(lines)
+	}
+	fn fmt_lines(&self, lines: &Lines, f: &mut Formatter) -> Result<(), fmt::Error> {
+		(**self).fmt_lines(lines, f)
+	}
+}
+
+/// A proof displayed in the layout of a proof system. See `ProofSystem::show`.
+pub struct Show<'a, S: ProofSystem + 'a> {
+	sys: &'a S,
+	lines: &'a Lines,
+}
+impl<'a, S: ProofSystem> Display for Show<'a, S> {
+	fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+		self.sys.fmt_lines(self.lines, f)
+	}
+}
+
+/// Looks up a proof system by its name, ignoring case: `Lemmon`, `Fitch` or `Suppes`.
+pub fn from_name(name: &str) -> Option<Box<ProofSystem>> {
+	match &name.to_lowercase()[..] {
+		"lemmon" => Some(Box::new(Lemmon)),
+		"fitch"  => Some(Box::new(Fitch)),
+		"suppes" => Some(Box::new(Suppes)),
+		_ => None,
+	}
+}
+
+/// Lemmon's system, from *Beginning Logic*. Each line lists the premises and assumptions it depends on, and
+/// rules such as `→I` discharge an assumption by citing it.
+#[derive(Copy, Clone, Debug)]
+pub struct Lemmon;
+impl ProofSystem for Lemmon {
+	fn name(&self) -> &'static str {
+		"Lemmon"
+	}
+	fn structure(&self) -> Structure {
+		Structure::Deps
+	}
+	fn rules(&self) -> &'static [Rule] {
+		use rule::Rule::*;
+		const RULES: &'static [Rule] = &[Premise, Assumption, AndI, AndE, OrI, OrE, ImpliesI, ImpliesE, Mtt, NotI, NotE,
+			Raa, Dn, IffI, IffE, Si, Ti];
+		RULES
+	}
+	fn from_lemmon(&self, lines: &Lines) -> Result<Lines, ConvertError> {
+		Ok(lines.clone())
+	}
+}
+
+/// The Fitch system, as in *forall x*. Assumptions open indented subproofs, which are cited as ranges to
+/// discharge them. See `fitch`.
+#[derive(Copy, Clone, Debug)]
+pub struct Fitch;
+impl ProofSystem for Fitch {
+	fn name(&self) -> &'static str {
+		"Fitch"
+	}
+	fn structure(&self) -> Structure {
+		Structure::Subproofs
+	}
+	fn rules(&self) -> &'static [Rule] {
+		use rule::Rule::*;
+		const RULES: &'static [Rule] = &[Premise, Assumption, Reit, AndI, AndE, OrI, OrE, ImpliesI, ImpliesE, IffI, IffE,
+			NotI, NotE, X, Ip];
+		RULES
+	}
+	fn check(&self, lines: &Lines, lib: &Library) -> Vec<Diagnostic> {
+		fitch::check(lines, lib)
+	}
+	fn from_lemmon(&self, lines: &Lines) -> Result<Lines, ConvertError> {
+		fitch::from_lemmon(lines)
+	}
+	fn fmt_lines(&self, lines: &Lines, f: &mut Formatter) -> Result<(), fmt::Error> {
+		fitch::fmt_lines(lines, f)
+	}
+}
+
+/// Suppes' system, from *Introduction to Logic*. Premises and assumptions are both introduced by `P`, anything
+/// tautologically implied by earlier lines follows by `T`, and `CP` (`→I`) discharges an assumption.
+#[derive(Copy, Clone, Debug)]
+pub struct Suppes;
+impl ProofSystem for Suppes {
+	fn name(&self) -> &'static str {
+		"Suppes"
+	}
+	fn structure(&self) -> Structure {
+		Structure::Deps
+	}
+	fn rules(&self) -> &'static [Rule] {
+		const RULES: &'static [Rule] = &[Rule::Premise, Rule::ImpliesI, Rule::Taut];
+		RULES
+	}
+	/// Lines that discharge an assumption some other way become `CP` followed by `T`. Everything else that
+	/// isn't `P` or `CP` becomes `T`.
+	fn from_lemmon(&self, lines: &Lines) -> Result<Lines, ConvertError> {
+		if let Some(d) = check::check(lines).into_iter().next() {
+			return Err(ConvertError::Invalid(d));
+		}
+		fn push(out: &mut Vec<Line>, f: Formula, rule: Rule, cites: &[usize]) -> usize {
+			let no = out.len();
+			out.push(Line::full(no, f.to_tokens(), Justification::with_lines(rule, cites).to_tokens(), Vec::new()));
+			no
+		}
+		
+		let forms: Vec<Formula> = lines.iter().map(|l| Formula::parse(&l.step).unwrap()).collect();
+		let mut out = Vec::with_capacity(lines.len());
+		let mut map = vec![0; lines.len()];
+		for no in 0..lines.len() {
+			let j = lines[no].justification().unwrap();
+			let cites = j.lines();
+			let f = forms[no].clone();
+			// Conditional proof of assumption `a` to conclusion `c`
+			let cp = |out: &mut Vec<Line>, a: usize, c: usize| {
+				let cond = Implies(Box::new(forms[a].clone()), Box::new(forms[c].clone()));
+				push(out, cond, Rule::ImpliesI, &[map[a], map[c]])
+			};
+			map[no] = match j.rule {
+				Rule::Premise | Rule::Assumption => push(&mut out, f, Rule::Premise, &[]),
+				Rule::ImpliesI => push(&mut out, f, Rule::ImpliesI, &[map[cites[0]], map[cites[1]]]),
+				Rule::NotI | Rule::Raa => {
+					let c = cp(&mut out, cites[0], cites[1]);
+					push(&mut out, f, Rule::Taut, &[c])
+				},
+				Rule::OrE => {
+					let c1 = cp(&mut out, cites[1], cites[2]);
+					let c2 = cp(&mut out, cites[3], cites[4]);
+					push(&mut out, f, Rule::Taut, &[map[cites[0]], c1, c2])
+				},
+				_ => {
+					let cites: Vec<usize> = cites.iter().map(|&c| map[c]).collect();
+					push(&mut out, f, Rule::Taut, &cites)
+				},
+			};
+		}
+		let mut lines = Lines::from_vec(out);
+		lines.update_deps_with(self, &Library::new());
+		Ok(lines)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use logic::Sequent;
+	
+	use check::{CheckError, Diagnostic};
+	use edit::{line, Lines};
+	use library::Library;
+	use prove::prove;
+	use rule::Rule;
+	use super::*;
+	
+	#[test]
+	fn test_systems_check_converted_proofs() {
+		for name in ["Lemmon", "fitch", "SUPPES"].iter() {
+			let sys = from_name(name).unwrap();
+			for s in ["P->Q, ~Q |- ~P", "P^(QvR) |- (P^Q)v(P^R)", "|- ((P->Q)->P)->P"].iter() {
+				let lines = sys.from_lemmon(&prove(&Sequent::parse(s).unwrap()).unwrap()).unwrap();
+				println!("[test_systems_check_converted_proofs] {} {}\n{:#}", sys.name(), s, sys.show(&lines));
+				assert_eq!(sys.check(&lines, &Library::standard()), vec![]);
+			}
+		}
+	}
+	
+	#[test]
+	fn test_suppes() {
+		let lines = Lines::from_vec(vec![
+			line(0, "P->Q", "P", vec![0]),
+			line(1, "~Q", "P", vec![1]),
+			line(2, "~P", "T 1, 2", vec![0, 1]),
+			line(3, "P", "T 1, 2", vec![0, 1]),
+			line(4, "~P", "MTT 1, 2", vec![0, 1]),
+			line(5, "~Q->~P", "CP 2, 3", vec![0]),
+		]);
+		assert_eq!(Suppes.check(&lines, &Library::new()), vec![
+			Diagnostic { no: 3, err: CheckError::Mismatch(Rule::Taut) },
+			Diagnostic { no: 4, err: CheckError::NotAllowed(Rule::Mtt) },
+		]);
+	}
+}
@@ -0,0 +1,370 @@
+//! Gentzen-style proof trees.
+//!
+//! A linear proof is unfolded into a tree from the line it proves: each line's justification becomes an
+//! inference whose premises are the trees of the lines it cites. Premises and assumptions become leaves.
+//! An assumption that is discharged by an inference below it is written in brackets with a number, `[P]¹`, and
+//! the inference that discharges it is labelled with the same number.
+use std::fmt::{self, Display, Formatter};
+
+use logic::Formula;
+
+use check::{self, CheckError, Diagnostic};
+use edit::Lines;
+use just::Justification;
+use library::Library;
+use rule::Rule;
+use system::ConvertError;
+
+/// A natural deduction proof tree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Tree {
+	pub formula: Formula,
+	/// The rule used to reach `formula`, or `None` if it is a premise or an assumption.
+	pub rule: Option<Rule>,
+	/// The name of the sequent cited by `SI` and `TI`, or of the derived rule.
+	pub name: Option<String>,
+	/// The number of the inference that discharges the assumption, if `self` is a discharged assumption.
+	pub marker: Option<usize>,
+	/// The numbers of the assumptions that are discharged by this inference.
+	pub discharges: Vec<usize>,
+	/// The trees of the premises of the inference. Empty for leaves.
+	pub children: Vec<Tree>,
+}
+
+/// Why a tree couldn't be written as LaTeX.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LatexError {
+	/// An inference has more than the five premises `bussproofs` can draw. Contains the formula it proves and the
+	/// number of premises.
+	TooManyPremises(Formula, usize),
+}
+impl Display for LatexError {
+	fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+		match self {
+			&LatexError::TooManyPremises(ref form, n) =>
+				write!(f, "the inference of {:#} has {} premises, but LaTeX can draw at most 5", form, n),
+		}
+	}
+}
+
+/// Unfolds the proof into a tree.
+struct Builder {
+	forms: Vec<Formula>,
+	justs: Vec<Justification>,
+	/// The number of the last marker given out.
+	markers: usize,
+}
+impl Builder {
+	/// Builds the tree of line `no`. `open` holds the assumption lines discharged below, with their markers.
+	fn build(&mut self, no: usize, open: &[(usize, usize)]) -> Tree {
+		let j = self.justs[no].clone();
+		let cites = j.lines();
+		let mut tree = Tree {
+			formula: self.forms[no].clone(),
+			rule: Some(j.rule),
+			name: j.name.clone(),
+			marker: None,
+			discharges: Vec::new(),
+			children: Vec::new(),
+		};
+		if j.rule.is_assumption() {
+			tree.rule = None;
+			tree.name = None;
+			tree.marker = open.iter().find(|&&(a, _)| a == no).map(|&(_, m)| m);
+			return tree;
+		}
+		
+		// The premises of the inference, and the assumption each discharges
+		let premises: Vec<(usize, Option<usize>)> = match (j.rule, cites.len()) {
+			(Rule::ImpliesI, 2) | (Rule::NotI, 2) | (Rule::Raa, 2) | (Rule::Ip, 2) => vec![(cites[1], Some(cites[0]))],
+			(Rule::OrE, 5) => vec![(cites[0], None), (cites[2], Some(cites[1])), (cites[4], Some(cites[3]))],
+			(Rule::IffI, 4) => vec![(cites[1], Some(cites[0])), (cites[3], Some(cites[2]))],
+			_ => cites.iter().map(|&c| (c, None)).collect(),
+		};
+		for (c, a) in premises {
+			let mut open = open.to_vec();
+			if let Some(a) = a {
+				self.markers += 1;
+				tree.discharges.push(self.markers);
+				open.push((a, self.markers));
+			}
+			tree.children.push(self.build(c, &open));
+		}
+		tree
+	}
+}
+
+impl Tree {
+	/// Builds the tree of the last line of a checked Lemmon-style proof. See `from_line`.
+	pub fn from_lines(lines: &Lines) -> Result<Tree, ConvertError> {
+		Tree::from_line(lines, &Library::standard(), lines.len().saturating_sub(1))
+	}
+	
+	/// Builds the tree of line `no` of a Lemmon-style proof, with `SI` and `TI` lines citing sequents in `lib`.
+	/// Fails if the line or any line before it has a mistake.
+	pub fn from_line(lines: &Lines, lib: &Library, no: usize) -> Result<Tree, ConvertError> {
+		if no >= lines.len() {
+			return Err(ConvertError::Invalid(Diagnostic {
+				no: no,
+				err: CheckError::BadRef(no),
+			}));
+		}
+		if let Some(d) = check::check_with(lines, lib).into_iter().find(|d| d.no <= no) {
+			return Err(ConvertError::Invalid(d));
+		}
+		let mut b = Builder {
+			forms: lines[..no + 1].iter().map(|l| Formula::parse(&l.step).unwrap()).collect(),
+			justs: lines[..no + 1].iter().map(|l| l.justification_with(lib).unwrap()).collect(),
+			markers: 0,
+		};
+		Ok(b.build(no, &[]))
+	}
+	
+	/// True if the tree is a premise or an assumption.
+	pub fn is_leaf(&self) -> bool {
+		self.rule.is_none()
+	}
+	
+	/// The label of the inference, e.g. `→I 1`, or an empty string for a leaf.
+	fn label(&self, alt: bool) -> String {
+		let mut s = match (self.rule, &self.name) {
+			(None, _) => return String::new(),
+			(Some(Rule::Derived), &Some(ref name)) => name.clone(),
+			(Some(rule), name) => {
+				let mut s = if alt { format!("{:#}", rule) } else { format!("{}", rule) };
+				if let &Some(ref name) = name {
+					s.push_str(&format!(" ({})", name));
+				}
+				s
+			},
+		};
+		for (i, m) in self.discharges.iter().enumerate() {
+			s.push_str(&format!("{}{}", if i == 0 { " " } else { "," }, m));
+		}
+		s
+	}
+	
+	/// Lays out the tree as lines of text of the same width, with the conclusion on the last line.
+	fn layout(&self, alt: bool) -> (Vec<String>, usize) {
+		fn width(s: &str) -> usize {
+			s.chars().count()
+		}
+		fn pad(s: &mut String, left: usize, right: usize) {
+			*s = format!("{}{}{}", " ".repeat(left), s, " ".repeat(right));
+		}
+		
+		let mut concl = if alt { format!("{:#}", self.formula) } else { format!("{}", self.formula) };
+		if self.is_leaf() {
+			if let Some(m) = self.marker {
+				concl = format!("[{}]{}", concl, m);
+			}
+			let w = width(&concl);
+			return (vec![concl], w);
+		}
+		
+		// Place the premises side by side, lined up along the bottom
+		const GAP: usize = 3;
+		let children: Vec<(Vec<String>, usize)> = self.children.iter().map(|c| c.layout(alt)).collect();
+		let height = children.iter().map(|c| c.0.len()).max().unwrap_or(0);
+		let mut rows = vec![String::new(); height];
+		for (i, &(ref lines, w)) in children.iter().enumerate() {
+			for (r, row) in rows.iter_mut().enumerate() {
+				if i != 0 {
+					row.push_str(&" ".repeat(GAP));
+				}
+				match (r + lines.len()).checked_sub(height) {
+					Some(k) => row.push_str(&lines[k]),
+					None => row.push_str(&" ".repeat(w)),
+				}
+			}
+		}
+		let premises_w = rows.first().map(|r| width(r)).unwrap_or(0);
+		
+		let label = self.label(alt);
+		let bar_w = premises_w.max(width(&concl));
+		let total = bar_w + if label.is_empty() { 0 } else { 1 + width(&label) };
+		for row in rows.iter_mut() {
+			let left = (bar_w - premises_w) / 2;
+			pad(row, left, total - premises_w - left);
+		}
+		let mut bar = (if alt { "─" } else { "-" }).repeat(bar_w);
+		if !label.is_empty() {
+			bar.push(' ');
+			bar.push_str(&label);
+		}
+		rows.push(bar);
+		let left = (bar_w - width(&concl)) / 2;
+		let right = total - width(&concl) - left;
+		pad(&mut concl, left, right);
+		rows.push(concl);
+		(rows, total)
+	}
+	
+	/// Writes the tree as a LaTeX `prooftree` environment for the `bussproofs` package. Fails if an inference has
+	/// more than five premises.
+	pub fn to_latex(&self) -> Result<String, LatexError> {
+		let mut s = String::from("\\begin{prooftree}\n");
+		try!(self.write_latex(&mut s));
+		s.push_str("\\end{prooftree}\n");
+		Ok(s)
+	}
+	
+	fn write_latex(&self, s: &mut String) -> Result<(), LatexError> {
+		let mut f = String::new();
+		latex_formula(&self.formula, &mut f);
+		if self.is_leaf() {
+			match self.marker {
+				Some(m) => s.push_str(&format!("\\AxiomC{{$[{}]^{{{}}}$}}\n", f, m)),
+				None => s.push_str(&format!("\\AxiomC{{${}$}}\n", f)),
+			}
+			return Ok(());
+		}
+		for c in self.children.iter() {
+			try!(c.write_latex(s));
+		}
+		let inf = match self.children.len() {
+			0 => {
+				s.push_str("\\AxiomC{}\n");
+				"Unary"
+			},
+			1 => "Unary",
+			2 => "Binary",
+			3 => "Trinary",
+			4 => "Quaternary",
+			5 => "Quinary",
+			n => return Err(LatexError::TooManyPremises(self.formula.clone(), n)),
+		};
+		let mut label = match (self.rule, &self.name) {
+			(Some(Rule::Derived), &Some(ref name)) => latex_text(name),
+			(rule, name) => {
+				let mut l = latex_text(&format!("{:#}", rule.unwrap()));
+				if let &Some(ref name) = name {
+					l.push_str(&format!(" ({})", latex_text(name)));
+				}
+				l
+			},
+		};
+		if !self.discharges.is_empty() {
+			let ms: Vec<String> = self.discharges.iter().map(|m| m.to_string()).collect();
+			label.push_str(&format!("$^{{{}}}$", ms.join(",")));
+		}
+		s.push_str(&format!("\\RightLabel{{{}}}\n\\{}InfC{{${}$}}\n", label, inf, f));
+		Ok(())
+	}
+}
+/// Draws the tree as text, with ascii connectives, or unicode connectives with `{:#}`.
+impl Display for Tree {
+	fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+		for row in self.layout(f.alternate()).0 {
+			try!(writeln!(f, "{}", row.trim_right()));
+		}
+		Ok(())
+	}
+}
+
+/// Writes `form` in LaTeX math mode, bracketed like `Formula`'s `Display`.
+fn latex_formula(form: &Formula, s: &mut String) {
+	fn brackets(p: &Formula, s: &mut String) {
+		let bare = match p {
+			&Formula::Var(_) | &Formula::Bottom => true,
+			&Formula::Not(ref q) => if let Formula::Var(_) = **q { true } else { false },
+			_ => false,
+		};
+		if !bare {
+			s.push('(');
+		}
+		latex_formula(p, s);
+		if !bare {
+			s.push(')');
+		}
+	}
+	let (p, op, q) = match form {
+		&Formula::Var(c) => return s.push(c),
+		&Formula::Bottom => return s.push_str("\\bot "),
+		&Formula::Not(ref p) => {
+			s.push_str("\\neg ");
+			if let Formula::Not(_) = **p {
+				latex_formula(p, s);
+			} else {
+				brackets(p, s);
+			}
+			return;
+		},
+		&Formula::And(ref p, ref q)     => (p, " \\land ", q),
+		&Formula::Or(ref p, ref q)      => (p, " \\lor ", q),
+		&Formula::Implies(ref p, ref q) => (p, " \\to ", q),
+		&Formula::Iff(ref p, ref q)     => (p, " \\leftrightarrow ", q),
+	};
+	brackets(p, s);
+	s.push_str(op);
+	brackets(q, s);
+}
+
+/// Escapes text for LaTeX, writing connectives in math mode.
+fn latex_text(text: &str) -> String {
+	let mut s = String::new();
+	for c in text.chars() {
+		match c {
+			'¬' => s.push_str("$\\neg$"),
+			'∧' => s.push_str("$\\land$"),
+			'∨' => s.push_str("$\\lor$"),
+			'→' => s.push_str("$\\to$"),
+			'↔' => s.push_str("$\\leftrightarrow$"),
+			'⊥' => s.push_str("$\\bot$"),
+			'&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+				s.push('\\');
+				s.push(c);
+			},
+			c => s.push(c),
+		}
+	}
+	s
+}
+
+#[cfg(test)]
+mod tests {
+	use logic::Sequent;
+	
+	use prove::prove;
+	use super::*;
+	
+	#[test]
+	fn test_tree() {
+		let lines = prove(&Sequent::parse("P->Q, Q->R |- P->R").unwrap()).unwrap();
+		let tree = Tree::from_lines(&lines).unwrap();
+		let s = format!("{:#}", tree);
+		println!("[test_tree] {}\n{}\n{}", lines, s, tree.to_latex().unwrap());
+		assert_eq!(s, "      P→Q   [P]1\n\
+			\x20     ────────── →E\n\
+			Q→R       Q\n\
+			─────────────────── →E\n\
+			\x20        R\n\
+			────────────────────── →I 1\n\
+			\x20        P→R\n");
+		assert_eq!(tree.to_latex().unwrap(), "\\begin{prooftree}\n\
+			\\AxiomC{$Q \\to R$}\n\
+			\\AxiomC{$P \\to Q$}\n\
+			\\AxiomC{$[P]^{1}$}\n\
+			\\RightLabel{$\\to$E}\n\
+			\\BinaryInfC{$Q$}\n\
+			\\RightLabel{$\\to$E}\n\
+			\\BinaryInfC{$R$}\n\
+			\\RightLabel{$\\to$I$^{1}$}\n\
+			\\UnaryInfC{$P \\to R$}\n\
+			\\end{prooftree}\n");
+		
+		// Each branch of ∨E discharges its own assumption
+		let lines = prove(&Sequent::parse("PvQ |- QvP").unwrap()).unwrap();
+		let tree = Tree::from_lines(&lines).unwrap();
+		println!("[test_tree] {}\n{:#}", lines, tree);
+		assert_eq!(tree.rule, Some(Rule::OrE));
+		assert_eq!(tree.discharges, vec![1, 2]);
+		assert_eq!(tree.children[1].children[0].marker, Some(1));
+		assert_eq!(tree.children[2].children[0].marker, Some(2));
+		
+		// bussproofs can't draw more than five premises
+		let mut wide = tree.clone();
+		wide.children = vec![tree.children[0].clone(); 6];
+		assert_eq!(wide.to_latex(), Err(LatexError::TooManyPremises(wide.formula.clone(), 6)));
+	}
+}
@@ -0,0 +1,467 @@
+//! A terminal frontend for the editor, started with `plc tui [FILE]`, for machines without a display.
+//!
+//! The proof is shown in the same three columns as `Gui::render`: the number and formula, the method and the
+//! dependencies. Lines with problems are marked with `!`, and what is wrong with the line the cursor is on is
+//! shown at the bottom of the screen, under a status line. Keys are passed to `Editor::handle_key`, apart from
+//! Ctrl+S to save, Ctrl+Q to quit and Ctrl+C, Ctrl+X and Ctrl+V, which copy, cut and paste with a clipboard
+//! kept by the frontend. As in the editor, `PLC_SYSTEM` and `PLC_RULES` choose the proof system and derived
+//! rules.
+//!
+//! Terminals don't report Shift, Ctrl or Alt held down with the arrow keys in a way `termion` can read, so the
+//! bindings that need them aren't available. Ctrl+A selects everything.
+use std::cell::Cell;
+use std::cmp;
+use std::fs::File;
+use std::io::{self, ErrorKind, Read, Write};
+use std::mem;
+use std::rc::Rc;
+
+use termion::{self, clear, color, cursor, style};
+use termion::event::Key as TermKey;
+use termion::input::TermRead;
+use termion::raw::IntoRawMode;
+use termion::screen::AlternateScreen;
+
+use logic::Token;
+
+use cli::{self, SUCCESS, ERROR};
+use edit::{Col, Editor, Selection};
+use input::{Change, EditorCommand, Key, KeyInput};
+use system::ProofSystem;
+
+/// How a part of the screen is drawn.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Style {
+	Normal,
+	Selected,
+	/// Something wrong with the proof, drawn in red.
+	Error,
+	/// The status line, drawn in reverse video.
+	Status,
+}
+
+/// What to draw on the terminal: a row of text for each row of the screen, and where to put the cursor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Screen {
+	pub rows: Vec<Vec<(Style, String)>>,
+	/// The column and row of the cursor, starting at 0.
+	pub cursor: (usize, usize),
+}
+impl Screen {
+	/// The text of a row, without its styles.
+	pub fn text(&self, y: usize) -> String {
+		self.rows[y].iter().map(|&(_, ref s)| &s[..]).collect()
+	}
+}
+
+/// Adds `s` to the end of `row`, joining it to the last part if they have the same style.
+fn push(row: &mut Vec<(Style, String)>, style: Style, s: &str) {
+	if s.is_empty() {
+		return;
+	}
+	if let Some(&mut (last, ref mut text)) = row.last_mut() {
+		if last == style {
+			text.push_str(s);
+			return;
+		}
+	}
+	row.push((style, s.to_string()));
+}
+
+/// Cuts `row` down to the `width` characters starting at character `start`.
+fn clip(row: Vec<(Style, String)>, start: usize, width: usize) -> Vec<(Style, String)> {
+	let mut skip = start;
+	let mut left = width;
+	let mut clipped = Vec::new();
+	for (style, s) in row.into_iter() {
+		if left == 0 {
+			break;
+		}
+		let n = s.chars().count();
+		if n <= skip {
+			skip -= n;
+			continue;
+		}
+		let part: String = s.chars().skip(skip).take(left).collect();
+		skip = 0;
+		left -= part.chars().count();
+		clipped.push((style, part));
+	}
+	clipped
+}
+
+/// The editor, as shown on a terminal.
+pub struct Tui<S: ProofSystem> {
+	edit: Editor<S>,
+	/// The file the proof is saved to, if one was given.
+	path: Option<String>,
+	/// The first line of the proof shown.
+	scroll: usize,
+	/// The first column of the proof shown, when its lines are wider than the terminal.
+	hscroll: usize,
+	/// A message shown at the bottom of the screen until the next key is pressed.
+	message: Option<String>,
+	clipboard: Option<String>,
+	/// True if the proof has been edited since it was loaded or saved. Set by the editor's listener.
+	modified: Rc<Cell<bool>>,
+	/// True if Ctrl+Q was just pressed with unsaved changes, so pressing it again quits.
+	confirm_quit: bool,
+	quit: bool,
+}
+impl<S: ProofSystem> Tui<S> {
+	pub fn new(mut edit: Editor<S>, path: Option<String>) -> Tui<S> {
+		let modified = Rc::new(Cell::new(false));
+		{
+			let modified = modified.clone();
+			edit.on_change(move |c| if c.is_edit() {
+				modified.set(true);
+			});
+		}
+		Tui {
+			edit: edit,
+			path: path,
+			scroll: 0,
+			hscroll: 0,
+			message: None,
+			clipboard: None,
+			modified: modified,
+			confirm_quit: false,
+			quit: false,
+		}
+	}
+	
+	pub fn edit(&self) -> &Editor<S> {
+		&self.edit
+	}
+	
+	/// True once the user has asked to quit.
+	pub fn quit(&self) -> bool {
+		self.quit
+	}
+	
+	/// True if the proof has been edited since it was loaded or saved.
+	pub fn modified(&self) -> bool {
+		self.modified.get()
+	}
+	
+	/// Handles a key press.
+	pub fn key(&mut self, k: &KeyInput) {
+		self.message = None;
+		let quitting = mem::replace(&mut self.confirm_quit, false);
+		if k.ctrl && !k.alt {
+			match k.key {
+				Key::Char('q') => {
+					if self.modified() && !quitting {
+						self.confirm_quit = true;
+						self.message = Some("there are unsaved changes: press Ctrl+Q again to quit".to_string());
+					} else {
+						self.quit = true;
+					}
+					return;
+				},
+				Key::Char('s') => {
+					self.save();
+					return;
+				},
+				Key::Char('c') => {
+					if let Some(Change::Copy(text)) = self.edit.execute(EditorCommand::Copy) {
+						self.clipboard = Some(text);
+					}
+					return;
+				},
+				Key::Char('x') => {
+					if let Some(text) = self.edit.cut() {
+						self.clipboard = Some(text);
+					}
+					return;
+				},
+				Key::Char('v') => {
+					if let Some(text) = self.clipboard.clone() {
+						if let Err(e) = self.edit.paste(&text) {
+							self.message = Some(format!("paste: {}", e));
+						}
+					}
+					return;
+				},
+				_ => {},
+			}
+		}
+		self.edit.handle_key(k);
+	}
+	
+	/// Saves the proof to the file given on the command line.
+	pub fn save(&mut self) {
+		self.message = Some(match self.path {
+			Some(ref path) => match File::create(path).and_then(|mut f| f.write_all(self.edit.save().as_bytes())) {
+				Ok(()) => {
+					self.modified.set(false);
+					format!("saved {}", path)
+				},
+				Err(e) => format!("{}: {}", path, e),
+			},
+			None => "no file to save to: give one on the command line".to_string(),
+		});
+	}
+	
+	/// Lays out the screen for a terminal `width` columns wide and `height` rows high, scrolling so the line the
+	/// cursor is on can be seen. The last two rows are the status line and the message line.
+	pub fn view(&mut self, width: usize, height: usize) -> Screen {
+		let lines = self.edit.lines();
+		let (cur_no, cur_col, cur_i) = self.edit.cursor().position();
+		let sel = self.edit.selection();
+		let shown = cmp::max(height, 3) - 2;
+		if cur_no < self.scroll {
+			self.scroll = cur_no;
+		} else if cur_no >= self.scroll + shown {
+			self.scroll = cur_no + 1 - shown;
+		}
+		
+		// Every token is written as one character, so columns are measured in tokens
+		let num_w = lines.len().to_string().len();
+		let step_w = cmp::max(20, lines.iter().map(|l| l.step.len() + 1).max().unwrap_or(0));
+		let method_w = cmp::max(15, lines.iter().map(|l| l.method.len() + 1).max().unwrap_or(0));
+		let step_x = 1 + num_w + 2;
+		let method_x = step_x + step_w + 1;
+		let deps_x = method_x + method_w + 1;
+		let x = match cur_col {
+			Col::Step => step_x + cur_i,
+			Col::Method => method_x + cur_i,
+			Col::Deps => deps_x + 1 + cur_i,
+		};
+		// Scrolled sideways to keep the cursor on the screen
+		let shown_w = cmp::max(width, 1);
+		if x < self.hscroll {
+			self.hscroll = x;
+		} else if x >= self.hscroll + shown_w {
+			self.hscroll = x + 1 - shown_w;
+		}
+		
+		let diagnostics = self.edit.check();
+		let has_problem = |no: usize| diagnostics.iter().any(|d| d.no == no) || self.edit.broken_refs().contains(&no)
+			|| self.edit.dep_errors().iter().any(|&(n, _)| n == no) || self.edit.dep_conflicts().contains(&no);
+		let problems = (0..lines.len()).filter(|&no| has_problem(no)).count();
+		
+		let mut rows = Vec::with_capacity(height);
+		for (no, l) in lines.iter().enumerate().skip(self.scroll).take(shown) {
+			let whole_line = match sel {
+				Some(Selection::Lines(start, end)) => start <= no && no < end,
+				_ => false,
+			};
+			// The tokens the diagnostics for the line are about are drawn in red
+			let spans: Vec<(Col, usize, usize)> = diagnostics.iter().filter(|d| d.no == no).map(|d| d.span(lines)).collect();
+			let style_at = |col: Col, i: usize, error: bool| match sel {
+				_ if whole_line => Style::Selected,
+				Some(Selection::Tokens { no: n, col: c, start, end }) if n == no && c == col && start <= i && i < end => Style::Selected,
+				_ if error || spans.iter().any(|&(c, start, end)| c == col && start <= i && i < end) => Style::Error,
+				_ => Style::Normal,
+			};
+			let column = |row: &mut Vec<(Style, String)>, col: Col, toks: &[Token], w: usize, error: bool| {
+				for (i, t) in toks.iter().enumerate() {
+					push(row, style_at(col, i, error), &format!("{:#}", t));
+				}
+				let pad = w.saturating_sub(toks.len());
+				push(row, if whole_line { Style::Selected } else { Style::Normal }, &" ".repeat(pad));
+			};
+			
+			let mut row = Vec::new();
+			if has_problem(no) {
+				push(&mut row, Style::Error, "!");
+			} else {
+				push(&mut row, Style::Normal, " ");
+			}
+			push(&mut row, style_at(Col::Step, usize::max_value(), false), &format!("{: >1$}. ", no + 1, num_w));
+			column(&mut row, Col::Step, &l.step, step_w + 1, false);
+			column(&mut row, Col::Method, &l.method, method_w + 1, self.edit.broken_refs().contains(&no));
+			let deps_error = self.edit.dep_conflicts().contains(&no) || self.edit.dep_errors().iter().any(|&(n, _)| n == no)
+				|| spans.iter().any(|&(c, _, _)| c == Col::Deps);
+			push(&mut row, style_at(Col::Deps, usize::max_value(), deps_error), "{");
+			column(&mut row, Col::Deps, &l.deps_tokens(), 0, deps_error);
+			push(&mut row, style_at(Col::Deps, usize::max_value(), deps_error), "}");
+			rows.push(clip(row, self.hscroll, width));
+		}
+		while rows.len() < shown {
+			rows.push(Vec::new());
+		}
+		
+		let mut status = format!(" {}{}  {}  line {}/{}  ", self.path.as_ref().map(|p| &p[..]).unwrap_or("[no file]"),
+			if self.modified() { " [modified]" } else { "" }, self.edit.system().name(), cur_no + 1, lines.len());
+		status.push_str(&match problems {
+			0 => "no problems".to_string(),
+			1 => "1 line with problems".to_string(),
+			n => format!("{} lines with problems", n),
+		});
+		let pad = width.saturating_sub(status.chars().count());
+		status.push_str(&" ".repeat(pad));
+		rows.push(clip(vec![(Style::Status, status)], 0, width));
+		
+		let message = self.message.clone().or_else(|| self.edit.problem_with(cur_no, &diagnostics))
+			.unwrap_or("Ctrl+S save  Ctrl+Q quit  Ctrl+C copy  Ctrl+X cut  Ctrl+V paste".to_string());
+		rows.push(clip(vec![(Style::Normal, message)], 0, width));
+		
+		Screen {
+			rows: rows,
+			cursor: (x - self.hscroll, cur_no - self.scroll),
+		}
+	}
+}
+
+/// Translates a key read by `termion`, or returns `None` if the editor doesn't use it.
+fn key_input(k: TermKey) -> Option<KeyInput> {
+	Some(match k {
+		TermKey::Char('\n') => KeyInput::new(Key::Return),
+		TermKey::Char('\t') => KeyInput::new(Key::Tab),
+		TermKey::Char(c)    => KeyInput::new(Key::Char(c)),
+		TermKey::Ctrl(c)    => KeyInput::new(Key::Char(c)).with_ctrl(),
+		TermKey::Alt(c)     => KeyInput::new(Key::Char(c)).with_alt(),
+		TermKey::BackTab    => KeyInput::new(Key::BackTab),
+		TermKey::Backspace  => KeyInput::new(Key::Backspace),
+		TermKey::Delete     => KeyInput::new(Key::Delete),
+		TermKey::Left  => KeyInput::new(Key::Left),
+		TermKey::Right => KeyInput::new(Key::Right),
+		TermKey::Up    => KeyInput::new(Key::Up),
+		TermKey::Down  => KeyInput::new(Key::Down),
+		TermKey::Home  => KeyInput::new(Key::Home),
+		TermKey::End   => KeyInput::new(Key::End),
+		_ => return None,
+	})
+}
+
+/// Draws `screen` over what was on the terminal.
+fn draw<W: Write>(out: &mut W, screen: &Screen) -> io::Result<()> {
+	try!(write!(out, "{}", cursor::Hide));
+	for (y, row) in screen.rows.iter().enumerate() {
+		try!(write!(out, "{}{}", cursor::Goto(1, y as u16 + 1), clear::CurrentLine));
+		for &(s, ref text) in row.iter() {
+			try!(match s {
+				Style::Normal => write!(out, "{}", text),
+				Style::Selected | Style::Status => write!(out, "{}{}{}", style::Invert, text, style::Reset),
+				Style::Error => write!(out, "{}{}{}", color::Fg(color::Red), text, color::Fg(color::Reset)),
+			});
+		}
+	}
+	let (x, y) = screen.cursor;
+	try!(write!(out, "{}{}", cursor::Goto(x as u16 + 1, y as u16 + 1), cursor::Show));
+	out.flush()
+}
+
+/// Runs the terminal editor on the proof in the file at the first of `args`, if there is one, until the user
+/// quits. A file that doesn't exist yet is created when the proof is saved. Returns the exit code.
+pub fn run(args: &[String]) -> i32 {
+	if args.len() > 1 {
+		eprintln!("{}", cli::USAGE);
+		return ERROR;
+	}
+	let mut edit = match cli::env_system() {
+		Ok(sys) => Editor::with_system(sys),
+		Err(e) => {
+			eprintln!("{}", e);
+			return ERROR;
+		},
+	};
+	if let Err(e) = cli::env_rules(edit.library_mut()) {
+		eprintln!("{}", e);
+		return ERROR;
+	}
+	edit.update_deps();
+	let path = args.first().cloned();
+	if let Some(ref path) = path {
+		let mut text = String::new();
+		match File::open(path).and_then(|mut f| f.read_to_string(&mut text)) {
+			Ok(_) => if let Err(e) = edit.load(&text) {
+				eprintln!("{}: {}", path, e);
+				return ERROR;
+			},
+			Err(ref e) if e.kind() == ErrorKind::NotFound => {},
+			Err(e) => {
+				eprintln!("{}: {}", path, e);
+				return ERROR;
+			},
+		}
+	}
+	let mut tui = Tui::new(edit, path);
+	
+	let stdin = io::stdin();
+	let mut out = match io::stdout().into_raw_mode() {
+		Ok(out) => AlternateScreen::from(out),
+		Err(e) => {
+			eprintln!("{}", e);
+			return ERROR;
+		},
+	};
+	let mut keys = stdin.keys();
+	// The screen is redrawn after each key, which is when it can change, or the terminal be found to be resized
+	while !tui.quit() {
+		// Terminals that don't know their size say it is 0 by 0
+		let (w, h) = match termion::terminal_size() {
+			Ok((w, h)) if w > 0 && h > 0 => (w, h),
+			_ => (80, 24),
+		};
+		if draw(&mut out, &tui.view(w as usize, h as usize)).is_err() {
+			return ERROR;
+		}
+		match keys.next() {
+			Some(Ok(k)) => if let Some(k) = key_input(k) {
+				tui.key(&k);
+			},
+			_ => break,
+		}
+	}
+	SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+	use edit::Editor;
+	use input::{Key, KeyInput};
+	use super::*;
+	
+	#[test]
+	fn test_tui() {
+		let mut ed = Editor::new();
+		ed.load("{1}  1. P ; Premise\n{2}  2. Q ; Premise\n{1, 2}  3. P&Q ; &I 1, 2\n{1}  4. R ; &E 3\n").unwrap();
+		let mut tui = Tui::new(ed, None);
+		let screen = tui.view(60, 4);
+		for y in 0..screen.rows.len() {
+			println!("[test_tui] {:?}", screen.rows[y]);
+		}
+		assert_eq!(screen.rows.len(), 4);
+		assert_eq!(screen.text(0), " 1. P                    Premise         {1}");
+		assert_eq!(screen.cursor, (4, 0));
+		assert!(screen.text(2).starts_with(" [no file]  Lemmon  line 1/4  1 line with problems"));
+		
+		// Moving down to the wrong line scrolls to it, and shows what is wrong with it
+		for _ in 0..3 {
+			tui.key(&KeyInput::new(Key::Down));
+		}
+		tui.key(&KeyInput::new(Key::Tab));
+		let screen = tui.view(60, 4);
+		for y in 0..screen.rows.len() {
+			println!("[test_tui] {:?}", screen.rows[y]);
+		}
+		assert_eq!(screen.text(0), " 3. P∧Q                  ∧I 1, 2         {1, 2}");
+		assert_eq!(screen.rows[1][0], (Style::Error, "!".to_string()));
+		assert_eq!(screen.rows[1].last(), Some(&(Style::Error, "{1}".to_string())));
+		assert!(screen.text(3).starts_with("line 4: "));
+		assert_eq!(screen.cursor, (29, 1));
+		
+		// Quitting with unsaved changes asks first
+		tui.key(&KeyInput::new(Key::Char('x')));
+		assert!(tui.modified());
+		tui.key(&KeyInput::new(Key::Char('q')).with_ctrl());
+		assert!(!tui.quit());
+		tui.key(&KeyInput::new(Key::Char('q')).with_ctrl());
+		assert!(tui.quit());
+		
+		// A narrow terminal scrolls sideways to keep the cursor on it, and back again
+		let wide = tui.view(60, 4);
+		let screen = tui.view(20, 4);
+		println!("[test_tui] {:?}", screen.rows[1]);
+		assert_eq!(screen.cursor, (19, 1));
+		assert_eq!(screen.text(1), wide.text(1).chars().skip(11).take(20).collect::<String>());
+		assert!(screen.text(2).starts_with(" [no file] [mod"));
+		tui.key(&KeyInput::new(Key::Home));
+		assert_eq!(tui.view(20, 4).cursor, (14, 1));
+		tui.key(&KeyInput::new(Key::BackTab));
+		assert_eq!(tui.view(20, 4).cursor, (0, 1));
+	}
+}